@@ -0,0 +1,289 @@
+//!
+//! Derives for glust's `Shader`, `ShaderAttribs` and `ShaderUniforms` traits.
+//! Use them through glust with its `derive` feature.
+//!
+extern crate proc_macro;
+extern crate proc_macro2;
+extern crate syn;
+#[macro_use]
+extern crate quote;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use syn::{Data, DeriveInput, Fields, Ident, LitStr, Type};
+
+///
+/// Implements `glust::Shader` for a struct holding a single `glust::ShaderProgram`:
+///
+/// ```ignore
+/// #[derive(Shader)]
+/// #[shader(vertex = "shaders/mesh.vert", fragment = "shaders/mesh.frag",
+///          attribs = "MeshAttribs", uniforms = "MeshUniforms<'a>")]
+/// struct MeshShader(ShaderProgram);
+/// ```
+///
+/// Source paths are relative to the crate root and are embedded at compile
+/// time. The uniforms type may use the lifetime `'a` of `Shader<'a>`.
+///
+#[proc_macro_derive(Shader, attributes(shader))]
+pub fn derive_shader(input : TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as DeriveInput);
+    match impl_shader(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into()
+    }
+}
+
+///
+/// Implements `glust::ShaderAttribs` for a struct of vertex buffers. Fields
+/// are the attributes in location order and are named as in GLSL; a derived
+/// `Shader` binds attributes without `layout(location)` to their field's.
+///
+#[proc_macro_derive(ShaderAttribs)]
+pub fn derive_shader_attribs(input : TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as DeriveInput);
+    match impl_shader_attribs(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into()
+    }
+}
+
+///
+/// Implements `glust::ShaderUniforms` for a struct of uniform values. Fields
+/// are named as in GLSL, and their types implement `glust::ShaderUniformValue`.
+///
+#[proc_macro_derive(ShaderUniforms)]
+pub fn derive_shader_uniforms(input : TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as DeriveInput);
+    match impl_shader_uniforms(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into()
+    }
+}
+
+struct ShaderAttr {
+    vertex : LitStr,
+    fragment : LitStr,
+    attribs : Type,
+    uniforms : Type
+}
+
+fn parse_shader_attr(input : &DeriveInput) -> syn::Result<ShaderAttr> {
+    let mut vertex = None;
+    let mut fragment = None;
+    let mut attribs = None;
+    let mut uniforms = None;
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("shader")) {
+        attr.parse_nested_meta(|meta| {
+            let value : LitStr = meta.value()?.parse()?;
+            if meta.path.is_ident("vertex") {
+                vertex = Some(value);
+            } else if meta.path.is_ident("fragment") {
+                fragment = Some(value);
+            } else if meta.path.is_ident("attribs") {
+                attribs = Some(value.parse()?);
+            } else if meta.path.is_ident("uniforms") {
+                uniforms = Some(value.parse()?);
+            } else {
+                return Err(meta.error("expected vertex, fragment, attribs or uniforms"));
+            }
+            Ok(())
+        })?;
+    }
+
+    let missing = |name : &str| syn::Error::new_spanned(&input.ident, format!("missing `{}` in #[shader(...)]", name));
+    Ok(ShaderAttr {
+        vertex: vertex.ok_or_else(|| missing("vertex"))?,
+        fragment: fragment.ok_or_else(|| missing("fragment"))?,
+        attribs: attribs.ok_or_else(|| missing("attribs"))?,
+        uniforms: uniforms.ok_or_else(|| missing("uniforms"))?
+    })
+}
+
+fn impl_shader(input : &DeriveInput) -> syn::Result<TokenStream2> {
+    let attr = parse_shader_attr(input)?;
+    let name = &input.ident;
+    let name_str = name.to_string();
+
+    let (program, construct) = match input.data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) if fields.named.len() == 1 => {
+                let field = fields.named[0].ident.clone().unwrap();
+                (quote!(self.#field), quote!(#name { #field: ::glust::ShaderProgram::new(shader) }))
+            }
+            Fields::Unnamed(ref fields) if fields.unnamed.len() == 1 => {
+                (quote!(self.0), quote!(#name(::glust::ShaderProgram::new(shader))))
+            }
+            _ => return Err(syn::Error::new_spanned(name, "expected a struct with a single ShaderProgram field"))
+        },
+        _ => return Err(syn::Error::new_spanned(name, "expected a struct with a single ShaderProgram field"))
+    };
+
+    let vertex = &attr.vertex;
+    let fragment = &attr.fragment;
+    let attribs = &attr.attribs;
+    let uniforms = &attr.uniforms;
+
+    Ok(quote! {
+        impl<'a> ::glust::Shader<'a> for #name {
+            type Attribs = #attribs;
+            type Uniforms = #uniforms;
+
+            fn new() -> Self {
+                let vs = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/", #vertex));
+                let fs = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/", #fragment));
+                let shader = ::glust::GlShaderBuilder::new()
+                    .stage(::glust::GlShaderStage::Vertex, vs)
+                    .stage(::glust::GlShaderStage::Fragment, fs)
+                    .bind_attrib_locations(<#attribs as ::glust::ShaderAttribs>::attrib_names())
+                    .build()
+                    .unwrap_or_else(|err| panic!("Error compiling {}: {}", #name_str, err));
+                #construct
+            }
+
+            fn create_va(attribs : Self::Attribs) -> ::std::result::Result<::glust::GlVertexArray, ::glust::GlError> {
+                ::glust::GlVertexArray::new(::glust::ShaderAttribs::into_buffers(attribs))
+            }
+
+            fn render(&self,
+                      vertex_array : &::glust::GlVertexArray,
+                      rendertarget : &::glust::RenderTarget,
+                      uniforms : Self::Uniforms) -> ::std::result::Result<(), ::glust::GlError> {
+                #program.render(vertex_array, rendertarget,
+                                <#attribs as ::glust::ShaderAttribs>::attrib_names(),
+                                &::glust::ShaderUniforms::to_uniforms(&uniforms))
+            }
+
+            fn render_indexed(&self,
+                              vertex_array : &::glust::GlVertexArray,
+                              index_buffer : &::glust::GlBufferRaw,
+                              rendertarget : &::glust::RenderTarget,
+                              uniforms : Self::Uniforms) -> ::std::result::Result<(), ::glust::GlError> {
+                #program.render_indexed(vertex_array, index_buffer, rendertarget,
+                                        <#attribs as ::glust::ShaderAttribs>::attrib_names(),
+                                        &::glust::ShaderUniforms::to_uniforms(&uniforms))
+            }
+        }
+    })
+}
+
+fn named_fields<'a>(input : &'a DeriveInput, what : &str) -> syn::Result<Vec<&'a Ident>> {
+    match input.data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => Ok(fields.named.iter().map(|f| f.ident.as_ref().unwrap()).collect()),
+            _ => Err(syn::Error::new_spanned(&input.ident, format!("{} must be a struct with named fields", what)))
+        },
+        _ => Err(syn::Error::new_spanned(&input.ident, format!("{} must be a struct with named fields", what)))
+    }
+}
+
+///
+/// GLSL name of a field; a leading `r#` of raw identifiers is dropped.
+///
+fn glsl_name(field : &Ident) -> String {
+    let name = field.to_string();
+    name.strip_prefix("r#").map(str::to_string).unwrap_or(name)
+}
+
+fn impl_shader_attribs(input : &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let fields = named_fields(input, "ShaderAttribs")?;
+    let names : Vec<_> = fields.iter().map(|f| glsl_name(f)).collect();
+
+    Ok(quote! {
+        impl ::glust::ShaderAttribs for #name {
+            fn attrib_names() -> &'static [&'static str] {
+                &[#(#names),*]
+            }
+
+            fn into_buffers(self) -> Vec<::glust::GlBufferRaw> {
+                vec![#(::glust::GlBufferRaw::from(self.#fields)),*]
+            }
+        }
+    })
+}
+
+fn impl_shader_uniforms(input : &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let fields = named_fields(input, "ShaderUniforms")?;
+    let names : Vec<_> = fields.iter().map(|f| glsl_name(f)).collect();
+
+    let lifetimes : Vec<_> = input.generics.lifetimes().collect();
+    let body = quote! {
+        #(( #names, ::glust::ShaderUniformValue::to_uniform(&self.#fields) )),*
+    };
+    Ok(match lifetimes.len() {
+        0 => quote! {
+            impl<'a> ::glust::ShaderUniforms<'a> for #name {
+                fn to_uniforms(&self) -> Vec<(&'static str, ::glust::GlShaderUniform<'a>)> {
+                    vec![#body]
+                }
+            }
+        },
+        1 => {
+            let lifetime = &lifetimes[0].lifetime;
+            quote! {
+                impl<#lifetime> ::glust::ShaderUniforms<#lifetime> for #name<#lifetime> {
+                    fn to_uniforms(&self) -> Vec<(&'static str, ::glust::GlShaderUniform<#lifetime>)> {
+                        vec![#body]
+                    }
+                }
+            }
+        }
+        _ => return Err(syn::Error::new_spanned(&input.generics, "ShaderUniforms supports at most one lifetime"))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error_of(result : syn::Result<TokenStream2>) -> String {
+        match result {
+            Ok(tokens) => panic!("expected an error, got {}", tokens),
+            Err(err) => err.to_string()
+        }
+    }
+
+    #[test]
+    fn glsl_names_drop_raw_prefixes() {
+        let input : DeriveInput = syn::parse_str("struct A { r#type : f32, plain : f32 }").unwrap();
+        let names : Vec<_> = named_fields(&input, "A").unwrap().into_iter().map(glsl_name).collect();
+        assert_eq!(names, vec!["type", "plain"]);
+    }
+
+    #[test]
+    fn shader_needs_every_key() {
+        let input : DeriveInput = syn::parse_str(
+            "#[shader(vertex = \"a.vert\", fragment = \"a.frag\", attribs = \"A\")] struct S(ShaderProgram);").unwrap();
+        assert_eq!(error_of(impl_shader(&input)), "missing `uniforms` in #[shader(...)]");
+        let input : DeriveInput = syn::parse_str(
+            "#[shader(vertex = \"a.vert\", geometry = \"a.geom\")] struct S(ShaderProgram);").unwrap();
+        assert_eq!(error_of(impl_shader(&input)), "expected vertex, fragment, attribs or uniforms");
+    }
+
+    #[test]
+    fn shader_needs_a_single_field_struct() {
+        let attr = "#[shader(vertex = \"a.vert\", fragment = \"a.frag\", attribs = \"A\", uniforms = \"U\")]";
+        for item in &["enum S { A }", "struct S(ShaderProgram, u32);", "struct S;"] {
+            let input : DeriveInput = syn::parse_str(&format!("{} {}", attr, item)).unwrap();
+            assert_eq!(error_of(impl_shader(&input)), "expected a struct with a single ShaderProgram field");
+        }
+        let input : DeriveInput = syn::parse_str(&format!("{} struct S {{ program : ShaderProgram }}", attr)).unwrap();
+        assert!(impl_shader(&input).is_ok());
+    }
+
+    #[test]
+    fn attribs_and_uniforms_need_named_fields() {
+        let input : DeriveInput = syn::parse_str("struct A(GlBuffer<f32>);").unwrap();
+        assert_eq!(error_of(impl_shader_attribs(&input)), "ShaderAttribs must be a struct with named fields");
+        let input : DeriveInput = syn::parse_str("enum U { A }").unwrap();
+        assert_eq!(error_of(impl_shader_uniforms(&input)), "ShaderUniforms must be a struct with named fields");
+    }
+
+    #[test]
+    fn uniforms_take_at_most_one_lifetime() {
+        let input : DeriveInput = syn::parse_str("struct U<'a, 'b> { a : &'a [f32], b : &'b [f32] }").unwrap();
+        assert_eq!(error_of(impl_shader_uniforms(&input)), "ShaderUniforms supports at most one lifetime");
+    }
+}
@@ -2,6 +2,7 @@ extern crate gl;
 
 use gl::types::*;
 use std::mem;
+use std::ptr;
 use std::os::raw::c_void;
 use gl_err::*;
 use std::marker::PhantomData;
@@ -63,20 +64,54 @@ impl GlBufferElementType for [f32;4] {
     fn components_per_element() -> usize { 4 }
 }
 
+///
+/// Size in bytes of a single component of the given GL type.
+///
+pub fn gl_type_size(gl_type_enum : GLenum) -> usize {
+    match gl_type_enum {
+        gl::BYTE | gl::UNSIGNED_BYTE => 1,
+        gl::SHORT | gl::UNSIGNED_SHORT | gl::HALF_FLOAT => 2,
+        gl::DOUBLE => 8,
+        _ => 4
+    }
+}
+
+///
+/// Whether the given GL type is an integer type, which vertex arrays feed to
+/// `int`/`uint` based inputs unconverted unless normalized.
+///
+pub fn gl_type_is_integer(gl_type_enum : GLenum) -> bool {
+    matches!(gl_type_enum, gl::BYTE | gl::UNSIGNED_BYTE | gl::SHORT | gl::UNSIGNED_SHORT | gl::INT | gl::UNSIGNED_INT)
+}
+
 impl GlBufferRaw {
     pub fn new<T: GlBufferElementType>(data : &[T], component_count : usize) -> Result<GlBufferRaw> {
         unsafe {
             Self::new_impl_raw(data.as_ptr() as *const c_void,
                                (mem::size_of::<T>() * data.len()) as isize,
                                (T::components_per_element() as usize) * data.len() / component_count,
-                               component_count, gl::ARRAY_BUFFER, T::gl_type_enum())
+                               component_count, gl::ARRAY_BUFFER, T::gl_type_enum(), gl::STATIC_DRAW)
+        }
+    }
+
+    ///
+    /// Uninitialized buffer of `element_count` elements of `T`, e.g. as a
+    /// transform feedback target.
+    ///
+    pub fn new_empty<T: GlBufferElementType>(element_count : usize, component_count : usize) -> Result<GlBufferRaw> {
+        unsafe {
+            Self::new_impl_raw(ptr::null(),
+                               (mem::size_of::<T>() * element_count) as isize,
+                               T::components_per_element() * element_count / component_count,
+                               component_count, gl::ARRAY_BUFFER, T::gl_type_enum(), gl::DYNAMIC_COPY)
         }
     }
     pub fn new_index<T: GlBufferElementType>(data : &[T]) -> Result<GlBufferRaw> {
         unsafe {
             Self::new_impl_raw(data.as_ptr() as *const c_void,
                          (mem::size_of::<T>() * data.len()) as isize,
-                         data.len(), T::components_per_element(), gl::ELEMENT_ARRAY_BUFFER, T::gl_type_enum())
+                         data.len(), T::components_per_element(), gl::ELEMENT_ARRAY_BUFFER, T::gl_type_enum(),
+                         gl::STATIC_DRAW)
         }
     }
 
@@ -85,11 +120,12 @@ impl GlBufferRaw {
                            element_count : usize,
                            component_count : usize,
                            buffer_type : GLenum,
-                           buffer_element_type : GLenum) -> Result<GlBufferRaw> {
+                           buffer_element_type : GLenum,
+                           usage : GLenum) -> Result<GlBufferRaw> {
         let mut buffer_id : GLuint = 0;
         gl::GenBuffers(1, &mut buffer_id);
         gl::BindBuffer(buffer_type, buffer_id);
-        gl::BufferData(buffer_type, data_size, data, gl::STATIC_DRAW);
+        gl::BufferData(buffer_type, data_size, data, usage);
 
         match validate_gl() {
             Err(s) => Err(s),
@@ -109,6 +145,16 @@ impl<T> GlBuffer<T> where T : GlBufferElementType {
     pub fn new(data : &[T]) -> Result<GlBuffer<T>> {
         Ok(GlBuffer::<T>(GlBufferRaw::new(data, T::components_per_element())?, PhantomData::<T>))
     }
+
+    pub fn new_empty(element_count : usize) -> Result<GlBuffer<T>> {
+        Ok(GlBuffer::<T>(GlBufferRaw::new_empty::<T>(element_count, T::components_per_element())?, PhantomData::<T>))
+    }
+}
+
+impl<T> From<GlBuffer<T>> for GlBufferRaw where T : GlBufferElementType {
+    fn from(buffer : GlBuffer<T>) -> GlBufferRaw {
+        buffer.0
+    }
 }
 
 impl<T> GlIndexBuffer<T> where T : GlBufferElementType {
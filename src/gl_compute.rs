@@ -0,0 +1,101 @@
+extern crate gl;
+use gl::types::*;
+use gl_err::*;
+use gl_buffer::GlBufferRaw;
+use gl_shaders::GlShader;
+use gl_shaders::GlShaderUniform;
+use gl_shader_builder::GlShaderBuilder;
+use gl_shader_builder::GlShaderStage;
+use gl_render::apply_uniforms;
+
+use std::ops::BitOr;
+
+///
+/// Compute shader program. Resources are bound the same way as for draws:
+/// storage buffers, image textures and plain uniforms go in `uniforms`.
+///
+pub struct GlComputeShader {
+    pub shader : GlShader,
+    pub work_group_size : [i32;3]
+}
+
+///
+/// Barrier bits for `memory_barrier`. Combine with `|`.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlMemoryBarrier(pub GLbitfield);
+
+impl GlMemoryBarrier {
+    pub const VERTEX_ATTRIB_ARRAY : GlMemoryBarrier = GlMemoryBarrier(gl::VERTEX_ATTRIB_ARRAY_BARRIER_BIT);
+    pub const ELEMENT_ARRAY : GlMemoryBarrier = GlMemoryBarrier(gl::ELEMENT_ARRAY_BARRIER_BIT);
+    pub const UNIFORM : GlMemoryBarrier = GlMemoryBarrier(gl::UNIFORM_BARRIER_BIT);
+    pub const TEXTURE_FETCH : GlMemoryBarrier = GlMemoryBarrier(gl::TEXTURE_FETCH_BARRIER_BIT);
+    pub const SHADER_IMAGE_ACCESS : GlMemoryBarrier = GlMemoryBarrier(gl::SHADER_IMAGE_ACCESS_BARRIER_BIT);
+    pub const COMMAND : GlMemoryBarrier = GlMemoryBarrier(gl::COMMAND_BARRIER_BIT);
+    pub const PIXEL_BUFFER : GlMemoryBarrier = GlMemoryBarrier(gl::PIXEL_BUFFER_BARRIER_BIT);
+    pub const TEXTURE_UPDATE : GlMemoryBarrier = GlMemoryBarrier(gl::TEXTURE_UPDATE_BARRIER_BIT);
+    pub const BUFFER_UPDATE : GlMemoryBarrier = GlMemoryBarrier(gl::BUFFER_UPDATE_BARRIER_BIT);
+    pub const FRAMEBUFFER : GlMemoryBarrier = GlMemoryBarrier(gl::FRAMEBUFFER_BARRIER_BIT);
+    pub const TRANSFORM_FEEDBACK : GlMemoryBarrier = GlMemoryBarrier(gl::TRANSFORM_FEEDBACK_BARRIER_BIT);
+    pub const ATOMIC_COUNTER : GlMemoryBarrier = GlMemoryBarrier(gl::ATOMIC_COUNTER_BARRIER_BIT);
+    pub const SHADER_STORAGE : GlMemoryBarrier = GlMemoryBarrier(gl::SHADER_STORAGE_BARRIER_BIT);
+    pub const CLIENT_MAPPED_BUFFER : GlMemoryBarrier = GlMemoryBarrier(gl::CLIENT_MAPPED_BUFFER_BARRIER_BIT);
+    pub const QUERY_BUFFER : GlMemoryBarrier = GlMemoryBarrier(gl::QUERY_BUFFER_BARRIER_BIT);
+    pub const ALL : GlMemoryBarrier = GlMemoryBarrier(gl::ALL_BARRIER_BITS);
+}
+
+impl BitOr for GlMemoryBarrier {
+    type Output = GlMemoryBarrier;
+    fn bitor(self, other : GlMemoryBarrier) -> GlMemoryBarrier {
+        GlMemoryBarrier(self.0 | other.0)
+    }
+}
+
+pub fn memory_barrier(barriers : GlMemoryBarrier) -> Result<()> {
+    unsafe {
+        gl::MemoryBarrier(barriers.0);
+    }
+    validate_gl()
+}
+
+impl GlComputeShader {
+    pub fn compile(source : &str) -> Result<GlComputeShader> {
+        let shader = GlShaderBuilder::new()
+            .stage(GlShaderStage::Compute, source)
+            .build()?;
+
+        let mut work_group_size = [0i32;3];
+        unsafe {
+            gl::GetProgramiv(shader.program_handle, gl::COMPUTE_WORK_GROUP_SIZE, work_group_size.as_mut_ptr());
+        }
+        validate_gl()?;
+
+        Ok(GlComputeShader {
+            shader: shader,
+            work_group_size: work_group_size
+        })
+    }
+
+    pub fn dispatch(&self, x : u32, y : u32, z : u32, uniforms : &[(&str, GlShaderUniform)]) -> Result<()> {
+        apply_uniforms(&self.shader, uniforms)?;
+        unsafe {
+            gl::UseProgram(self.shader.program_handle);
+            gl::DispatchCompute(x, y, z);
+        }
+        validate_gl()
+    }
+
+    ///
+    /// Dispatches with the group counts read from `buffer` at byte `offset`,
+    /// laid out as three consecutive GLuints.
+    ///
+    pub fn dispatch_indirect(&self, buffer : &GlBufferRaw, offset : usize, uniforms : &[(&str, GlShaderUniform)]) -> Result<()> {
+        apply_uniforms(&self.shader, uniforms)?;
+        unsafe {
+            gl::UseProgram(self.shader.program_handle);
+            gl::BindBuffer(gl::DISPATCH_INDIRECT_BUFFER, buffer.buffer_id);
+            gl::DispatchComputeIndirect(offset as GLintptr);
+        }
+        validate_gl()
+    }
+}
@@ -71,3 +71,10 @@ impl From<std::ffi::NulError> for GlError {
 
 
 
+
+#[cfg(feature="images")]
+impl From<::image::ImageError> for GlError {
+    fn from(err : ::image::ImageError) -> GlError {
+        GlError(err.to_string())
+    }
+}
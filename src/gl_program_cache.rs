@@ -0,0 +1,211 @@
+extern crate gl;
+use gl::types::*;
+use gl_err::*;
+
+use std::ffi::CStr;
+use std::fs;
+use std::os::raw::c_void;
+use std::path::PathBuf;
+
+///
+/// On-disk cache of linked program binaries (glGetProgramBinary). Entries are
+/// keyed by the program sources and the driver vendor, renderer and version,
+/// so a driver update invalidates them.
+///
+pub struct GlProgramCache {
+    pub dir : PathBuf
+}
+
+///
+/// Key of a cache entry. `name` is a hash of `text`, used as the file name;
+/// the full text is stored in the entry and compared on load, so entries with
+/// colliding hashes are misses rather than the wrong program.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct GlProgramCacheKey {
+    pub name : String,
+    pub text : String
+}
+
+impl GlProgramCache {
+    pub fn new<P: Into<PathBuf>>(dir : P) -> GlProgramCache {
+        GlProgramCache { dir: dir.into() }
+    }
+
+    ///
+    /// Cache key of a program described by `text`, which should contain
+    /// everything that affects the linked binary.
+    ///
+    pub fn key(&self, text : &str) -> GlProgramCacheKey {
+        let driver : Vec<String> = [gl::VENDOR, gl::RENDERER, gl::VERSION].iter().map(|&name| gl_string(name)).collect();
+        key_with_driver(text, &driver)
+    }
+
+    ///
+    /// Creates a program from the cached binary. Returns `None` if there is no
+    /// entry or the driver rejects it, in which case the entry is removed.
+    ///
+    pub fn load(&self, key : &GlProgramCacheKey) -> Option<GLuint> {
+        self.load_with_parameters(key, &[])
+    }
+
+    ///
+    /// Like `load`, but sets program parameters such as GL_PROGRAM_SEPARABLE
+    /// before the binary is loaded, as they are with a compiled program.
+    ///
+    pub fn load_with_parameters(&self, key : &GlProgramCacheKey, parameters : &[(GLenum, GLint)]) -> Option<GLuint> {
+        let path = self.entry_path(key);
+        let data = fs::read(&path).ok()?;
+        let (format, binary) = decode_entry(&data, &key.text)?;
+
+        unsafe {
+            let program_id = gl::CreateProgram();
+            for &(name, value) in parameters {
+                gl::ProgramParameteri(program_id, name, value);
+            }
+            gl::ProgramBinary(program_id, format, binary.as_ptr() as *const c_void, binary.len() as GLsizei);
+            let mut link_result : GLint = 0;
+            gl::GetProgramiv(program_id, gl::LINK_STATUS, &mut link_result);
+            if link_result == 0 || validate_gl().is_err() {
+                gl::DeleteProgram(program_id);
+                let _ = fs::remove_file(&path);
+                return None;
+            }
+            Some(program_id)
+        }
+    }
+
+    ///
+    /// Writes the binary of a linked program. The program should have been
+    /// linked with GL_PROGRAM_BINARY_RETRIEVABLE_HINT set. Failures are ignored,
+    /// as the cache only speeds up later builds.
+    ///
+    pub fn store(&self, key : &GlProgramCacheKey, program_id : GLuint) {
+        let mut length : GLint = 0;
+        unsafe {
+            gl::GetProgramiv(program_id, gl::PROGRAM_BINARY_LENGTH, &mut length);
+        }
+        if length <= 0 {
+            return;
+        }
+
+        let mut binary = vec![0u8; length as usize];
+        let mut format : GLenum = 0;
+        let mut written : GLsizei = 0;
+        unsafe {
+            gl::GetProgramBinary(program_id, length, &mut written, &mut format, binary.as_mut_ptr() as *mut c_void);
+        }
+        if validate_gl().is_err() || written <= 0 {
+            return;
+        }
+        binary.truncate(written as usize);
+
+        if fs::create_dir_all(&self.dir).is_ok() {
+            let _ = fs::write(self.entry_path(key), encode_entry(format, &key.text, &binary));
+        }
+    }
+
+    fn entry_path(&self, key : &GlProgramCacheKey) -> PathBuf {
+        self.dir.join(format!("{}.bin", key.name))
+    }
+}
+
+///
+/// Key of `text` with the driver strings appended, each after a NUL so that
+/// e.g. vendor "AB" and renderer "C" differ from vendor "A" and renderer "BC".
+///
+fn key_with_driver(text : &str, driver : &[String]) -> GlProgramCacheKey {
+    let mut full_text = text.to_string();
+    for s in driver {
+        full_text.push('\0');
+        full_text.push_str(s);
+    }
+    GlProgramCacheKey {
+        name: format!("{:016x}", fnv1a(FNV_OFFSET_BASIS, full_text.as_bytes())),
+        text: full_text
+    }
+}
+
+///
+/// Entry file: the binary format and the key text length as little endian
+/// u32s, the key text, then the binary.
+///
+fn encode_entry(format : GLenum, text : &str, binary : &[u8]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(8 + text.len() + binary.len());
+    data.extend_from_slice(&format.to_le_bytes());
+    data.extend_from_slice(&(text.len() as u32).to_le_bytes());
+    data.extend_from_slice(text.as_bytes());
+    data.extend_from_slice(binary);
+    data
+}
+
+///
+/// The format and binary of an entry, if it was stored for `text`.
+///
+fn decode_entry<'a>(data : &'a [u8], text : &str) -> Option<(GLenum, &'a [u8])> {
+    if data.len() < 8 {
+        return None;
+    }
+    let format = u32::from_le_bytes([data[0], data[1], data[2], data[3]]);
+    let text_len = u32::from_le_bytes([data[4], data[5], data[6], data[7]]) as usize;
+    let rest = &data[8..];
+    if text_len != text.len() || rest.len() <= text_len || &rest[..text_len] != text.as_bytes() {
+        return None;
+    }
+    Some((format, &rest[text_len..]))
+}
+
+const FNV_OFFSET_BASIS : u64 = 0xcbf2_9ce4_8422_2325;
+
+///
+/// FNV-1a, used instead of the std hasher whose output may change between
+/// Rust releases, which would silently invalidate the cache.
+///
+fn fnv1a(mut hash : u64, bytes : &[u8]) -> u64 {
+    for &b in bytes {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+///
+/// A driver string such as GL_VERSION, empty if there is none.
+///
+pub fn gl_string(name : GLenum) -> String {
+    unsafe {
+        let s = gl::GetString(name);
+        if s.is_null() {
+            String::new()
+        } else {
+            CStr::from_ptr(s as *const _).to_string_lossy().into_owned()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn driver(strings : &[&str]) -> Vec<String> {
+        strings.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn driver_strings_are_delimited() {
+        let a = key_with_driver("program", &driver(&["AB", "C", "4.6"]));
+        let b = key_with_driver("program", &driver(&["A", "BC", "4.6"]));
+        assert_ne!(a.text, b.text);
+        assert_ne!(a.name, b.name);
+    }
+
+    #[test]
+    fn entries_only_load_for_their_key_text() {
+        let data = encode_entry(0x1234, "program\0vendor", &[1, 2, 3]);
+        assert_eq!(decode_entry(&data, "program\0vendor"), Some((0x1234, &[1u8, 2, 3][..])));
+        assert_eq!(decode_entry(&data, "other\0\0vendor"), None);
+        assert_eq!(decode_entry(&data, "program"), None);
+        assert_eq!(decode_entry(&data[..10], "program\0vendor"), None);
+        assert_eq!(decode_entry(&encode_entry(0x1234, "program", &[]), "program"), None);
+    }
+}
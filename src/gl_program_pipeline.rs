@@ -0,0 +1,129 @@
+extern crate gl;
+use gl::types::*;
+use gl_err::*;
+use gl_shaders::GlShader;
+use gl_shader_builder::GlShaderStage;
+use gl_render::GlRenderProgram;
+
+use std::ptr;
+use std::rc::Rc;
+
+///
+/// Program pipeline combining stages of separable programs, built with
+/// `GlShaderBuilder::separable`. A stage program can be shared by any number
+/// of pipelines, e.g. one fullscreen vertex stage for many fragment stages:
+///
+/// ```ignore
+/// let mut pipeline = GlProgramPipeline::new()?;
+/// pipeline.use_stages(&[GlShaderStage::Vertex], &fullscreen_vs)?;
+/// pipeline.use_stages(&[GlShaderStage::Fragment], &blur_fs)?;
+/// render(&pipeline, &va, va.vertex_count, &target, &uniforms)?;
+/// ```
+///
+/// Uniforms given to a draw are set in every stage program that has them.
+///
+pub struct GlProgramPipeline {
+    pub pipeline_handle : GLuint,
+    programs : Vec<(GLbitfield, Rc<GlShader>)>
+}
+
+impl GlProgramPipeline {
+    pub fn new() -> Result<GlProgramPipeline> {
+        let mut pipeline_handle : GLuint = 0;
+        unsafe {
+            gl::GenProgramPipelines(1, &mut pipeline_handle);
+        }
+        validate_gl()?;
+        Ok(GlProgramPipeline {
+            pipeline_handle: pipeline_handle,
+            programs: Vec::new()
+        })
+    }
+
+    ///
+    /// Uses `program` for `stages`, replacing the programs used for them so far.
+    ///
+    pub fn use_stages(&mut self, stages : &[GlShaderStage], program : &Rc<GlShader>) -> Result<()> {
+        let mut separable : GLint = 0;
+        unsafe {
+            gl::GetProgramiv(program.program_handle, gl::PROGRAM_SEPARABLE, &mut separable);
+        }
+        if separable == 0 {
+            return Err(GlError::new("Program isn't separable; build it with GlShaderBuilder::separable".to_string()));
+        }
+
+        let bits = stages.iter().fold(0, |bits, stage| bits | stage.gl_bit());
+        unsafe {
+            gl::UseProgramStages(self.pipeline_handle, bits, program.program_handle);
+        }
+        validate_gl()?;
+
+        for &mut (ref mut program_bits, _) in &mut self.programs {
+            *program_bits &= !bits;
+        }
+        self.programs.retain(|&(program_bits, _)| program_bits != 0);
+        self.programs.push((bits, program.clone()));
+        Ok(())
+    }
+
+    ///
+    /// Programs of the pipeline, with the stages each one is used for.
+    ///
+    pub fn stage_programs(&self) -> Vec<(Vec<GlShaderStage>, &GlShader)> {
+        let all = [GlShaderStage::Vertex, GlShaderStage::TessControl, GlShaderStage::TessEvaluation,
+                   GlShaderStage::Geometry, GlShaderStage::Fragment, GlShaderStage::Compute];
+        self.programs.iter()
+            .map(|&(bits, ref program)| {
+                let stages = all.iter().cloned().filter(|stage| bits & stage.gl_bit() != 0).collect();
+                (stages, &**program)
+            })
+            .collect()
+    }
+
+    ///
+    /// Checks that the stages can be used together, e.g. that their interfaces
+    /// match. Returns the driver's log on failure.
+    ///
+    pub fn validate(&self) -> Result<()> {
+        let mut status : GLint = 0;
+        let mut log_length : GLint = 0;
+        unsafe {
+            gl::ValidateProgramPipeline(self.pipeline_handle);
+            gl::GetProgramPipelineiv(self.pipeline_handle, gl::VALIDATE_STATUS, &mut status);
+            gl::GetProgramPipelineiv(self.pipeline_handle, gl::INFO_LOG_LENGTH, &mut log_length);
+        }
+        validate_gl()?;
+        if status != 0 {
+            return Ok(());
+        }
+
+        let mut log = vec![0 as GLchar; log_length.max(1) as usize];
+        unsafe {
+            gl::GetProgramPipelineInfoLog(self.pipeline_handle, log.len() as GLsizei, ptr::null_mut(), log.as_mut_ptr());
+        }
+        let log : Vec<u8> = log.iter().take_while(|&&c| c != 0).map(|&c| c as u8).collect();
+        Err(GlError::new(format!("Program pipeline validation failed: {}", String::from_utf8(log)?)))
+    }
+}
+
+impl GlRenderProgram for GlProgramPipeline {
+    fn programs(&self) -> Vec<&GlShader> {
+        self.programs.iter().map(|&(_, ref program)| &**program).collect()
+    }
+
+    fn bind(&self) {
+        unsafe {
+            // A current program would take precedence over the pipeline.
+            gl::UseProgram(0);
+            gl::BindProgramPipeline(self.pipeline_handle);
+        }
+    }
+}
+
+impl Drop for GlProgramPipeline {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteProgramPipelines(1, &self.pipeline_handle);
+        }
+    }
+}
@@ -0,0 +1,291 @@
+extern crate gl;
+use gl::types::*;
+use gl_err::*;
+use gl_shaders::GlShader;
+use glsl_types::GlslType;
+
+use std::ptr;
+
+#[derive(Debug)]
+pub struct UniformInfo {
+    pub name : String,
+    pub datatype : GlslType,
+    pub size : i32,
+    pub location : i32
+}
+
+#[derive(Debug)]
+pub struct AttribInfo {
+    pub name : String,
+    pub datatype : GlslType,
+    pub size : i32,
+    pub location : i32
+}
+
+///
+/// Fragment shader output. `index` is the dual-source blending index, 0 unless
+/// the output was bound with glBindFragDataLocationIndexed or `layout(index)`.
+///
+#[derive(Debug)]
+pub struct FragOutputInfo {
+    pub name : String,
+    pub datatype : GlslType,
+    pub location : i32,
+    pub index : GLuint
+}
+
+///
+/// Input or output variable of a program's first or last stage. Arrays, such
+/// as the per-vertex inputs of geometry shaders, have an `array_size` above 1.
+///
+#[derive(Debug)]
+pub struct InterfaceVariableInfo {
+    pub name : String,
+    pub datatype : GlslType,
+    pub array_size : i32
+}
+
+///
+/// Uniform block or shader storage block, with its members sorted by offset.
+///
+#[derive(Debug)]
+pub struct BlockInfo {
+    pub name : String,
+    pub binding : GLuint,
+    pub data_size : usize,
+    pub members : Vec<BlockMemberInfo>
+}
+
+///
+/// Member of a block. Strides are 0 for members that aren't arrays or matrices.
+///
+#[derive(Debug)]
+pub struct BlockMemberInfo {
+    pub name : String,
+    pub datatype : GlslType,
+    pub offset : usize,
+    pub array_size : i32,
+    pub array_stride : i32,
+    pub matrix_stride : i32,
+    pub row_major : bool
+}
+
+impl GlShader {
+
+    pub fn get_uniform_infos(&self) -> Result<Vec<UniformInfo>> {
+        let num_uniforms = self.get_number_of_uniforms()?;
+        let mut ret = Vec::with_capacity(num_uniforms as usize);
+        for i in 0..num_uniforms {
+            unsafe {
+                let mut uniform_name_buf = Vec::with_capacity(1024 as usize);
+                for _ in 1..1024 { uniform_name_buf.push(0); }
+                let mut uniform_name_len : i32 = 0;
+                let mut uniform_size : i32 = 0;
+                let mut uniform_type : GLenum = 0 as GLenum;
+                gl::GetActiveUniform(self.program_handle, i, 1024, &mut uniform_name_len, &mut uniform_size, &mut uniform_type,
+                                     uniform_name_buf.as_mut_ptr());
+                uniform_name_buf.resize(uniform_name_len as usize, 0);
+                uniform_name_buf.push(0);
+                let location = gl::GetUniformLocation(self.program_handle, uniform_name_buf.as_ptr());
+                uniform_name_buf.pop();
+                let name_str = String::from_utf8(uniform_name_buf.iter().map(|&c| c as u8).collect())?;
+                ret.push(UniformInfo {
+                    name: name_str,
+                    datatype: GlslType::from_gl(uniform_type),
+                    size: uniform_size,
+                    location: location
+                });
+            }
+        }
+        Ok(ret)
+    }
+
+    ///
+    /// Active sampler uniforms, i.e. the uniforms textures are bound to.
+    ///
+    pub fn get_sampler_infos(&self) -> Result<Vec<UniformInfo>> {
+        Ok(self.get_uniform_infos()?.into_iter().filter(|u| u.datatype.is_sampler()).collect())
+    }
+
+    fn get_number_of_uniforms(&self) -> Result<u32>
+     {
+        unsafe {
+            let mut num_uniforms : GLint = 0;
+            gl::GetProgramiv(self.program_handle, gl::ACTIVE_UNIFORMS, &mut num_uniforms);
+            validate_gl()?;
+            Ok(num_uniforms as u32)
+        }
+    }
+
+    pub fn get_attrib_infos_sorted(&self) -> Result<Vec<AttribInfo>> {
+        let num_attribs = self.get_number_of_attributes()?;
+        let mut ret = Vec::with_capacity(num_attribs as usize);
+        for i in 0..num_attribs {
+            unsafe {
+                let mut attrib_name_buf = Vec::with_capacity(1024 as usize);
+                for _ in 1..1024 { attrib_name_buf.push(0); }
+                let mut attrib_name_len : i32 = 0;
+                let mut attrib_size : i32 = 0;
+                let mut attrib_type : GLenum = 0 as GLenum;
+                gl::GetActiveAttrib(self.program_handle, i, 1024, &mut attrib_name_len, &mut attrib_size, &mut attrib_type,
+                                     attrib_name_buf.as_mut_ptr());
+                attrib_name_buf.resize(attrib_name_len as usize, 0);
+                attrib_name_buf.push(0);
+                let location = gl::GetAttribLocation(self.program_handle, attrib_name_buf.as_ptr());
+                attrib_name_buf.pop();
+                let name_str = String::from_utf8(attrib_name_buf.iter().map(|&c| c as u8).collect())?;
+                ret.push(AttribInfo {
+                    name: name_str,
+                    datatype: GlslType::from_gl(attrib_type),
+                    size: attrib_size,
+                    location: location
+                });
+            }
+        }
+
+        ret.sort_by(|a,b| a.location.cmp(&b.location) );
+
+        Ok(ret)
+    }
+
+    fn get_number_of_attributes(&self) -> Result<u32> {
+        unsafe {
+            let mut num_attribs : GLint = 0;
+            gl::GetProgramiv(self.program_handle, gl::ACTIVE_ATTRIBUTES, &mut num_attribs);
+            validate_gl()?;
+            Ok(num_attribs as u32)
+
+        }
+    }
+
+    pub fn get_frag_outputs_sorted(&self) -> Result<Vec<FragOutputInfo>> {
+        let num_outputs = self.get_resource_count(gl::PROGRAM_OUTPUT)?;
+        let mut outputs = Vec::with_capacity(num_outputs as usize);
+        for i in 0..num_outputs {
+            let props = self.get_resource_props(gl::PROGRAM_OUTPUT, i, &[gl::TYPE, gl::LOCATION])?;
+            // LOCATION_INDEX is an error if the last stage isn't a fragment shader.
+            let index = self.get_resource_props(gl::PROGRAM_OUTPUT, i, &[gl::LOCATION_INDEX])
+                .map(|p| p[0].max(0) as GLuint)
+                .unwrap_or(0);
+            outputs.push(FragOutputInfo {
+                name: self.get_resource_name(gl::PROGRAM_OUTPUT, i)?,
+                datatype: GlslType::from_gl(props[0] as GLenum),
+                location: props[1],
+                index: index
+            });
+        }
+        outputs.sort_by(|a,b| (a.location, a.index).cmp(&(b.location, b.index)));
+        Ok(outputs)
+    }
+
+    ///
+    /// Inputs of the first stage of the program, sorted by name.
+    ///
+    pub fn get_program_inputs(&self) -> Result<Vec<InterfaceVariableInfo>> {
+        self.get_interface_variables(gl::PROGRAM_INPUT)
+    }
+
+    ///
+    /// Outputs of the last stage of the program, sorted by name.
+    ///
+    pub fn get_program_outputs(&self) -> Result<Vec<InterfaceVariableInfo>> {
+        self.get_interface_variables(gl::PROGRAM_OUTPUT)
+    }
+
+    fn get_interface_variables(&self, interface : GLenum) -> Result<Vec<InterfaceVariableInfo>> {
+        let count = self.get_resource_count(interface)?;
+        let mut variables = Vec::with_capacity(count as usize);
+        for i in 0..count {
+            let props = self.get_resource_props(interface, i, &[gl::TYPE, gl::ARRAY_SIZE])?;
+            variables.push(InterfaceVariableInfo {
+                name: self.get_resource_name(interface, i)?,
+                datatype: GlslType::from_gl(props[0] as GLenum),
+                array_size: props[1]
+            });
+        }
+        variables.sort_by(|a,b| a.name.cmp(&b.name));
+        Ok(variables)
+    }
+
+    pub fn get_uniform_blocks(&self) -> Result<Vec<BlockInfo>> {
+        self.get_blocks(gl::UNIFORM_BLOCK, gl::UNIFORM)
+    }
+
+    pub fn get_storage_blocks(&self) -> Result<Vec<BlockInfo>> {
+        self.get_blocks(gl::SHADER_STORAGE_BLOCK, gl::BUFFER_VARIABLE)
+    }
+
+    fn get_blocks(&self, block_interface : GLenum, member_interface : GLenum) -> Result<Vec<BlockInfo>> {
+        let num_blocks = self.get_resource_count(block_interface)?;
+        let mut blocks = Vec::with_capacity(num_blocks as usize);
+        for i in 0..num_blocks {
+            let props = self.get_resource_props(block_interface, i,
+                                                &[gl::BUFFER_BINDING, gl::BUFFER_DATA_SIZE, gl::NUM_ACTIVE_VARIABLES])?;
+            let num_members = props[2] as usize;
+            let mut member_indices = vec![0 as GLint; num_members];
+            unsafe {
+                gl::GetProgramResourceiv(self.program_handle, block_interface, i, 1, &gl::ACTIVE_VARIABLES,
+                                         num_members as GLsizei, ptr::null_mut(), member_indices.as_mut_ptr());
+            }
+            validate_gl()?;
+
+            let mut members = Vec::with_capacity(num_members);
+            for &member in &member_indices {
+                let member = member as GLuint;
+                let member_props = self.get_resource_props(member_interface, member,
+                    &[gl::TYPE, gl::OFFSET, gl::ARRAY_SIZE, gl::ARRAY_STRIDE, gl::MATRIX_STRIDE, gl::IS_ROW_MAJOR])?;
+                members.push(BlockMemberInfo {
+                    name: self.get_resource_name(member_interface, member)?,
+                    datatype: GlslType::from_gl(member_props[0] as GLenum),
+                    offset: member_props[1] as usize,
+                    array_size: member_props[2],
+                    array_stride: member_props[3],
+                    matrix_stride: member_props[4],
+                    row_major: member_props[5] != 0
+                });
+            }
+            members.sort_by(|a,b| a.offset.cmp(&b.offset));
+
+            blocks.push(BlockInfo {
+                name: self.get_resource_name(block_interface, i)?,
+                binding: props[0] as GLuint,
+                data_size: props[1] as usize,
+                members: members
+            });
+        }
+        Ok(blocks)
+    }
+
+    fn get_resource_count(&self, interface : GLenum) -> Result<u32> {
+        unsafe {
+            let mut count : GLint = 0;
+            gl::GetProgramInterfaceiv(self.program_handle, interface, gl::ACTIVE_RESOURCES, &mut count);
+            validate_gl()?;
+            Ok(count as u32)
+        }
+    }
+
+    fn get_resource_name(&self, interface : GLenum, index : GLuint) -> Result<String> {
+        let name_len = self.get_resource_props(interface, index, &[gl::NAME_LENGTH])?[0];
+        let mut name_buf = vec![0 as GLchar; name_len.max(1) as usize];
+        let mut written : GLsizei = 0;
+        unsafe {
+            gl::GetProgramResourceName(self.program_handle, interface, index, name_buf.len() as GLsizei,
+                                       &mut written, name_buf.as_mut_ptr());
+        }
+        validate_gl()?;
+        name_buf.truncate(written as usize);
+        Ok(String::from_utf8(name_buf.iter().map(|&c| c as u8).collect())?)
+    }
+
+    fn get_resource_props(&self, interface : GLenum, index : GLuint, props : &[GLenum]) -> Result<Vec<GLint>> {
+        let mut values = vec![0 as GLint; props.len()];
+        unsafe {
+            gl::GetProgramResourceiv(self.program_handle, interface, index, props.len() as GLsizei, props.as_ptr(),
+                                     values.len() as GLsizei, ptr::null_mut(), values.as_mut_ptr());
+        }
+        validate_gl()?;
+        Ok(values)
+    }
+
+}
@@ -1,8 +1,10 @@
 extern crate gl;
 use std::ptr;
+use std::ffi::CString;
 
 use gl_shaders::GlShader;
 use gl_shaders::GlShaderUniform;
+use gl_shaders::GlShaderUniformArray;
 use gl_vertex_array::GlVertexArray;
 use gl_vertex_array::HasGlVertexArrayHandle;
 use gl_buffer::GlBufferRaw;
@@ -15,6 +17,30 @@ pub enum RenderTarget<'a> {
     Screen(usize, usize)
 }
 
+///
+/// Program state a draw runs with: a `GlShader`, or a `GlProgramPipeline` of
+/// separable programs.
+///
+pub trait GlRenderProgram {
+    ///
+    /// Programs uniforms are set in.
+    ///
+    fn programs(&self) -> Vec<&GlShader>;
+    fn bind(&self);
+}
+
+impl GlRenderProgram for GlShader {
+    fn programs(&self) -> Vec<&GlShader> {
+        vec![self]
+    }
+
+    fn bind(&self) {
+        unsafe {
+            gl::UseProgram(self.program_handle);
+        }
+    }
+}
+
 pub fn clear(r : GLfloat, g : GLfloat, b : GLfloat, a : GLfloat, fb : Option<&GlFramebuffer>) -> Result<()> {
     unsafe {
         if let Some(fb) = fb {
@@ -38,21 +64,76 @@ fn set_state() -> Result<()> {
     validate_gl()
 }
 
-fn apply_uniforms(shader : &GlShader, uniforms : &[(&str, GlShaderUniform)]) -> Result<()> {
+pub fn apply_uniforms(shader : &GlShader, uniforms : &[(&str, GlShaderUniform)]) -> Result<()> {
+    apply_uniforms_to(&[shader], uniforms)
+}
+
+///
+/// Sets the uniforms in every program that has them. Texture units, image
+/// units and storage bindings are shared by the programs.
+///
+pub fn apply_uniforms_to(programs : &[&GlShader], uniforms : &[(&str, GlShaderUniform)]) -> Result<()> {
     let mut texture_counter = 0i32;
+    let mut image_counter = 0i32;
+    let mut storage_counter = 0u32;
     for &(name, ref uniform) in uniforms {
         let result = match uniform {
             &GlShaderUniform::TextureHandle(handle) => {
                 unsafe {
                     gl::ActiveTexture((gl::TEXTURE0 as i32 + texture_counter) as GLenum);
                     gl::BindTexture(gl::TEXTURE_2D, handle);
+                    // A sampler left on the unit by another pass would override the texture's own state.
+                    gl::BindSampler(texture_counter as GLuint, 0);
                 }
-                let result = shader.set_uniform(name, &GlShaderUniform::Int(texture_counter));
+                let result = set_uniform(programs, name, &GlShaderUniform::Int(texture_counter));
                 texture_counter += 1;
                 result
             }
+            &GlShaderUniform::SampledTexture(handle, sampler) => {
+                unsafe {
+                    gl::ActiveTexture((gl::TEXTURE0 as i32 + texture_counter) as GLenum);
+                    gl::BindTexture(gl::TEXTURE_2D, handle);
+                    gl::BindSampler(texture_counter as GLuint, sampler);
+                }
+                let result = set_uniform(programs, name, &GlShaderUniform::Int(texture_counter));
+                texture_counter += 1;
+                result
+            }
+            &GlShaderUniform::Array(GlShaderUniformArray::TextureHandle(handles)) => {
+                let mut units = Vec::with_capacity(handles.len());
+                for &handle in handles {
+                    unsafe {
+                        gl::ActiveTexture((gl::TEXTURE0 as i32 + texture_counter) as GLenum);
+                        gl::BindTexture(gl::TEXTURE_2D, handle);
+                        gl::BindSampler(texture_counter as GLuint, 0);
+                    }
+                    units.push(texture_counter);
+                    texture_counter += 1;
+                }
+                set_uniform(programs, name, &GlShaderUniform::Array(GlShaderUniformArray::Int(&units)))
+            }
+            &GlShaderUniform::ImageTexture(handle, format, access) => {
+                unsafe {
+                    gl::BindImageTexture(image_counter as GLuint, handle, 0, gl::FALSE, 0, access.gl_enum(), format);
+                }
+                let result = set_uniform(programs, name, &GlShaderUniform::Int(image_counter));
+                image_counter += 1;
+                result
+            }
+            &GlShaderUniform::StorageBuffer(handle) => {
+                let results : Vec<_> = programs.iter()
+                    .map(|program| bind_storage_block(program, name, storage_counter, handle))
+                    .collect();
+                let result = match results.iter().position(|r| r.is_ok()) {
+                    Some(_) => Ok(()),
+                    None => results.into_iter().next()
+                        .unwrap_or_else(|| Err(GlError::new(format!("No program has storage block {:?}", name))))
+                };
+                storage_counter += 1;
+                result
+            }
             uniform => {
-                shader.set_uniform(name, uniform)
+                set_uniform(programs, name, uniform)
             }
         };
         match result {
@@ -65,13 +146,47 @@ fn apply_uniforms(shader : &GlShader, uniforms : &[(&str, GlShaderUniform)]) ->
     validate_gl()
 }
 
-pub fn render<V>(shader     : &GlShader,
+///
+/// Sets a uniform in every program that has it. A single program reports a
+/// missing uniform itself.
+///
+fn set_uniform(programs : &[&GlShader], name : &str, uniform : &GlShaderUniform) -> Result<()> {
+    if programs.len() == 1 {
+        return programs[0].set_uniform(name, uniform);
+    }
+    let mut found = false;
+    for program in programs.iter().filter(|program| program.uniform_location(name).is_some()) {
+        program.set_uniform(name, uniform)?;
+        found = true;
+    }
+    if found {
+        Ok(())
+    } else {
+        Err(GlError::new(format!("No program of the pipeline has uniform {:?}", name)))
+    }
+}
+
+fn bind_storage_block(shader : &GlShader, name : &str, binding : GLuint, buffer_id : GLuint) -> Result<()> {
+    let name_c = CString::new(name)?;
+    unsafe {
+        let block_index = gl::GetProgramResourceIndex(shader.program_handle, gl::SHADER_STORAGE_BLOCK, name_c.as_ptr());
+        if block_index == gl::INVALID_INDEX {
+            return Err(GlError::new(format!("Error getting storage block index: {:?}", name)));
+        }
+        gl::ShaderStorageBlockBinding(shader.program_handle, block_index, binding);
+        gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, binding, buffer_id);
+    }
+    validate_gl()
+}
+
+pub fn render<S, V>(shader  : &S,
               vertex_array  : &V,
               vertex_count  : i32,
               rendertarget  : &RenderTarget,
-              uniforms      : &[(&str, GlShaderUniform)]) -> Result<()> where V : HasGlVertexArrayHandle {
+              uniforms      : &[(&str, GlShaderUniform)]) -> Result<()>
+    where S : GlRenderProgram + ?Sized, V : HasGlVertexArrayHandle {
     set_state()?;
-    apply_uniforms(shader, uniforms)?;
+    apply_uniforms_to(&shader.programs(), uniforms)?;
     unsafe {
         match *rendertarget {
             RenderTarget::Framebuffer(fb) => {
@@ -85,19 +200,20 @@ pub fn render<V>(shader     : &GlShader,
         }
 
         gl::BindVertexArray(vertex_array.gl_vao_handle());
-        gl::UseProgram(shader.program_handle);
+        shader.bind();
         gl::DrawArrays(gl::TRIANGLES, 0, vertex_count);
     }
     validate_gl()
 }
 
-pub fn render_indexed<V>(shader        : &GlShader,
+pub fn render_indexed<S, V>(shader     : &S,
                       vertex_array  : &V,
                       index_buffer  : &GlBufferRaw,
                       rendertarget  : &RenderTarget,
-                      uniforms      : &[(&str, GlShaderUniform)]) -> Result<()> where V : HasGlVertexArrayHandle {
+                      uniforms      : &[(&str, GlShaderUniform)]) -> Result<()>
+    where S : GlRenderProgram + ?Sized, V : HasGlVertexArrayHandle {
     set_state()?;
-    apply_uniforms(shader, uniforms)?;
+    apply_uniforms_to(&shader.programs(), uniforms)?;
 
     unsafe {
         match *rendertarget {
@@ -113,7 +229,7 @@ pub fn render_indexed<V>(shader        : &GlShader,
 
         gl::BindVertexArray(vertex_array.gl_vao_handle());
         gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, index_buffer.buffer_id);
-        gl::UseProgram(shader.program_handle);
+        shader.bind();
         gl::DrawElements(gl::TRIANGLES, (index_buffer.el_count * index_buffer.component_count) as i32, index_buffer.gl_type_enum, ptr::null());
     }
     validate_gl()
@@ -0,0 +1,238 @@
+extern crate gl;
+use gl::types::*;
+use gl_err::*;
+
+// GL 4.6 / EXT_texture_filter_anisotropic, not in the generated bindings.
+const TEXTURE_MAX_ANISOTROPY : GLenum = 0x84FE;
+const MAX_TEXTURE_MAX_ANISOTROPY : GLenum = 0x84FF;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GlFilter {
+    Nearest,
+    Linear
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GlWrap {
+    Repeat,
+    MirroredRepeat,
+    ClampToEdge,
+    ClampToBorder,
+    MirrorClampToEdge
+}
+
+///
+/// Comparison of depth textures sampled with `sampler2DShadow` and friends.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GlCompareFunc {
+    Never,
+    Less,
+    LessEqual,
+    Equal,
+    NotEqual,
+    GreaterEqual,
+    Greater,
+    Always
+}
+
+///
+/// Sampler object, holding how a texture is filtered and addressed apart
+/// from the texture, so one texture can be sampled differently per pass:
+///
+/// ```ignore
+/// let shadow = GlSampler::new()?.filter(GlFilter::Linear)?.compare(Some(GlCompareFunc::LessEqual))?;
+/// render(&shader, &va, count, &target, &[("shadow_map", GlShaderUniform::SampledTexture(depth.handle, shadow.handle))])?;
+/// ```
+///
+/// Defaults sample only the base level, so textures without mipmaps work:
+/// nearest minification, linear magnification and repeat wrapping. Use
+/// `mip_filter` for mipmapped textures. Setters report GL errors, e.g. a
+/// wrap mode the driver lacks.
+///
+pub struct GlSampler {
+    pub handle : GLuint,
+    min_filter : GlFilter,
+    mip_filter : Option<GlFilter>
+}
+
+impl GlFilter {
+    pub fn gl_enum(&self) -> GLenum {
+        match *self {
+            GlFilter::Nearest => gl::NEAREST,
+            GlFilter::Linear => gl::LINEAR
+        }
+    }
+}
+
+impl GlWrap {
+    pub fn gl_enum(&self) -> GLenum {
+        match *self {
+            GlWrap::Repeat => gl::REPEAT,
+            GlWrap::MirroredRepeat => gl::MIRRORED_REPEAT,
+            GlWrap::ClampToEdge => gl::CLAMP_TO_EDGE,
+            GlWrap::ClampToBorder => gl::CLAMP_TO_BORDER,
+            GlWrap::MirrorClampToEdge => gl::MIRROR_CLAMP_TO_EDGE
+        }
+    }
+}
+
+impl GlCompareFunc {
+    pub fn gl_enum(&self) -> GLenum {
+        match *self {
+            GlCompareFunc::Never => gl::NEVER,
+            GlCompareFunc::Less => gl::LESS,
+            GlCompareFunc::LessEqual => gl::LEQUAL,
+            GlCompareFunc::Equal => gl::EQUAL,
+            GlCompareFunc::NotEqual => gl::NOTEQUAL,
+            GlCompareFunc::GreaterEqual => gl::GEQUAL,
+            GlCompareFunc::Greater => gl::GREATER,
+            GlCompareFunc::Always => gl::ALWAYS
+        }
+    }
+}
+
+impl GlSampler {
+    pub fn new() -> Result<GlSampler> {
+        let mut handle : GLuint = 0;
+        unsafe {
+            gl::GenSamplers(1, &mut handle);
+        }
+        validate_gl()?;
+        // GL's own default minification uses mipmaps.
+        GlSampler {
+            handle: handle,
+            min_filter: GlFilter::Nearest,
+            mip_filter: None
+        }.apply_min_filter()
+    }
+
+    ///
+    /// Sets both the minification and magnification filter.
+    ///
+    pub fn filter(self, filter : GlFilter) -> Result<Self> {
+        self.min_filter(filter)?.mag_filter(filter)
+    }
+
+    pub fn min_filter(mut self, filter : GlFilter) -> Result<Self> {
+        self.min_filter = filter;
+        self.apply_min_filter()
+    }
+
+    pub fn mag_filter(self, filter : GlFilter) -> Result<Self> {
+        self.parameter_i(gl::TEXTURE_MAG_FILTER, filter.gl_enum() as GLint)
+    }
+
+    ///
+    /// Sets filtering between mip levels, or `None` to sample only the base
+    /// level, e.g. for textures without mipmaps.
+    ///
+    pub fn mip_filter(mut self, filter : Option<GlFilter>) -> Result<Self> {
+        self.mip_filter = filter;
+        self.apply_min_filter()
+    }
+
+    ///
+    /// Sets the wrap mode of every axis.
+    ///
+    pub fn wrap(self, wrap : GlWrap) -> Result<Self> {
+        self.wrap_s(wrap)?.wrap_t(wrap)?.wrap_r(wrap)
+    }
+
+    pub fn wrap_s(self, wrap : GlWrap) -> Result<Self> {
+        self.parameter_i(gl::TEXTURE_WRAP_S, wrap.gl_enum() as GLint)
+    }
+
+    pub fn wrap_t(self, wrap : GlWrap) -> Result<Self> {
+        self.parameter_i(gl::TEXTURE_WRAP_T, wrap.gl_enum() as GLint)
+    }
+
+    pub fn wrap_r(self, wrap : GlWrap) -> Result<Self> {
+        self.parameter_i(gl::TEXTURE_WRAP_R, wrap.gl_enum() as GLint)
+    }
+
+    ///
+    /// Color sampled outside the texture with `GlWrap::ClampToBorder`.
+    ///
+    pub fn border_color(self, color : [f32; 4]) -> Result<Self> {
+        unsafe {
+            gl::SamplerParameterfv(self.handle, gl::TEXTURE_BORDER_COLOR, color.as_ptr());
+        }
+        validate_gl()?;
+        Ok(self)
+    }
+
+    ///
+    /// Sets the maximum anisotropy, clamped to what the driver supports.
+    /// 1 disables anisotropic filtering.
+    ///
+    pub fn anisotropy(self, max_anisotropy : f32) -> Result<Self> {
+        let mut supported : GLfloat = 1.0;
+        unsafe {
+            gl::GetFloatv(MAX_TEXTURE_MAX_ANISOTROPY, &mut supported);
+        }
+        // Drivers without anisotropic filtering report an error; leave it off.
+        if validate_gl().is_err() {
+            return Ok(self);
+        }
+        self.parameter_f(TEXTURE_MAX_ANISOTROPY, max_anisotropy.max(1.0).min(supported))
+    }
+
+    ///
+    /// Limits sampling to mip levels of detail `min..=max`.
+    ///
+    pub fn lod_range(self, min : f32, max : f32) -> Result<Self> {
+        self.parameter_f(gl::TEXTURE_MIN_LOD, min)?.parameter_f(gl::TEXTURE_MAX_LOD, max)
+    }
+
+    pub fn lod_bias(self, bias : f32) -> Result<Self> {
+        self.parameter_f(gl::TEXTURE_LOD_BIAS, bias)
+    }
+
+    ///
+    /// Compares depth texture values to the reference coordinate with `func`,
+    /// or samples them as values with `None`.
+    ///
+    pub fn compare(self, func : Option<GlCompareFunc>) -> Result<Self> {
+        match func {
+            Some(func) => self.parameter_i(gl::TEXTURE_COMPARE_MODE, gl::COMPARE_REF_TO_TEXTURE as GLint)?
+                .parameter_i(gl::TEXTURE_COMPARE_FUNC, func.gl_enum() as GLint),
+            None => self.parameter_i(gl::TEXTURE_COMPARE_MODE, gl::NONE as GLint)
+        }
+    }
+
+    fn apply_min_filter(self) -> Result<Self> {
+        let filter = match (self.min_filter, self.mip_filter) {
+            (filter, None) => filter.gl_enum(),
+            (GlFilter::Nearest, Some(GlFilter::Nearest)) => gl::NEAREST_MIPMAP_NEAREST,
+            (GlFilter::Nearest, Some(GlFilter::Linear)) => gl::NEAREST_MIPMAP_LINEAR,
+            (GlFilter::Linear, Some(GlFilter::Nearest)) => gl::LINEAR_MIPMAP_NEAREST,
+            (GlFilter::Linear, Some(GlFilter::Linear)) => gl::LINEAR_MIPMAP_LINEAR
+        };
+        self.parameter_i(gl::TEXTURE_MIN_FILTER, filter as GLint)
+    }
+
+    fn parameter_i(self, name : GLenum, value : GLint) -> Result<Self> {
+        unsafe {
+            gl::SamplerParameteri(self.handle, name, value);
+        }
+        validate_gl()?;
+        Ok(self)
+    }
+
+    fn parameter_f(self, name : GLenum, value : GLfloat) -> Result<Self> {
+        unsafe {
+            gl::SamplerParameterf(self.handle, name, value);
+        }
+        validate_gl()?;
+        Ok(self)
+    }
+}
+
+impl Drop for GlSampler {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteSamplers(1, &self.handle);
+        }
+    }
+}
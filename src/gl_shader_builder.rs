@@ -0,0 +1,473 @@
+extern crate gl;
+use gl::types::*;
+use gl_err::*;
+use gl_shaders::*;
+use glsl_preprocess::*;
+use gl_program_cache::GlProgramCache;
+use gl_transform_feedback::GlFeedbackMode;
+use gl_spirv::GlSpirvModule;
+use gl_stage_interface::*;
+
+use std::ffi::CString;
+use std::fmt;
+use std::rc::Rc;
+
+///
+/// A programmable pipeline stage.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GlShaderStage {
+    Vertex,
+    TessControl,
+    TessEvaluation,
+    Geometry,
+    Fragment,
+    Compute
+}
+
+///
+/// Builds a `GlShader` from any set of stages. Every stage is compiled before
+/// reporting, so a failed build lists the errors of each failing stage.
+///
+#[derive(Clone)]
+pub struct GlShaderBuilder {
+    stages : Vec<(GlShaderStage, ShaderSource)>,
+    includes : Option<Rc<dyn ShaderFileSystem>>,
+    defines : Vec<(String, String)>,
+    binary_cache : Option<Rc<GlProgramCache>>,
+    matrix_layout : GlMatrixLayout,
+    separable : bool,
+    feedback_varyings : Option<(Vec<String>, GlFeedbackMode)>,
+    attrib_locations : Vec<(String, GLuint)>,
+    frag_data_locations : Vec<(String, GLuint, GLuint)>,
+    check_interfaces : bool
+}
+
+#[derive(Clone)]
+enum ShaderSource {
+    Text(String),
+    File(String),
+    Spirv(GlSpirvModule)
+}
+
+///
+/// Final source of a stage as given to the driver.
+///
+struct StageSource {
+    stage : GlShaderStage,
+    source : String,
+    preprocessed : Option<PreprocessedSource>,
+    spirv : Option<GlSpirvModule>
+}
+
+impl GlShaderStage {
+    pub fn gl_enum(&self) -> GLenum {
+        match *self {
+            GlShaderStage::Vertex => gl::VERTEX_SHADER,
+            GlShaderStage::TessControl => gl::TESS_CONTROL_SHADER,
+            GlShaderStage::TessEvaluation => gl::TESS_EVALUATION_SHADER,
+            GlShaderStage::Geometry => gl::GEOMETRY_SHADER,
+            GlShaderStage::Fragment => gl::FRAGMENT_SHADER,
+            GlShaderStage::Compute => gl::COMPUTE_SHADER
+        }
+    }
+
+    ///
+    /// Bit of the stage for glUseProgramStages.
+    ///
+    pub fn gl_bit(&self) -> GLbitfield {
+        match *self {
+            GlShaderStage::Vertex => gl::VERTEX_SHADER_BIT,
+            GlShaderStage::TessControl => gl::TESS_CONTROL_SHADER_BIT,
+            GlShaderStage::TessEvaluation => gl::TESS_EVALUATION_SHADER_BIT,
+            GlShaderStage::Geometry => gl::GEOMETRY_SHADER_BIT,
+            GlShaderStage::Fragment => gl::FRAGMENT_SHADER_BIT,
+            GlShaderStage::Compute => gl::COMPUTE_SHADER_BIT
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            GlShaderStage::Vertex => "vertex",
+            GlShaderStage::TessControl => "tessellation control",
+            GlShaderStage::TessEvaluation => "tessellation evaluation",
+            GlShaderStage::Geometry => "geometry",
+            GlShaderStage::Fragment => "fragment",
+            GlShaderStage::Compute => "compute"
+        }
+    }
+}
+
+impl fmt::Display for GlShaderStage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} shader", self.name())
+    }
+}
+
+impl GlShaderBuilder {
+    pub fn new() -> GlShaderBuilder {
+        GlShaderBuilder {
+            stages: Vec::new(),
+            includes: None,
+            defines: Vec::new(),
+            binary_cache: None,
+            matrix_layout: GlMatrixLayout::RowMajor,
+            separable: false,
+            feedback_varyings: None,
+            attrib_locations: Vec::new(),
+            frag_data_locations: Vec::new(),
+            check_interfaces: false
+        }
+    }
+
+    ///
+    /// Sets the source of a stage, replacing any earlier source for it.
+    ///
+    pub fn stage(mut self, stage : GlShaderStage, source : &str) -> Self {
+        self.stages.retain(|&(s, _)| s != stage);
+        self.stages.push((stage, ShaderSource::Text(source.to_string())));
+        self
+    }
+
+    ///
+    /// Sets the source of a stage to a file read from the include file system.
+    ///
+    pub fn stage_file(mut self, stage : GlShaderStage, path : &str) -> Self {
+        self.stages.retain(|&(s, _)| s != stage);
+        self.stages.push((stage, ShaderSource::File(path.to_string())));
+        self
+    }
+
+    ///
+    /// Sets a stage to a SPIR-V module. Includes and defines don't apply to it.
+    ///
+    pub fn stage_spirv(mut self, stage : GlShaderStage, module : GlSpirvModule) -> Self {
+        self.stages.retain(|&(s, _)| s != stage);
+        self.stages.push((stage, ShaderSource::Spirv(module)));
+        self
+    }
+
+    ///
+    /// Resolves `#include` directives in every stage through `file_system`.
+    /// Compile errors then name the included file the error is in.
+    ///
+    pub fn includes(mut self, file_system : Rc<dyn ShaderFileSystem>) -> Self {
+        self.includes = Some(file_system);
+        self
+    }
+
+    ///
+    /// Adds `#define name value` to every stage, right after its `#version` line.
+    /// `value` may be empty for plain feature flags.
+    ///
+    pub fn define(mut self, name : &str, value : &str) -> Self {
+        self.defines.retain(|&(ref n, _)| n != name);
+        self.defines.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    ///
+    /// Caches the linked program in `cache`. Later builds with the same sources
+    /// on the same driver load the binary instead of compiling.
+    ///
+    pub fn binary_cache(mut self, cache : Rc<GlProgramCache>) -> Self {
+        self.binary_cache = Some(cache);
+        self
+    }
+
+    ///
+    /// Links the program with GL_PROGRAM_SEPARABLE, so its stages can be
+    /// combined with other programs' in a `GlProgramPipeline`.
+    ///
+    pub fn separable(mut self) -> Self {
+        self.separable = true;
+        self
+    }
+
+    ///
+    /// Captures the named outputs of the last vertex processing stage with
+    /// transform feedback. See `GlTransformFeedback`.
+    ///
+    pub fn transform_feedback_varyings(mut self, varyings : &[&str], mode : GlFeedbackMode) -> Self {
+        self.feedback_varyings = Some((varyings.iter().map(|v| v.to_string()).collect(), mode));
+        self
+    }
+
+    ///
+    /// Binds vertex attribute `name` to `location` before linking. A
+    /// `layout(location)` in the shader takes precedence.
+    ///
+    pub fn bind_attrib_location(mut self, name : &str, location : u32) -> Self {
+        self.attrib_locations.retain(|&(ref n, _)| n != name);
+        self.attrib_locations.push((name.to_string(), location));
+        self
+    }
+
+    ///
+    /// Binds each of `names` to its index, e.g. to the field order of a
+    /// `ShaderAttribs` struct.
+    ///
+    pub fn bind_attrib_locations(self, names : &[&str]) -> Self {
+        names.iter().enumerate().fold(self, |builder, (location, name)| builder.bind_attrib_location(name, location as u32))
+    }
+
+    ///
+    /// Binds fragment output `name` to draw buffer `color_number` before
+    /// linking. A `layout(location)` in the shader takes precedence.
+    ///
+    pub fn bind_frag_data_location(self, name : &str, color_number : u32) -> Self {
+        self.bind_frag_data_location_indexed(name, color_number, 0)
+    }
+
+    ///
+    /// Like `bind_frag_data_location`, with the source `index` used for dual
+    /// source blending.
+    ///
+    pub fn bind_frag_data_location_indexed(mut self, name : &str, color_number : u32, index : u32) -> Self {
+        self.frag_data_locations.retain(|&(ref n, _, _)| n != name);
+        self.frag_data_locations.push((name.to_string(), color_number, index));
+        self
+    }
+
+    ///
+    /// Binds each of `names` to the draw buffer of its index, so outputs match
+    /// the texture order of a `GlFramebuffer`.
+    ///
+    pub fn frag_outputs(self, names : &[&str]) -> Self {
+        names.iter().enumerate().fold(self, |builder, (color_number, name)| builder.bind_frag_data_location(name, color_number as u32))
+    }
+
+    ///
+    /// Before linking, checks that each stage's inputs match the previous
+    /// stage's outputs in name, type and interpolation, reporting every
+    /// mismatch with both declarations. Costs a separable link per stage.
+    /// With a binary cache the check is part of the key, so a program cached
+    /// without it is never loaded in place of running it.
+    ///
+    pub fn check_interfaces(mut self) -> Self {
+        self.check_interfaces = true;
+        self
+    }
+
+    ///
+    /// Sets `GlShader::matrix_layout` of the built shader.
+    ///
+    pub fn matrix_layout(mut self, layout : GlMatrixLayout) -> Self {
+        self.matrix_layout = layout;
+        self
+    }
+
+    pub fn build(self) -> Result<GlShader> {
+        if self.stages.is_empty() {
+            return Err(GlError::new("No shader stages given".to_string()));
+        }
+
+        let sources = self.resolve_sources()?;
+        let program_id = match self.binary_cache {
+            Some(ref cache) => {
+                let key = cache.key(&self.cache_key_text(&sources));
+                match cache.load_with_parameters(&key, &self.program_parameters()) {
+                    Some(program_id) => program_id,
+                    None => {
+                        let program_id = self.compile_and_link(&sources)?;
+                        cache.store(&key, program_id);
+                        program_id
+                    }
+                }
+            }
+            None => self.compile_and_link(&sources)?
+        };
+
+        let mut shader = GlShader::from_program(program_id)?;
+        shader.matrix_layout = self.matrix_layout;
+        Ok(shader)
+    }
+
+    fn compile_and_link(&self, sources : &[StageSource]) -> Result<GLuint> {
+        let mut feedback_names = Vec::new();
+        if let Some((ref varyings, _)) = self.feedback_varyings {
+            for varying in varyings {
+                feedback_names.push(CString::new(&varying[..])?);
+            }
+        }
+        let mut attrib_names = Vec::with_capacity(self.attrib_locations.len());
+        for &(ref name, location) in &self.attrib_locations {
+            attrib_names.push((CString::new(&name[..])?, location));
+        }
+        let mut frag_data_names = Vec::with_capacity(self.frag_data_locations.len());
+        for &(ref name, color_number, index) in &self.frag_data_locations {
+            frag_data_names.push((CString::new(&name[..])?, color_number, index));
+        }
+
+        let mut handles = Vec::with_capacity(sources.len());
+        let mut errors = Vec::new();
+        for source in sources {
+            let result = match source.spirv {
+                Some(ref module) => module.load(source.stage),
+                None => load_shader_prog(source.stage.gl_enum(), &source.source)
+            };
+            match result {
+                Ok(handle) => handles.push(handle),
+                Err(err) => {
+                    let log = match source.preprocessed {
+                        Some(ref preprocessed) => preprocessed.annotate_log(&err.to_string()),
+                        None => err.to_string()
+                    };
+                    errors.push(format!("{}: {}", source.stage, log))
+                }
+            }
+        }
+
+        if !errors.is_empty() {
+            delete_shaders(&handles);
+            return Err(GlError::new(errors.join("\n")));
+        }
+
+        if self.check_interfaces {
+            let stages : Vec<StageInterface> = sources.iter().zip(&handles)
+                .map(|(source, &handle)| StageInterface {
+                    stage: source.stage,
+                    shader_handle: handle,
+                    source: if source.spirv.is_none() { Some(&source.source[..]) } else { None }
+                })
+                .collect();
+            if let Err(err) = check_stage_interfaces(&stages) {
+                delete_shaders(&handles);
+                return Err(err);
+            }
+        }
+
+        let program_id = unsafe {
+            let program_id = gl::CreateProgram();
+            for &handle in &handles {
+                gl::AttachShader(program_id, handle);
+            }
+            if self.binary_cache.is_some() {
+                gl::ProgramParameteri(program_id, gl::PROGRAM_BINARY_RETRIEVABLE_HINT, gl::TRUE as GLint);
+            }
+            for (name, value) in self.program_parameters() {
+                gl::ProgramParameteri(program_id, name, value);
+            }
+            if let Some((_, mode)) = self.feedback_varyings {
+                let pointers : Vec<*const GLchar> = feedback_names.iter().map(|n| n.as_ptr()).collect();
+                gl::TransformFeedbackVaryings(program_id, pointers.len() as GLsizei, pointers.as_ptr(), mode.gl_enum());
+            }
+            for &(ref name, location) in &attrib_names {
+                gl::BindAttribLocation(program_id, location, name.as_ptr());
+            }
+            for &(ref name, color_number, index) in &frag_data_names {
+                gl::BindFragDataLocationIndexed(program_id, color_number, index, name.as_ptr());
+            }
+            gl::LinkProgram(program_id);
+            for &handle in &handles {
+                gl::DetachShader(program_id, handle);
+            }
+            program_id
+        };
+        delete_shaders(&handles);
+
+        if let Err(err) = check_link_status(program_id) {
+            unsafe {
+                gl::DeleteProgram(program_id);
+            }
+            return Err(err);
+        }
+        Ok(program_id)
+    }
+
+    ///
+    /// Resolves includes and defines of every stage into the source that is
+    /// given to the driver. Errors of all failing stages are reported together.
+    ///
+    fn resolve_sources(&self) -> Result<Vec<StageSource>> {
+        let mut sources = Vec::with_capacity(self.stages.len());
+        let mut errors = Vec::new();
+        for &(stage, ref source) in &self.stages {
+            match self.resolve_source(stage, source) {
+                Ok(source) => sources.push(source),
+                Err(err) => errors.push(format!("{}: {}", stage, err))
+            }
+        }
+        if !errors.is_empty() {
+            return Err(GlError::new(errors.join("\n")));
+        }
+        Ok(sources)
+    }
+
+    fn resolve_source(&self, stage : GlShaderStage, source : &ShaderSource) -> Result<StageSource> {
+        match (&self.includes, source) {
+            (_, &ShaderSource::Spirv(ref module)) => {
+                Ok(StageSource {
+                    stage: stage,
+                    source: module.cache_key_text(),
+                    preprocessed: None,
+                    spirv: Some(module.clone())
+                })
+            }
+            (&Some(ref file_system), source) => {
+                let (name, text) = match *source {
+                    ShaderSource::Text(ref text) => (format!("<{}>", stage), text.clone()),
+                    ShaderSource::File(ref path) => (path.clone(), file_system.read_file(path)?),
+                    ShaderSource::Spirv(_) => unreachable!()
+                };
+                let preprocessed = preprocess(&**file_system, &name, &text)?;
+                Ok(StageSource {
+                    stage: stage,
+                    source: inject_defines(&preprocessed.source, &self.defines),
+                    preprocessed: Some(preprocessed),
+                    spirv: None
+                })
+            }
+            (&None, &ShaderSource::Text(ref text)) => {
+                Ok(StageSource {
+                    stage: stage,
+                    source: inject_defines(text, &self.defines),
+                    preprocessed: None,
+                    spirv: None
+                })
+            }
+            (&None, &ShaderSource::File(ref path)) => {
+                Err(GlError::new(format!("No include file system to read {:?} from", path)))
+            }
+        }
+    }
+
+    ///
+    /// Program parameters set before linking or loading a cached binary.
+    ///
+    fn program_parameters(&self) -> Vec<(GLenum, GLint)> {
+        let mut parameters = Vec::new();
+        if self.separable {
+            parameters.push((gl::PROGRAM_SEPARABLE, gl::TRUE as GLint));
+        }
+        parameters
+    }
+
+    ///
+    /// Everything that affects the linked program, for the binary cache key.
+    ///
+    fn cache_key_text(&self, sources : &[StageSource]) -> String {
+        let mut text = format!("separable {}\ncheck_interfaces {}\n", self.separable, self.check_interfaces);
+        if let Some((ref varyings, mode)) = self.feedback_varyings {
+            text.push_str(&format!("feedback {:?} {}\n", mode, varyings.join(",")));
+        }
+        for &(ref name, location) in &self.attrib_locations {
+            text.push_str(&format!("attrib {} {}\n", name, location));
+        }
+        for &(ref name, color_number, index) in &self.frag_data_locations {
+            text.push_str(&format!("frag_data {} {} {}\n", name, color_number, index));
+        }
+        for source in sources {
+            text.push_str(&format!("{}\n{}\n", source.stage, source.source));
+        }
+        text
+    }
+}
+
+fn delete_shaders(handles : &[GLuint]) {
+    for &handle in handles {
+        unsafe {
+            gl::DeleteShader(handle);
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use gl_err::*;
+use gl_shaders::GlShader;
+use gl_shader_builder::GlShaderBuilder;
+use gl_shader_builder::GlShaderStage;
+
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+///
+/// A `GlShader` loaded from source files that recompiles itself when the
+/// files change. Changes are detected by polling modification times.
+///
+pub struct GlReloadableShader {
+    shader : GlShader,
+    sources : Vec<(GlShaderStage, PathBuf, Option<SystemTime>)>
+}
+
+impl GlReloadableShader {
+    ///
+    /// Compiles the program from the given stage files. Unlike `poll`, this
+    /// fails if the initial compile fails, as there is no program to fall back to.
+    ///
+    pub fn load<P: AsRef<Path>>(stages : &[(GlShaderStage, P)]) -> Result<GlReloadableShader> {
+        let mut sources : Vec<_> = stages.iter()
+            .map(|&(stage, ref path)| (stage, path.as_ref().to_path_buf(), None))
+            .collect();
+        let shader = compile_sources(&mut sources)?;
+        Ok(GlReloadableShader {
+            shader: shader,
+            sources: sources
+        })
+    }
+
+    pub fn shader(&self) -> &GlShader {
+        &self.shader
+    }
+
+    ///
+    /// Recompiles if any source file changed since the last compile. Returns
+    /// `Ok(true)` if the program was replaced. If compiling fails, the error is
+    /// returned and the previous program stays in use until the files change again.
+    ///
+    pub fn poll(&mut self) -> Result<bool> {
+        let changed = self.sources.iter()
+            .any(|&(_, ref path, mtime)| modified_time(path) != mtime);
+        if !changed {
+            return Ok(false);
+        }
+
+        let shader = compile_sources(&mut self.sources)?;
+        self.shader = shader;
+        Ok(true)
+    }
+}
+
+fn modified_time(path : &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn compile_sources(sources : &mut [(GlShaderStage, PathBuf, Option<SystemTime>)]) -> Result<GlShader> {
+    let mut builder = GlShaderBuilder::new();
+    for &mut (stage, ref path, ref mut mtime) in sources.iter_mut() {
+        // Record the time before reading, so an edit landing during the read
+        // is picked up by the next poll.
+        *mtime = modified_time(path);
+        let source = fs::read_to_string(path)
+            .map_err(|err| GlError::new(format!("Error reading {}: {}", path.display(), err)))?;
+        builder = builder.stage(stage, &source);
+    }
+    builder.build()
+        .map_err(|err| GlError::new(format!("Error compiling {}: {}", describe_sources(sources), err)))
+}
+
+fn describe_sources(sources : &[(GlShaderStage, PathBuf, Option<SystemTime>)]) -> String {
+    let paths : Vec<_> = sources.iter().map(|&(_, ref path, _)| path.display().to_string()).collect();
+    paths.join(", ")
+}
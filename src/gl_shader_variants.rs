@@ -0,0 +1,98 @@
+use gl_err::*;
+use gl_shaders::GlShader;
+use gl_shader_builder::GlShaderBuilder;
+
+use std::collections::HashMap;
+
+///
+/// Lazily compiled permutations of one shader. Each unique set of defines
+/// compiles into its own `GlShader` on first use and is cached after that.
+///
+pub struct GlShaderVariants {
+    builder : GlShaderBuilder,
+    variants : HashMap<Vec<(String, String)>, GlShader>
+}
+
+impl GlShaderVariants {
+    ///
+    /// `builder` holds the shared sources and settings of all variants.
+    ///
+    pub fn new(builder : GlShaderBuilder) -> GlShaderVariants {
+        GlShaderVariants {
+            builder: builder,
+            variants: HashMap::new()
+        }
+    }
+
+    ///
+    /// Returns the variant for `defines`, compiling it if needed. Each define
+    /// is `NAME` or `NAME=VALUE`; their order doesn't matter. Defining a name
+    /// twice with different values is an error.
+    ///
+    pub fn get(&mut self, defines : &[&str]) -> Result<&GlShader> {
+        let key = define_set(defines)?;
+        if !self.variants.contains_key(&key) {
+            let shader = key.iter()
+                .fold(self.builder.clone(), |builder, &(ref name, ref value)| builder.define(name, value))
+                .build()
+                .map_err(|err| GlError::new(format!("Error compiling variant {:?}: {}", defines, err)))?;
+            self.variants.insert(key.clone(), shader);
+        }
+        Ok(&self.variants[&key])
+    }
+
+    pub fn compiled_count(&self) -> usize {
+        self.variants.len()
+    }
+
+    ///
+    /// Drops all compiled variants, e.g. after the sources have changed.
+    ///
+    pub fn clear(&mut self) {
+        self.variants.clear();
+    }
+}
+
+fn define_set(defines : &[&str]) -> Result<Vec<(String, String)>> {
+    let mut set : Vec<_> = defines.iter()
+        .map(|define| match define.find('=') {
+            Some(i) => (define[..i].trim().to_string(), define[i + 1..].trim().to_string()),
+            None => (define.trim().to_string(), String::new())
+        })
+        .collect();
+    set.sort();
+    set.dedup();
+    for pair in set.windows(2) {
+        if pair[0].0 == pair[1].0 {
+            return Err(GlError::new(format!("{} defined as both {:?} and {:?} in {:?}",
+                                            pair[0].0, pair[0].1, pair[1].1, defines)));
+        }
+    }
+    Ok(set)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::define_set;
+
+    fn pair(name : &str, value : &str) -> (String, String) {
+        (name.to_string(), value.to_string())
+    }
+
+    #[test]
+    fn define_order_doesnt_matter() {
+        assert_eq!(define_set(&["B", "A=2"]).unwrap(), vec![pair("A", "2"), pair("B", "")]);
+        assert_eq!(define_set(&["A = 2", "B"]).unwrap(), define_set(&["B", "A=2"]).unwrap());
+    }
+
+    #[test]
+    fn repeated_defines_are_merged() {
+        assert_eq!(define_set(&["A=1", "A=1", "B"]).unwrap(), vec![pair("A", "1"), pair("B", "")]);
+    }
+
+    #[test]
+    fn conflicting_defines_are_errors() {
+        assert!(define_set(&["A=2", "A=1"]).is_err());
+        assert!(define_set(&["A", "A=1"]).is_err());
+    }
+}
@@ -1,257 +1,634 @@
 extern crate gl;
 use gl::types::*;
 use gl_err::*;
+use gl_shader_builder::GlShaderBuilder;
+use gl_shader_builder::GlShaderStage;
+use gl_spirv::GlSpirvModule;
+use glsl_types::GlslType;
+#[cfg(feature="validate")]
+use glsl_validate::validate_glsl;
 
 use std::ptr;
 use std::ffi::CString;
-
+use std::collections::HashMap;
+
+///
+/// Value of a uniform. Matrices are `MatCxR` with C columns and R rows, as in
+/// GLSL, and their element order is given by `GlShader::matrix_layout`.
+/// Samplers and images are set with `Int` units, or bound by the renderer
+/// from `TextureHandle`, `SampledTexture` (a texture and a `GlSampler`) and
+/// `ImageTexture`. `Array` sets consecutive elements of an array uniform in
+/// one call.
+///
 #[derive(Debug, Clone, Copy)]
-pub enum GlShaderUniform {
+pub enum GlShaderUniform<'a> {
     Mat4x4([f32;16]),
     Int(i32),
     TextureHandle(GLuint),
+    SampledTexture(GLuint, GLuint),
     Vec2([f32;2]),
     Vec3([f32;3]),
     Vec4([f32;4]),
-    Float(f32)
+    Float(f32),
+    StorageBuffer(GLuint),
+    ImageTexture(GLuint, GLenum, GlImageAccess),
+    IVec2([i32;2]),
+    IVec3([i32;3]),
+    IVec4([i32;4]),
+    UInt(u32),
+    UVec2([u32;2]),
+    UVec3([u32;3]),
+    UVec4([u32;4]),
+    Bool(bool),
+    BVec2([bool;2]),
+    BVec3([bool;3]),
+    BVec4([bool;4]),
+    Mat2x2([f32;4]),
+    Mat3x3([f32;9]),
+    Mat2x3([f32;6]),
+    Mat2x4([f32;8]),
+    Mat3x2([f32;6]),
+    Mat3x4([f32;12]),
+    Mat4x2([f32;8]),
+    Mat4x3([f32;12]),
+    Double(f64),
+    DVec2([f64;2]),
+    DVec3([f64;3]),
+    DVec4([f64;4]),
+    DMat2x2([f64;4]),
+    DMat3x3([f64;9]),
+    DMat4x4([f64;16]),
+    DMat2x3([f64;6]),
+    DMat2x4([f64;8]),
+    DMat3x2([f64;6]),
+    DMat3x4([f64;12]),
+    DMat4x2([f64;8]),
+    DMat4x3([f64;12]),
+    Array(GlShaderUniformArray<'a>)
 }
 
-#[derive(Debug)]
-pub struct GlShader {
-    pub program_handle : gl::types::GLuint
-}
-
-#[derive(Debug)]
-pub struct UniformInfo {
-    pub name : String,
-    pub datatype : GLenum,
-    pub size : i32
+///
+/// Values of an array uniform, one slice element per array element.
+/// `TextureHandle` arrays are bound to consecutive texture units by the renderer.
+///
+#[derive(Debug, Clone, Copy)]
+pub enum GlShaderUniformArray<'a> {
+    Float(&'a [f32]),
+    Vec2(&'a [[f32;2]]),
+    Vec3(&'a [[f32;3]]),
+    Vec4(&'a [[f32;4]]),
+    Int(&'a [i32]),
+    IVec2(&'a [[i32;2]]),
+    IVec3(&'a [[i32;3]]),
+    IVec4(&'a [[i32;4]]),
+    UInt(&'a [u32]),
+    UVec2(&'a [[u32;2]]),
+    UVec3(&'a [[u32;3]]),
+    UVec4(&'a [[u32;4]]),
+    Bool(&'a [bool]),
+    Mat2x2(&'a [[f32;4]]),
+    Mat3x3(&'a [[f32;9]]),
+    Mat4x4(&'a [[f32;16]]),
+    Mat2x3(&'a [[f32;6]]),
+    Mat2x4(&'a [[f32;8]]),
+    Mat3x2(&'a [[f32;6]]),
+    Mat3x4(&'a [[f32;12]]),
+    Mat4x2(&'a [[f32;8]]),
+    Mat4x3(&'a [[f32;12]]),
+    Double(&'a [f64]),
+    DVec2(&'a [[f64;2]]),
+    DVec3(&'a [[f64;3]]),
+    DVec4(&'a [[f64;4]]),
+    DMat2x2(&'a [[f64;4]]),
+    DMat3x3(&'a [[f64;9]]),
+    DMat4x4(&'a [[f64;16]]),
+    DMat2x3(&'a [[f64;6]]),
+    DMat2x4(&'a [[f64;8]]),
+    DMat3x2(&'a [[f64;6]]),
+    DMat3x4(&'a [[f64;12]]),
+    DMat4x2(&'a [[f64;8]]),
+    DMat4x3(&'a [[f64;12]]),
+    TextureHandle(&'a [GLuint])
 }
 
-#[derive(Debug)]
-pub struct AttribInfo {
-    pub name : String,
-    pub datatype : GLenum,
-    pub size : i32,
-    pub location : i32
+///
+/// Element order of matrix uniforms. `RowMajor` uploads with transpose set,
+/// and is the default to match how `Mat4x4` has always been uploaded.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GlMatrixLayout {
+    RowMajor,
+    ColumnMajor
 }
 
-#[derive(Debug)]
-pub struct FragOutputInfo {
-    pub name : String,
-    pub datatype : GLenum,
-    pub location : i32,
-    pub index : GLuint
+///
+/// Access mode of an image bound with `GlShaderUniform::ImageTexture`.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GlImageAccess {
+    ReadOnly,
+    WriteOnly,
+    ReadWrite
 }
 
-impl GlShader {
-
-    pub fn compile(vs_source : &str, fs_source : &str) -> Result<GlShader> {
+impl<'a> GlShaderUniform<'a> {
+    ///
+    /// The GLSL type this value is uploaded as, or `None` for resources that
+    /// the renderer binds instead of setting them as a uniform value.
+    ///
+    pub fn glsl_type(&self) -> Option<GlslType> {
+        match *self {
+            GlShaderUniform::Float(_) => Some(GlslType::Float),
+            GlShaderUniform::Vec2(_) => Some(GlslType::Vec2),
+            GlShaderUniform::Vec3(_) => Some(GlslType::Vec3),
+            GlShaderUniform::Vec4(_) => Some(GlslType::Vec4),
+            GlShaderUniform::Int(_) => Some(GlslType::Int),
+            GlShaderUniform::IVec2(_) => Some(GlslType::IVec2),
+            GlShaderUniform::IVec3(_) => Some(GlslType::IVec3),
+            GlShaderUniform::IVec4(_) => Some(GlslType::IVec4),
+            GlShaderUniform::UInt(_) => Some(GlslType::UInt),
+            GlShaderUniform::UVec2(_) => Some(GlslType::UVec2),
+            GlShaderUniform::UVec3(_) => Some(GlslType::UVec3),
+            GlShaderUniform::UVec4(_) => Some(GlslType::UVec4),
+            GlShaderUniform::Bool(_) => Some(GlslType::Bool),
+            GlShaderUniform::BVec2(_) => Some(GlslType::BVec2),
+            GlShaderUniform::BVec3(_) => Some(GlslType::BVec3),
+            GlShaderUniform::BVec4(_) => Some(GlslType::BVec4),
+            GlShaderUniform::Mat2x2(_) => Some(GlslType::Mat2),
+            GlShaderUniform::Mat3x3(_) => Some(GlslType::Mat3),
+            GlShaderUniform::Mat4x4(_) => Some(GlslType::Mat4),
+            GlShaderUniform::Mat2x3(_) => Some(GlslType::Mat2x3),
+            GlShaderUniform::Mat2x4(_) => Some(GlslType::Mat2x4),
+            GlShaderUniform::Mat3x2(_) => Some(GlslType::Mat3x2),
+            GlShaderUniform::Mat3x4(_) => Some(GlslType::Mat3x4),
+            GlShaderUniform::Mat4x2(_) => Some(GlslType::Mat4x2),
+            GlShaderUniform::Mat4x3(_) => Some(GlslType::Mat4x3),
+            GlShaderUniform::Double(_) => Some(GlslType::Double),
+            GlShaderUniform::DVec2(_) => Some(GlslType::DVec2),
+            GlShaderUniform::DVec3(_) => Some(GlslType::DVec3),
+            GlShaderUniform::DVec4(_) => Some(GlslType::DVec4),
+            GlShaderUniform::DMat2x2(_) => Some(GlslType::DMat2),
+            GlShaderUniform::DMat3x3(_) => Some(GlslType::DMat3),
+            GlShaderUniform::DMat4x4(_) => Some(GlslType::DMat4),
+            GlShaderUniform::DMat2x3(_) => Some(GlslType::DMat2x3),
+            GlShaderUniform::DMat2x4(_) => Some(GlslType::DMat2x4),
+            GlShaderUniform::DMat3x2(_) => Some(GlslType::DMat3x2),
+            GlShaderUniform::DMat3x4(_) => Some(GlslType::DMat3x4),
+            GlShaderUniform::DMat4x2(_) => Some(GlslType::DMat4x2),
+            GlShaderUniform::DMat4x3(_) => Some(GlslType::DMat4x3),
+            GlShaderUniform::Array(ref array) => array.glsl_type(),
+            GlShaderUniform::TextureHandle(_) |
+            GlShaderUniform::SampledTexture(..) |
+            GlShaderUniform::StorageBuffer(_) |
+            GlShaderUniform::ImageTexture(..) => None
+        }
+    }
 
-        let vs_handle = load_shader_prog(gl::VERTEX_SHADER, vs_source)?;
-        let fs_handle = load_shader_prog(gl::FRAGMENT_SHADER, fs_source)?;
-
-        let program_id = unsafe {
-            let program_id = gl::CreateProgram();
-            gl::AttachShader(program_id, vs_handle);
-            gl::AttachShader(program_id, fs_handle);
-            gl::LinkProgram(program_id);
-
-            let mut link_result : gl::types::GLint = 0;
-            gl::GetProgramiv(program_id, gl::LINK_STATUS, &mut link_result);
-
-            let mut log_length = 0;
-            gl::GetProgramiv(program_id, gl::INFO_LOG_LENGTH, &mut log_length);
-            let error_str = if log_length > 0 {
-                let mut error_msg = Vec::with_capacity((log_length+1) as usize);
-                for _ in 1..log_length { error_msg.push(0); }
-                let error_msg_ptr = error_msg.as_mut_ptr();
-                gl::GetProgramInfoLog(program_id, log_length, ptr::null_mut(), error_msg_ptr);
-                let s = String::from_utf8(error_msg.iter().map(|&c| c as u8).collect())?;
-                println!("Info log: {:?}", s);
-                s
-            } else {
-                String::from("")
-            };
+    ///
+    /// Whether this value can be set to a uniform of the given GLSL type.
+    /// Unknown types are accepted, as there is nothing to check against.
+    ///
+    pub fn matches_glsl_type(&self, glsl_type : GlslType) -> bool {
+        if let GlslType::Unknown(_) = glsl_type {
+            return true;
+        }
+        match *self {
+            GlShaderUniform::TextureHandle(_) |
+            GlShaderUniform::SampledTexture(..) |
+            GlShaderUniform::Array(GlShaderUniformArray::TextureHandle(_)) => glsl_type.is_sampler(),
+            GlShaderUniform::ImageTexture(..) => glsl_type.is_image(),
+            GlShaderUniform::StorageBuffer(_) => false,
+            _ => self.glsl_type().map_or(false, |value_type| glsl_assignable(value_type, glsl_type))
+        }
+    }
 
-            if !error_str.is_empty() {
-                Err(GlError::new(error_str))
-            } else {
-                match link_result {
-                    0 => {
-                        Err(GlError::new(error_str))
-                    }
-                    _ => {
-                        Ok(program_id)
-                    }
-                }
+    ///
+    /// Name of the variant, for error messages.
+    ///
+    pub fn type_name(&self) -> String {
+        match *self {
+            GlShaderUniform::TextureHandle(_) => "TextureHandle".to_string(),
+            GlShaderUniform::SampledTexture(..) => "SampledTexture".to_string(),
+            GlShaderUniform::StorageBuffer(_) => "StorageBuffer".to_string(),
+            GlShaderUniform::ImageTexture(..) => "ImageTexture".to_string(),
+            GlShaderUniform::Array(GlShaderUniformArray::TextureHandle(_)) => "TextureHandle array".to_string(),
+            GlShaderUniform::Array(ref array) => match array.glsl_type() {
+                Some(glsl_type) => format!("{} array", glsl_type),
+                None => "array".to_string()
+            },
+            _ => match self.glsl_type() {
+                Some(glsl_type) => glsl_type.to_string(),
+                None => "?".to_string()
             }
-        }?;
+        }
+    }
+}
 
-        unsafe {
-            gl::DetachShader(program_id, vs_handle);
-            gl::DetachShader(program_id, fs_handle);
+impl<'a> GlShaderUniformArray<'a> {
+    ///
+    /// The GLSL type of each element.
+    ///
+    pub fn glsl_type(&self) -> Option<GlslType> {
+        match *self {
+            GlShaderUniformArray::Float(_) => Some(GlslType::Float),
+            GlShaderUniformArray::Vec2(_) => Some(GlslType::Vec2),
+            GlShaderUniformArray::Vec3(_) => Some(GlslType::Vec3),
+            GlShaderUniformArray::Vec4(_) => Some(GlslType::Vec4),
+            GlShaderUniformArray::Int(_) => Some(GlslType::Int),
+            GlShaderUniformArray::IVec2(_) => Some(GlslType::IVec2),
+            GlShaderUniformArray::IVec3(_) => Some(GlslType::IVec3),
+            GlShaderUniformArray::IVec4(_) => Some(GlslType::IVec4),
+            GlShaderUniformArray::UInt(_) => Some(GlslType::UInt),
+            GlShaderUniformArray::UVec2(_) => Some(GlslType::UVec2),
+            GlShaderUniformArray::UVec3(_) => Some(GlslType::UVec3),
+            GlShaderUniformArray::UVec4(_) => Some(GlslType::UVec4),
+            GlShaderUniformArray::Bool(_) => Some(GlslType::Bool),
+            GlShaderUniformArray::Mat2x2(_) => Some(GlslType::Mat2),
+            GlShaderUniformArray::Mat3x3(_) => Some(GlslType::Mat3),
+            GlShaderUniformArray::Mat4x4(_) => Some(GlslType::Mat4),
+            GlShaderUniformArray::Mat2x3(_) => Some(GlslType::Mat2x3),
+            GlShaderUniformArray::Mat2x4(_) => Some(GlslType::Mat2x4),
+            GlShaderUniformArray::Mat3x2(_) => Some(GlslType::Mat3x2),
+            GlShaderUniformArray::Mat3x4(_) => Some(GlslType::Mat3x4),
+            GlShaderUniformArray::Mat4x2(_) => Some(GlslType::Mat4x2),
+            GlShaderUniformArray::Mat4x3(_) => Some(GlslType::Mat4x3),
+            GlShaderUniformArray::Double(_) => Some(GlslType::Double),
+            GlShaderUniformArray::DVec2(_) => Some(GlslType::DVec2),
+            GlShaderUniformArray::DVec3(_) => Some(GlslType::DVec3),
+            GlShaderUniformArray::DVec4(_) => Some(GlslType::DVec4),
+            GlShaderUniformArray::DMat2x2(_) => Some(GlslType::DMat2),
+            GlShaderUniformArray::DMat3x3(_) => Some(GlslType::DMat3),
+            GlShaderUniformArray::DMat4x4(_) => Some(GlslType::DMat4),
+            GlShaderUniformArray::DMat2x3(_) => Some(GlslType::DMat2x3),
+            GlShaderUniformArray::DMat2x4(_) => Some(GlslType::DMat2x4),
+            GlShaderUniformArray::DMat3x2(_) => Some(GlslType::DMat3x2),
+            GlShaderUniformArray::DMat3x4(_) => Some(GlslType::DMat3x4),
+            GlShaderUniformArray::DMat4x2(_) => Some(GlslType::DMat4x2),
+            GlShaderUniformArray::DMat4x3(_) => Some(GlslType::DMat4x3),
+            GlShaderUniformArray::TextureHandle(_) => None
+        }
+    }
 
-            gl::DeleteShader(vs_handle);
-            gl::DeleteShader(fs_handle);
+    pub fn len(&self) -> usize {
+        match *self {
+            GlShaderUniformArray::Float(data) => data.len(),
+            GlShaderUniformArray::Vec2(data) => data.len(),
+            GlShaderUniformArray::Vec3(data) => data.len(),
+            GlShaderUniformArray::Vec4(data) => data.len(),
+            GlShaderUniformArray::Int(data) => data.len(),
+            GlShaderUniformArray::IVec2(data) => data.len(),
+            GlShaderUniformArray::IVec3(data) => data.len(),
+            GlShaderUniformArray::IVec4(data) => data.len(),
+            GlShaderUniformArray::UInt(data) => data.len(),
+            GlShaderUniformArray::UVec2(data) => data.len(),
+            GlShaderUniformArray::UVec3(data) => data.len(),
+            GlShaderUniformArray::UVec4(data) => data.len(),
+            GlShaderUniformArray::Bool(data) => data.len(),
+            GlShaderUniformArray::Mat2x2(data) => data.len(),
+            GlShaderUniformArray::Mat3x3(data) => data.len(),
+            GlShaderUniformArray::Mat4x4(data) => data.len(),
+            GlShaderUniformArray::Mat2x3(data) => data.len(),
+            GlShaderUniformArray::Mat2x4(data) => data.len(),
+            GlShaderUniformArray::Mat3x2(data) => data.len(),
+            GlShaderUniformArray::Mat3x4(data) => data.len(),
+            GlShaderUniformArray::Mat4x2(data) => data.len(),
+            GlShaderUniformArray::Mat4x3(data) => data.len(),
+            GlShaderUniformArray::Double(data) => data.len(),
+            GlShaderUniformArray::DVec2(data) => data.len(),
+            GlShaderUniformArray::DVec3(data) => data.len(),
+            GlShaderUniformArray::DVec4(data) => data.len(),
+            GlShaderUniformArray::DMat2x2(data) => data.len(),
+            GlShaderUniformArray::DMat3x3(data) => data.len(),
+            GlShaderUniformArray::DMat4x4(data) => data.len(),
+            GlShaderUniformArray::DMat2x3(data) => data.len(),
+            GlShaderUniformArray::DMat2x4(data) => data.len(),
+            GlShaderUniformArray::DMat3x2(data) => data.len(),
+            GlShaderUniformArray::DMat3x4(data) => data.len(),
+            GlShaderUniformArray::DMat4x2(data) => data.len(),
+            GlShaderUniformArray::DMat4x3(data) => data.len(),
+            GlShaderUniformArray::TextureHandle(data) => data.len()
         }
+    }
 
-        let shader = GlShader{ program_handle: program_id };
-        //println!("Created program, uniforms: {}", shader.get_number_of_uniforms());
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
 
-        Ok(shader)
+impl GlImageAccess {
+    pub fn gl_enum(&self) -> GLenum {
+        match *self {
+            GlImageAccess::ReadOnly => gl::READ_ONLY,
+            GlImageAccess::WriteOnly => gl::WRITE_ONLY,
+            GlImageAccess::ReadWrite => gl::READ_WRITE
+        }
     }
+}
 
-    pub fn set_uniform(&self, uniform_name : &str, uniform : &GlShaderUniform) -> Result<()> {
-        let uniform_name_c = CString::new(uniform_name)?;
-        let uniform_id = unsafe {
-            gl::GetUniformLocation(self.program_handle, uniform_name_c.as_ptr())
-        };
+#[derive(Debug)]
+pub struct GlShader {
+    pub program_handle : gl::types::GLuint,
+    pub matrix_layout : GlMatrixLayout,
+    uniform_locations : HashMap<String, GlUniformLocation>
+}
 
-        if uniform_id == -1 {
-            return Err(GlError::new(format!("Error getting uniform location: {:?}, note that unused uniforms are stripped out", uniform_name)));
-        }
+///
+/// Location and reflected type of an active uniform, looked up once with
+/// `GlShader::uniform_location` and then used to set it without string work.
+/// For array elements, `size` is the number of elements from this one to the
+/// end of the array.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlUniformLocation {
+    pub location : GLint,
+    pub datatype : GlslType,
+    pub size : i32
+}
 
-        unsafe {
-            gl::UseProgram(self.program_handle);
-        }
+impl GlShader {
 
-        match *uniform {
-            GlShaderUniform::Mat4x4(data) =>
-                unsafe {
-                    gl::UniformMatrix4fv(uniform_id, 1, gl::TRUE, data.as_ptr());
-                },
-            GlShaderUniform::Vec3(data) =>
-                unsafe {
-                    gl::Uniform3fv(uniform_id, 1, data.as_ptr());
-                },
-            GlShaderUniform::Int(data) =>
-                unsafe {
-                    gl::Uniform1i(uniform_id, data);
-                },
-            GlShaderUniform::TextureHandle(_) => {
-                return Err(GlError::new("Can't set texture handle here. Needs renderer.".to_string()))
-                ;
-            },
-            _ => return Err(GlError::new(format!("unimplemented datatype {:?}", uniform)))
-        };
-        validate_gl()
+    pub fn compile(vs_source : &str, fs_source : &str) -> Result<GlShader> {
+        GlShaderBuilder::new()
+            .stage(GlShaderStage::Vertex, vs_source)
+            .stage(GlShaderStage::Fragment, fs_source)
+            .build()
     }
 
-    pub fn get_uniform_infos(&self) -> Result<Vec<UniformInfo>> {
-        let num_uniforms = self.get_number_of_uniforms()?;
-        let mut ret = Vec::with_capacity(num_uniforms as usize);
-        for i in 0..num_uniforms {
-            unsafe {
-                let mut uniform_name_buf = Vec::with_capacity(1024 as usize);
-                for _ in 1..1024 { uniform_name_buf.push(0); }
-                let mut uniform_name_len : i32 = 0;
-                let mut uniform_size : i32 = 0;
-                let mut uniform_type : GLenum = 0 as GLenum;
-                gl::GetActiveUniform(self.program_handle, i, 1024, &mut uniform_name_len, &mut uniform_size, &mut uniform_type,
-                                     uniform_name_buf.as_mut_ptr());
-                uniform_name_buf.resize(uniform_name_len as usize, 0);
-                let name_str = String::from_utf8(uniform_name_buf.iter().map(|&c| c as u8).collect())?;
-                ret.push(UniformInfo {
-                    name: name_str,
-                    datatype: uniform_type,
-                    size: uniform_size
-                });
+    ///
+    /// Builds a program from SPIR-V vertex and fragment binaries with entry
+    /// point `main`. Use `GlShaderBuilder::stage_spirv` for other stages,
+    /// entry points or specialization constants.
+    ///
+    pub fn from_spirv(vs_binary : &[u8], fs_binary : &[u8]) -> Result<GlShader> {
+        GlShaderBuilder::new()
+            .stage_spirv(GlShaderStage::Vertex, GlSpirvModule::new(vs_binary)?)
+            .stage_spirv(GlShaderStage::Fragment, GlSpirvModule::new(fs_binary)?)
+            .build()
+    }
+
+    ///
+    /// Wraps a linked program, building its table of uniform locations.
+    /// The program is deleted when the `GlShader` is dropped.
+    ///
+    pub fn from_program(program_handle : GLuint) -> Result<GlShader> {
+        let mut shader = GlShader {
+            program_handle: program_handle,
+            matrix_layout: GlMatrixLayout::RowMajor,
+            uniform_locations: HashMap::new()
+        };
+        for info in shader.get_uniform_infos()? {
+            if info.location == -1 {
+                // Members of uniform blocks have no location.
+                continue;
             }
+            let location = GlUniformLocation {
+                location: info.location,
+                datatype: info.datatype,
+                size: info.size
+            };
+            // Arrays are reported as "name[0]", but are usually set by the bare name.
+            // The other elements are addressable as "name[i]".
+            if info.name.ends_with("[0]") {
+                let base = info.name[..info.name.len() - 3].to_string();
+                for i in 1..info.size {
+                    let element_name = CString::new(format!("{}[{}]", base, i))?;
+                    let element_location = unsafe {
+                        gl::GetUniformLocation(program_handle, element_name.as_ptr())
+                    };
+                    shader.uniform_locations.insert(element_name.into_string().unwrap(), GlUniformLocation {
+                        location: element_location,
+                        datatype: info.datatype,
+                        size: info.size - i
+                    });
+                }
+                shader.uniform_locations.insert(base, location);
+            }
+            shader.uniform_locations.insert(info.name, location);
         }
-        Ok(ret)
+        Ok(shader)
     }
 
-    fn get_number_of_uniforms(&self) -> Result<u32>
-     {
-        unsafe {
-            let mut num_uniforms : GLint = 0;
-            gl::GetProgramiv(self.program_handle, gl::ACTIVE_UNIFORMS, &mut num_uniforms);
-            validate_gl()?;
-            Ok(num_uniforms as u32)
+    pub fn uniform_location(&self, uniform_name : &str) -> Option<GlUniformLocation> {
+        self.uniform_locations.get(uniform_name).cloned()
+    }
+
+    ///
+    /// Sets a uniform by name. Array elements and struct members are addressed
+    /// as in GLSL, e.g. `lights[3].color`.
+    ///
+    pub fn set_uniform(&self, uniform_name : &str, uniform : &GlShaderUniform) -> Result<()> {
+        let location = match self.uniform_locations.get(uniform_name) {
+            Some(location) => location,
+            None => return Err(self.missing_uniform_error(uniform_name))
+        };
+        if let Err(mismatch) = check_uniform(location, uniform) {
+            return Err(GlError::new(format!("Uniform {:?} {}", uniform_name, mismatch)));
         }
+        self.upload_uniform(location, uniform)
     }
 
-    pub fn get_attrib_infos_sorted(&self) -> Result<Vec<AttribInfo>> {
-        let num_attribs = self.get_number_of_attributes()?;
-        let mut ret = Vec::with_capacity(num_attribs as usize);
-        for i in 0..num_attribs {
-            unsafe {
-                let mut attrib_name_buf = Vec::with_capacity(1024 as usize);
-                for _ in 1..1024 { attrib_name_buf.push(0); }
-                let mut attrib_name_len : i32 = 0;
-                let mut attrib_size : i32 = 0;
-                let mut attrib_type : GLenum = 0 as GLenum;
-                gl::GetActiveAttrib(self.program_handle, i, 1024, &mut attrib_name_len, &mut attrib_size, &mut attrib_type,
-                                     attrib_name_buf.as_mut_ptr());
-                attrib_name_buf.resize(attrib_name_len as usize, 0);
-                let name_str = String::from_utf8(attrib_name_buf.iter().map(|&c| c as u8).collect())?;
-                let location = gl::GetAttribLocation(self.program_handle, attrib_name_buf.as_ptr());
-                ret.push(AttribInfo {
-                    name: name_str,
-                    datatype: attrib_type,
-                    size: attrib_size,
-                    location: location
-                });
+    fn missing_uniform_error(&self, uniform_name : &str) -> GlError {
+        // Tell an out of range element apart from a missing uniform.
+        if let Some(open) = uniform_name.rfind('[') {
+            let first_element = format!("{}[0]", &uniform_name[..open]);
+            if let Some(location) = self.uniform_locations.get(&first_element) {
+                return GlError::new(format!("Index out of range: {:?}, array has {} elements", uniform_name, location.size));
             }
         }
-
-        ret.sort_by(|a,b| a.location.cmp(&b.location) );
-
-        Ok(ret)
+        GlError::new(format!("Error getting uniform location: {:?}, note that unused uniforms are stripped out", uniform_name))
     }
 
-    fn get_number_of_attributes(&self) -> Result<u32> {
-        unsafe {
-            let mut num_attribs : GLint = 0;
-            gl::GetProgramiv(self.program_handle, gl::ACTIVE_ATTRIBUTES, &mut num_attribs);
-            validate_gl()?;
-            Ok(num_attribs as u32)
-
+    ///
+    /// Sets a uniform by a location from `uniform_location`.
+    ///
+    pub fn set_uniform_at(&self, location : &GlUniformLocation, uniform : &GlShaderUniform) -> Result<()> {
+        if let Err(mismatch) = check_uniform(location, uniform) {
+            return Err(GlError::new(format!("Uniform at location {} {}", location.location, mismatch)));
         }
+        self.upload_uniform(location, uniform)
     }
 
-    pub fn get_frag_outputs_sorted(&self) -> Result<Vec<FragOutputInfo>> {
-        let num_outputs = self.get_number_of_frag_outputs()?;
-
-        let mut outputs = Vec::new();
+    fn upload_uniform(&self, location : &GlUniformLocation, uniform : &GlShaderUniform) -> Result<()> {
+        let uniform_id = location.location;
 
         unsafe {
-            for i in 0..num_outputs {
-                let mut name_buf = Vec::with_capacity(1024 as usize);
-                for _ in 1..1024 { name_buf.push(0); }
-                let mut name_len = 0;
-
-                println!("getProgramResourceName");
-                gl::GetProgramResourceName(self.program_handle, gl::PROGRAM_OUTPUT, i, 1024, &mut name_len, name_buf.as_mut_ptr());
+            gl::UseProgram(self.program_handle);
+        }
 
-                println!("getProgramResourceIndex");
-                let index = gl::GetProgramResourceIndex(self.program_handle, gl::PROGRAM_OUTPUT, name_buf.as_ptr());
+        let transpose = self.transpose();
 
-                let name_str = String::from_utf8(name_buf.iter().map(|&c| c as u8).collect())?;
+        unsafe {
+            match *uniform {
+                GlShaderUniform::Float(data) => gl::Uniform1f(uniform_id, data),
+                GlShaderUniform::Vec2(data) => gl::Uniform2fv(uniform_id, 1, data.as_ptr()),
+                GlShaderUniform::Vec3(data) => gl::Uniform3fv(uniform_id, 1, data.as_ptr()),
+                GlShaderUniform::Vec4(data) => gl::Uniform4fv(uniform_id, 1, data.as_ptr()),
+                GlShaderUniform::Int(data) => gl::Uniform1i(uniform_id, data),
+                GlShaderUniform::IVec2(data) => gl::Uniform2iv(uniform_id, 1, data.as_ptr()),
+                GlShaderUniform::IVec3(data) => gl::Uniform3iv(uniform_id, 1, data.as_ptr()),
+                GlShaderUniform::IVec4(data) => gl::Uniform4iv(uniform_id, 1, data.as_ptr()),
+                GlShaderUniform::UInt(data) => gl::Uniform1ui(uniform_id, data),
+                GlShaderUniform::UVec2(data) => gl::Uniform2uiv(uniform_id, 1, data.as_ptr()),
+                GlShaderUniform::UVec3(data) => gl::Uniform3uiv(uniform_id, 1, data.as_ptr()),
+                GlShaderUniform::UVec4(data) => gl::Uniform4uiv(uniform_id, 1, data.as_ptr()),
+                GlShaderUniform::Bool(data) => gl::Uniform1i(uniform_id, data as GLint),
+                GlShaderUniform::BVec2(data) => gl::Uniform2i(uniform_id, data[0] as GLint, data[1] as GLint),
+                GlShaderUniform::BVec3(data) =>
+                    gl::Uniform3i(uniform_id, data[0] as GLint, data[1] as GLint, data[2] as GLint),
+                GlShaderUniform::BVec4(data) =>
+                    gl::Uniform4i(uniform_id, data[0] as GLint, data[1] as GLint, data[2] as GLint, data[3] as GLint),
+                GlShaderUniform::Mat2x2(data) => gl::UniformMatrix2fv(uniform_id, 1, transpose, data.as_ptr()),
+                GlShaderUniform::Mat3x3(data) => gl::UniformMatrix3fv(uniform_id, 1, transpose, data.as_ptr()),
+                GlShaderUniform::Mat4x4(data) => gl::UniformMatrix4fv(uniform_id, 1, transpose, data.as_ptr()),
+                GlShaderUniform::Mat2x3(data) => gl::UniformMatrix2x3fv(uniform_id, 1, transpose, data.as_ptr()),
+                GlShaderUniform::Mat2x4(data) => gl::UniformMatrix2x4fv(uniform_id, 1, transpose, data.as_ptr()),
+                GlShaderUniform::Mat3x2(data) => gl::UniformMatrix3x2fv(uniform_id, 1, transpose, data.as_ptr()),
+                GlShaderUniform::Mat3x4(data) => gl::UniformMatrix3x4fv(uniform_id, 1, transpose, data.as_ptr()),
+                GlShaderUniform::Mat4x2(data) => gl::UniformMatrix4x2fv(uniform_id, 1, transpose, data.as_ptr()),
+                GlShaderUniform::Mat4x3(data) => gl::UniformMatrix4x3fv(uniform_id, 1, transpose, data.as_ptr()),
+                GlShaderUniform::Double(data) => gl::Uniform1d(uniform_id, data),
+                GlShaderUniform::DVec2(data) => gl::Uniform2dv(uniform_id, 1, data.as_ptr()),
+                GlShaderUniform::DVec3(data) => gl::Uniform3dv(uniform_id, 1, data.as_ptr()),
+                GlShaderUniform::DVec4(data) => gl::Uniform4dv(uniform_id, 1, data.as_ptr()),
+                GlShaderUniform::DMat2x2(data) => gl::UniformMatrix2dv(uniform_id, 1, transpose, data.as_ptr()),
+                GlShaderUniform::DMat3x3(data) => gl::UniformMatrix3dv(uniform_id, 1, transpose, data.as_ptr()),
+                GlShaderUniform::DMat4x4(data) => gl::UniformMatrix4dv(uniform_id, 1, transpose, data.as_ptr()),
+                GlShaderUniform::DMat2x3(data) => gl::UniformMatrix2x3dv(uniform_id, 1, transpose, data.as_ptr()),
+                GlShaderUniform::DMat2x4(data) => gl::UniformMatrix2x4dv(uniform_id, 1, transpose, data.as_ptr()),
+                GlShaderUniform::DMat3x2(data) => gl::UniformMatrix3x2dv(uniform_id, 1, transpose, data.as_ptr()),
+                GlShaderUniform::DMat3x4(data) => gl::UniformMatrix3x4dv(uniform_id, 1, transpose, data.as_ptr()),
+                GlShaderUniform::DMat4x2(data) => gl::UniformMatrix4x2dv(uniform_id, 1, transpose, data.as_ptr()),
+                GlShaderUniform::DMat4x3(data) => gl::UniformMatrix4x3dv(uniform_id, 1, transpose, data.as_ptr()),
+                GlShaderUniform::Array(ref array) => {
+                    return self.upload_uniform_array(location, array);
+                }
+                GlShaderUniform::TextureHandle(_) |
+                GlShaderUniform::SampledTexture(..) |
+                GlShaderUniform::StorageBuffer(_) |
+                GlShaderUniform::ImageTexture(..) => {
+                    return Err(GlError::new(format!("Can't set {:?} here. Needs renderer.", uniform)));
+                }
+            }
+        }
+        validate_gl()
+    }
 
-                outputs.push(FragOutputInfo {
-                    name: name_str,
-                    datatype: 0,
-                    location: -1,
-                    index: index
-                });
+    fn upload_uniform_array(&self, location : &GlUniformLocation, array : &GlShaderUniformArray) -> Result<()> {
+        let uniform_id = location.location;
+        let count = array.len() as GLsizei;
+        let transpose = self.transpose();
 
+        unsafe {
+            gl::UseProgram(self.program_handle);
+            match *array {
+                GlShaderUniformArray::Float(data) => gl::Uniform1fv(uniform_id, count, data.as_ptr()),
+                GlShaderUniformArray::Vec2(data) => gl::Uniform2fv(uniform_id, count, data.as_ptr() as *const f32),
+                GlShaderUniformArray::Vec3(data) => gl::Uniform3fv(uniform_id, count, data.as_ptr() as *const f32),
+                GlShaderUniformArray::Vec4(data) => gl::Uniform4fv(uniform_id, count, data.as_ptr() as *const f32),
+                GlShaderUniformArray::Int(data) => gl::Uniform1iv(uniform_id, count, data.as_ptr()),
+                GlShaderUniformArray::IVec2(data) => gl::Uniform2iv(uniform_id, count, data.as_ptr() as *const i32),
+                GlShaderUniformArray::IVec3(data) => gl::Uniform3iv(uniform_id, count, data.as_ptr() as *const i32),
+                GlShaderUniformArray::IVec4(data) => gl::Uniform4iv(uniform_id, count, data.as_ptr() as *const i32),
+                GlShaderUniformArray::UInt(data) => gl::Uniform1uiv(uniform_id, count, data.as_ptr()),
+                GlShaderUniformArray::UVec2(data) => gl::Uniform2uiv(uniform_id, count, data.as_ptr() as *const u32),
+                GlShaderUniformArray::UVec3(data) => gl::Uniform3uiv(uniform_id, count, data.as_ptr() as *const u32),
+                GlShaderUniformArray::UVec4(data) => gl::Uniform4uiv(uniform_id, count, data.as_ptr() as *const u32),
+                GlShaderUniformArray::Bool(data) => {
+                    let ints : Vec<GLint> = data.iter().map(|&b| b as GLint).collect();
+                    gl::Uniform1iv(uniform_id, count, ints.as_ptr())
+                }
+                GlShaderUniformArray::Mat2x2(data) =>
+                    gl::UniformMatrix2fv(uniform_id, count, transpose, data.as_ptr() as *const f32),
+                GlShaderUniformArray::Mat3x3(data) =>
+                    gl::UniformMatrix3fv(uniform_id, count, transpose, data.as_ptr() as *const f32),
+                GlShaderUniformArray::Mat4x4(data) =>
+                    gl::UniformMatrix4fv(uniform_id, count, transpose, data.as_ptr() as *const f32),
+                GlShaderUniformArray::Mat2x3(data) =>
+                    gl::UniformMatrix2x3fv(uniform_id, count, transpose, data.as_ptr() as *const f32),
+                GlShaderUniformArray::Mat2x4(data) =>
+                    gl::UniformMatrix2x4fv(uniform_id, count, transpose, data.as_ptr() as *const f32),
+                GlShaderUniformArray::Mat3x2(data) =>
+                    gl::UniformMatrix3x2fv(uniform_id, count, transpose, data.as_ptr() as *const f32),
+                GlShaderUniformArray::Mat3x4(data) =>
+                    gl::UniformMatrix3x4fv(uniform_id, count, transpose, data.as_ptr() as *const f32),
+                GlShaderUniformArray::Mat4x2(data) =>
+                    gl::UniformMatrix4x2fv(uniform_id, count, transpose, data.as_ptr() as *const f32),
+                GlShaderUniformArray::Mat4x3(data) =>
+                    gl::UniformMatrix4x3fv(uniform_id, count, transpose, data.as_ptr() as *const f32),
+                GlShaderUniformArray::Double(data) => gl::Uniform1dv(uniform_id, count, data.as_ptr()),
+                GlShaderUniformArray::DVec2(data) => gl::Uniform2dv(uniform_id, count, data.as_ptr() as *const f64),
+                GlShaderUniformArray::DVec3(data) => gl::Uniform3dv(uniform_id, count, data.as_ptr() as *const f64),
+                GlShaderUniformArray::DVec4(data) => gl::Uniform4dv(uniform_id, count, data.as_ptr() as *const f64),
+                GlShaderUniformArray::DMat2x2(data) =>
+                    gl::UniformMatrix2dv(uniform_id, count, transpose, data.as_ptr() as *const f64),
+                GlShaderUniformArray::DMat3x3(data) =>
+                    gl::UniformMatrix3dv(uniform_id, count, transpose, data.as_ptr() as *const f64),
+                GlShaderUniformArray::DMat4x4(data) =>
+                    gl::UniformMatrix4dv(uniform_id, count, transpose, data.as_ptr() as *const f64),
+                GlShaderUniformArray::DMat2x3(data) =>
+                    gl::UniformMatrix2x3dv(uniform_id, count, transpose, data.as_ptr() as *const f64),
+                GlShaderUniformArray::DMat2x4(data) =>
+                    gl::UniformMatrix2x4dv(uniform_id, count, transpose, data.as_ptr() as *const f64),
+                GlShaderUniformArray::DMat3x2(data) =>
+                    gl::UniformMatrix3x2dv(uniform_id, count, transpose, data.as_ptr() as *const f64),
+                GlShaderUniformArray::DMat3x4(data) =>
+                    gl::UniformMatrix3x4dv(uniform_id, count, transpose, data.as_ptr() as *const f64),
+                GlShaderUniformArray::DMat4x2(data) =>
+                    gl::UniformMatrix4x2dv(uniform_id, count, transpose, data.as_ptr() as *const f64),
+                GlShaderUniformArray::DMat4x3(data) =>
+                    gl::UniformMatrix4x3dv(uniform_id, count, transpose, data.as_ptr() as *const f64),
+                GlShaderUniformArray::TextureHandle(_) => {
+                    return Err(GlError::new(format!("Can't set {:?} here. Needs renderer.", array)));
+                }
             }
         }
-        Ok(outputs)
+        validate_gl()
     }
 
-    fn get_number_of_frag_outputs(&self) -> Result<u32> {
-        unsafe {
-            let mut num_frag_outputs : GLint = 0;
-            println!("getProgramInteraceiv");
-            gl::GetProgramInterfaceiv(self.program_handle, gl::PROGRAM_OUTPUT, gl::ACTIVE_RESOURCES, &mut num_frag_outputs);
-            validate_gl()?;
-            Ok(num_frag_outputs as u32)
+    fn transpose(&self) -> GLboolean {
+        match self.matrix_layout {
+            GlMatrixLayout::RowMajor => gl::TRUE,
+            GlMatrixLayout::ColumnMajor => gl::FALSE
+        }
+    }
+
+}
+
+///
+/// Checks that `uniform` can be assigned to the reflected type and size at
+/// `location`, describing the mismatch if not.
+///
+fn check_uniform(location : &GlUniformLocation, uniform : &GlShaderUniform) -> ::std::result::Result<(), String> {
+    let glsl_type = location.datatype;
+    if !uniform.matches_glsl_type(glsl_type) {
+        return Err(format!("has GLSL type {}, can't set it from {}", glsl_type, uniform.type_name()));
+    }
+    if let GlShaderUniform::Array(ref array) = *uniform {
+        if array.len() > location.size as usize {
+            return Err(format!("has {} elements from this location, can't set {} elements",
+                               location.size, array.len()));
         }
     }
+    Ok(())
+}
 
+///
+/// Whether a value of GLSL type `value` can be set to a uniform of type
+/// `target`. Besides exact matches, booleans can be set from any scalar or
+/// vector of the same size, and samplers and images from texture unit ints.
+///
+fn glsl_assignable(value : GlslType, target : GlslType) -> bool {
+    if value == target {
+        return true;
+    }
+    match (value, target) {
+        (GlslType::Float, GlslType::Bool) | (GlslType::Int, GlslType::Bool) | (GlslType::UInt, GlslType::Bool) |
+        (GlslType::Vec2, GlslType::BVec2) | (GlslType::IVec2, GlslType::BVec2) | (GlslType::UVec2, GlslType::BVec2) |
+        (GlslType::Vec3, GlslType::BVec3) | (GlslType::IVec3, GlslType::BVec3) | (GlslType::UVec3, GlslType::BVec3) |
+        (GlslType::Vec4, GlslType::BVec4) | (GlslType::IVec4, GlslType::BVec4) | (GlslType::UVec4, GlslType::BVec4) => true,
+        (GlslType::Int, target) => target.is_sampler() || target.is_image(),
+        _ => false
+    }
 }
 
 impl Drop for GlShader {
@@ -264,15 +641,68 @@ impl Drop for GlShader {
     }
 }
 
-fn load_shader_prog(shader_type : gl::types::GLenum, source : &str) -> Result<gl::types::GLuint> {
+///
+/// Checks the link status of a linked program, returning the info log as
+/// the error if linking failed or the driver reported anything.
+///
+pub fn check_link_status(program_id : GLuint) -> Result<()> {
     unsafe {
+        let mut link_result : gl::types::GLint = 0;
+        gl::GetProgramiv(program_id, gl::LINK_STATUS, &mut link_result);
+
+        let mut log_length = 0;
+        gl::GetProgramiv(program_id, gl::INFO_LOG_LENGTH, &mut log_length);
+        let error_str = if log_length > 0 {
+            let mut error_msg = Vec::with_capacity((log_length+1) as usize);
+            for _ in 1..log_length { error_msg.push(0); }
+            let error_msg_ptr = error_msg.as_mut_ptr();
+            gl::GetProgramInfoLog(program_id, log_length, ptr::null_mut(), error_msg_ptr);
+            String::from_utf8(error_msg.iter().map(|&c| c as u8).collect())?
+        } else {
+            String::from("")
+        };
+
+        if !error_str.is_empty() {
+            Err(GlError::new(error_str))
+        } else {
+            match link_result {
+                0 => Err(GlError::new(error_str)),
+                _ => Ok(())
+            }
+        }
+    }
+}
+
+pub fn load_shader_prog(shader_type : gl::types::GLenum, source : &str) -> Result<gl::types::GLuint> {
+    #[cfg(feature="validate")]
+    validate_glsl(shader_type, source)?;
+
+    let vs_source_c = CString::new(source)?;
+    let id_shader = unsafe {
         let id_shader = gl::CreateShader(shader_type);
 
-        let vs_source_c = CString::new(source).unwrap();
         let vs_source_arr_c = Box::into_raw(Box::new(vs_source_c.as_ptr()));
         gl::ShaderSource(id_shader, 1, vs_source_arr_c, ptr::null());
         gl::CompileShader(id_shader);
+        id_shader
+    };
+    match check_compile_status(id_shader) {
+        Ok(()) => Ok(id_shader),
+        Err(err) => {
+            unsafe {
+                gl::DeleteShader(id_shader);
+            }
+            Err(err)
+        }
+    }
+}
 
+///
+/// Checks the compile status of a shader object, returning the info log as
+/// the error if compiling failed.
+///
+pub fn check_compile_status(id_shader : GLuint) -> Result<()> {
+    unsafe {
         let mut result : gl::types::GLint = 0;
 
         gl::GetShaderiv(id_shader, gl::COMPILE_STATUS, &mut result);
@@ -288,7 +718,7 @@ fn load_shader_prog(shader_type : gl::types::GLenum, source : &str) -> Result<gl
                 Err(GlError::new(error_str))
             }
             _ => {
-                Ok(id_shader)
+                Ok(())
             }
         }
     }
@@ -0,0 +1,155 @@
+extern crate gl;
+use gl::types::*;
+use gl_err::*;
+use gl_shaders::check_compile_status;
+use gl_shader_builder::GlShaderStage;
+
+use std::ffi::CString;
+use std::mem;
+use std::os::raw::c_void;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// GL 4.6 / ARB_gl_spirv, not in the generated bindings.
+const SHADER_BINARY_FORMAT_SPIR_V : GLenum = 0x9551;
+
+const SPIRV_MAGIC : u32 = 0x0723_0203;
+
+type SpecializeShaderFn = extern "system" fn(GLuint, *const GLchar, GLuint, *const GLuint, *const GLuint);
+
+static SPECIALIZE_SHADER : AtomicUsize = AtomicUsize::new(0);
+
+///
+/// Loads the SPIR-V entry points missing from the `gl` bindings. Call it with
+/// the same loader as `gl::load_with`:
+///
+/// ```ignore
+/// gl::load_with(|s| window.get_proc_address(s) as *const _);
+/// glust::load_spirv_with(|s| window.get_proc_address(s) as *const _);
+/// ```
+///
+pub fn load_spirv_with<F>(mut loadfn : F) where F : FnMut(&'static str) -> *const c_void {
+    let mut function = loadfn("glSpecializeShader");
+    if function.is_null() {
+        function = loadfn("glSpecializeShaderARB");
+    }
+    SPECIALIZE_SHADER.store(function as usize, Ordering::SeqCst);
+}
+
+///
+/// A SPIR-V shader module with the entry point and specialization constants
+/// to compile it with. Stages of a program are added with
+/// `GlShaderBuilder::stage_spirv`; all stages of a program must be SPIR-V.
+///
+/// SPIR-V compilers may strip names, in which case uniforms can't be set by
+/// name; compile with debug names kept (e.g. `glslangValidator -g`).
+///
+#[derive(Debug, Clone)]
+pub struct GlSpirvModule {
+    binary : Vec<u8>,
+    entry_point : String,
+    constants : Vec<(u32, u32)>
+}
+
+impl GlSpirvModule {
+    ///
+    /// Wraps a SPIR-V binary as read from a `.spv` file, using entry point
+    /// `main` and no specialization.
+    ///
+    pub fn new(binary : &[u8]) -> Result<GlSpirvModule> {
+        if binary.len() < 20 || binary.len() % 4 != 0 {
+            return Err(GlError::new(format!("Not a SPIR-V binary: size {} isn't whole words", binary.len())));
+        }
+        let magic = (binary[0] as u32) | (binary[1] as u32) << 8 | (binary[2] as u32) << 16 | (binary[3] as u32) << 24;
+        if magic != SPIRV_MAGIC && magic.swap_bytes() != SPIRV_MAGIC {
+            return Err(GlError::new(format!("Not a SPIR-V binary: magic number {:08x}", magic)));
+        }
+        Ok(GlSpirvModule {
+            binary: binary.to_vec(),
+            entry_point: "main".to_string(),
+            constants: Vec::new()
+        })
+    }
+
+    ///
+    /// Like `new`, for a binary as 32-bit words as produced by shader compiler
+    /// libraries.
+    ///
+    pub fn from_words(words : &[u32]) -> Result<GlSpirvModule> {
+        let mut binary = Vec::with_capacity(words.len() * 4);
+        for &word in words {
+            binary.extend_from_slice(&[word as u8, (word >> 8) as u8, (word >> 16) as u8, (word >> 24) as u8]);
+        }
+        GlSpirvModule::new(&binary)
+    }
+
+    ///
+    /// Sets the entry point function used for the stage.
+    ///
+    pub fn entry_point(mut self, name : &str) -> Self {
+        self.entry_point = name.to_string();
+        self
+    }
+
+    ///
+    /// Sets the specialization constant with `constant_id` to the raw 32-bit
+    /// `value`, replacing any earlier value.
+    ///
+    pub fn specialize(mut self, constant_id : u32, value : u32) -> Self {
+        self.constants.retain(|&(id, _)| id != constant_id);
+        self.constants.push((constant_id, value));
+        self
+    }
+
+    pub fn specialize_i32(self, constant_id : u32, value : i32) -> Self {
+        self.specialize(constant_id, value as u32)
+    }
+
+    pub fn specialize_f32(self, constant_id : u32, value : f32) -> Self {
+        self.specialize(constant_id, value.to_bits())
+    }
+
+    pub fn specialize_bool(self, constant_id : u32, value : bool) -> Self {
+        self.specialize(constant_id, value as u32)
+    }
+
+    ///
+    /// Text identifying the module for the program binary cache key.
+    ///
+    pub fn cache_key_text(&self) -> String {
+        let mut text = format!("spirv {} {:?}\n", self.entry_point, self.constants);
+        for byte in &self.binary {
+            text.push_str(&format!("{:02x}", byte));
+        }
+        text
+    }
+
+    ///
+    /// Creates a shader object of `stage` from the module and specializes it.
+    /// Returns the driver's log on failure.
+    ///
+    pub fn load(&self, stage : GlShaderStage) -> Result<GLuint> {
+        let function = SPECIALIZE_SHADER.load(Ordering::SeqCst);
+        if function == 0 {
+            return Err(GlError::new("glSpecializeShader isn't loaded; call load_spirv_with along with gl::load_with".to_string()));
+        }
+        let specialize_shader : SpecializeShaderFn = unsafe { mem::transmute(function) };
+        let entry_point = CString::new(&self.entry_point[..])?;
+        let indices : Vec<GLuint> = self.constants.iter().map(|&(id, _)| id).collect();
+        let values : Vec<GLuint> = self.constants.iter().map(|&(_, value)| value).collect();
+
+        let id_shader = unsafe {
+            let id_shader = gl::CreateShader(stage.gl_enum());
+            gl::ShaderBinary(1, &id_shader, SHADER_BINARY_FORMAT_SPIR_V,
+                             self.binary.as_ptr() as *const c_void, self.binary.len() as GLsizei);
+            specialize_shader(id_shader, entry_point.as_ptr(), indices.len() as GLuint, indices.as_ptr(), values.as_ptr());
+            id_shader
+        };
+        if let Err(err) = validate_gl().and_then(|_| check_compile_status(id_shader)) {
+            unsafe {
+                gl::DeleteShader(id_shader);
+            }
+            return Err(err);
+        }
+        Ok(id_shader)
+    }
+}
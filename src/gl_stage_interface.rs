@@ -0,0 +1,181 @@
+extern crate gl;
+use gl::types::*;
+use gl_err::*;
+use gl_shaders::GlShader;
+use gl_shader_builder::GlShaderStage;
+use glsl_parser::*;
+use glsl_types::GlslType;
+
+use std::collections::HashMap;
+
+///
+/// A compiled stage to check, with its GLSL source if it has one.
+///
+pub struct StageInterface<'a> {
+    pub stage : GlShaderStage,
+    pub shader_handle : GLuint,
+    pub source : Option<&'a str>
+}
+
+///
+/// Interface variable as seen from one side: the reflected type, and the
+/// interpolation and explicit location declared in the source. Both are
+/// `None` if the source is SPIR-V or doesn't parse.
+///
+struct Varying {
+    name : String,
+    datatype : GlslType,
+    interpolation : Option<String>,
+    location : Option<u32>
+}
+
+///
+/// Checks that the inputs of each stage match the outputs of the stage before
+/// it in name, type and interpolation. Each stage is linked alone as a
+/// separable program for reflection; stages that can't be are skipped and
+/// left to the link log. All mismatches are reported in one error.
+///
+pub fn check_stage_interfaces(stages : &[StageInterface]) -> Result<()> {
+    let order = [GlShaderStage::Vertex, GlShaderStage::TessControl, GlShaderStage::TessEvaluation,
+                 GlShaderStage::Geometry, GlShaderStage::Fragment];
+    let mut pipeline : Vec<&StageInterface> = Vec::new();
+    for stage in &order {
+        if let Some(interface) = stages.iter().find(|s| s.stage == *stage) {
+            pipeline.push(interface);
+        }
+    }
+
+    let mut errors = Vec::new();
+    for pair in pipeline.windows(2) {
+        let (producer, consumer) = (pair[0], pair[1]);
+        let outputs = match reflect_varyings(producer, GlslStorage::Out)? {
+            Some(outputs) => outputs,
+            None => continue
+        };
+        let inputs = match reflect_varyings(consumer, GlslStorage::In)? {
+            Some(inputs) => inputs,
+            None => continue
+        };
+
+        for input in &inputs {
+            let output = outputs.iter().find(|o| o.name == input.name)
+                .or_else(|| input.location.and_then(|l| outputs.iter().find(|o| o.location == Some(l))));
+            let output = match output {
+                Some(output) => output,
+                None => {
+                    errors.push(format!("{} stage has no output matching {} input `{}` ({})",
+                                        capitalized(producer.stage), consumer.stage, input.name, input.datatype));
+                    continue;
+                }
+            };
+            if output.datatype != input.datatype {
+                errors.push(format!("{} output `{}` is {}, but {} input `{}` is {}",
+                                    capitalized(producer.stage), output.name, output.datatype,
+                                    consumer.stage, input.name, input.datatype));
+            }
+            // Unknown on either side (SPIR-V or unparsed source): leave it to the linker
+            if let (Some(output_interpolation), Some(input_interpolation)) = (&output.interpolation, &input.interpolation) {
+                if output_interpolation != input_interpolation {
+                    errors.push(format!("{} output `{}` is {}, but {} input `{}` is {}",
+                                        capitalized(producer.stage), output.name, output_interpolation,
+                                        consumer.stage, input.name, input_interpolation));
+                }
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(GlError::new(errors.join("\n")))
+    }
+}
+
+fn capitalized(stage : GlShaderStage) -> String {
+    let name = stage.name();
+    name[..1].to_uppercase() + &name[1..]
+}
+
+///
+/// Reflects the user-defined inputs or outputs of a stage. Returns `None` if
+/// the stage doesn't link on its own.
+///
+fn reflect_varyings(interface : &StageInterface, storage : GlslStorage) -> Result<Option<Vec<Varying>>> {
+    let program_handle = unsafe {
+        let program_handle = gl::CreateProgram();
+        gl::ProgramParameteri(program_handle, gl::PROGRAM_SEPARABLE, gl::TRUE as GLint);
+        gl::AttachShader(program_handle, interface.shader_handle);
+        gl::LinkProgram(program_handle);
+        gl::DetachShader(program_handle, interface.shader_handle);
+        let mut link_result : GLint = 0;
+        gl::GetProgramiv(program_handle, gl::LINK_STATUS, &mut link_result);
+        if link_result == 0 {
+            gl::DeleteProgram(program_handle);
+            return Ok(None);
+        }
+        program_handle
+    };
+    let program = GlShader::from_program(program_handle)?;
+    let variables = if storage == GlslStorage::In { program.get_program_inputs()? } else { program.get_program_outputs()? };
+
+    let declared = interface.source
+        .and_then(|source| parse_glsl(source).ok())
+        .map(|unit| declared_qualifiers(&unit, storage))
+        .unwrap_or_default();
+
+    let mut varyings = Vec::new();
+    for variable in variables {
+        let name = if variable.name.ends_with("[0]") {
+            variable.name[..variable.name.len() - 3].to_string()
+        } else {
+            variable.name
+        };
+        if name.starts_with("gl_") || name.contains(".gl_") {
+            continue;
+        }
+        let (interpolation, location) = match declared.get(&name) {
+            Some(&(ref interpolation, location)) => (Some(interpolation.clone()), location),
+            None => (None, None)
+        };
+        varyings.push(Varying {
+            name: name,
+            datatype: variable.datatype,
+            interpolation: interpolation,
+            location: location
+        });
+    }
+    Ok(Some(varyings))
+}
+
+///
+/// Interpolation qualifier and explicit location of each input or output
+/// variable and block member declared in a stage, keyed by reflection name.
+///
+fn declared_qualifiers(unit : &GlslTranslationUnit, storage : GlslStorage) -> HashMap<String, (String, Option<u32>)> {
+    let mut declared = HashMap::new();
+    let storage_matches = |qualifiers : &GlslQualifiers| {
+        qualifiers.storage == Some(storage) || qualifiers.storage == Some(GlslStorage::Varying)
+    };
+    let location = |qualifiers : &GlslQualifiers| qualifiers.layout_value("location").and_then(|l| l.trim().parse().ok());
+    let interpolation = |qualifiers : &GlslQualifiers, default : &str| {
+        qualifiers.interpolation.clone().unwrap_or_else(|| default.to_string())
+    };
+
+    for variable in unit.variables() {
+        if storage_matches(&variable.qualifiers) {
+            declared.insert(variable.name.clone(),
+                            (interpolation(&variable.qualifiers, "smooth"), location(&variable.qualifiers)));
+        }
+    }
+    for block in unit.blocks() {
+        if !storage_matches(&block.qualifiers) {
+            continue;
+        }
+        let block_interpolation = interpolation(&block.qualifiers, "smooth");
+        for member in &block.members {
+            declared.insert(format!("{}.{}", block.name, member.name),
+                            (interpolation(&member.qualifiers, &block_interpolation), location(&member.qualifiers)));
+        }
+    }
+    declared
+}
@@ -2,54 +2,329 @@ extern crate gl;
 use gl::types::*;
 use std::os::raw::c_void;
 use gl_err::*;
+use gl_program_cache::gl_string;
+use gl_texture_format::{pixel_size, TextureFormat};
+use std::mem;
 use std::ptr;
 
+///
+/// Element types pixels can be read back into: plain numbers for which any
+/// bit pattern the driver writes is a valid value. Implemented for `u8`,
+/// `i8`, `u16`, `i16`, `u32`, `i32` and `f32` only.
+///
+pub trait GlPixelElement : Copy + Default + sealed::Sealed {}
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+macro_rules! pixel_elements {
+    ($($t:ty),*) => {
+        $(
+            impl sealed::Sealed for $t {}
+            impl GlPixelElement for $t {}
+        )*
+    }
+}
+
+pixel_elements!(u8, i8, u16, i16, u32, i32, f32);
+
 pub struct GlTexture {
-    pub handle : GLuint
+    pub handle : GLuint,
+    pub width : usize,
+    pub height : usize,
+    pub format : TextureFormat
 }
 
 impl GlTexture {
 
     pub fn new_rgba8_empty(width : usize, height : usize) -> Result<GlTexture> {
-        Self::new_empty(width, height, gl::RGBA, gl::RGBA, gl::UNSIGNED_BYTE)
+        Self::new_empty(width, height, TextureFormat::RGBA8)
     }
     pub fn new_f32_empty(width : usize, height : usize) -> Result<GlTexture> {
-        Self::new_empty(width, height, gl::RED, gl::RED, gl::FLOAT)
+        Self::new_empty(width, height, TextureFormat::R32F)
     }
 
     pub fn new_rgba8(width : usize, height : usize, data_rgba : &[u8]) -> Result<GlTexture> {
+        Self::new_with_data(width, height, TextureFormat::RGBA8, data_rgba)
+    }
+
+    ///
+    /// Creates a texture from tightly packed pixels in the upload layout of
+    /// `format`, e.g. `&[f32]` for `RGBA32F` or `&[u16]` halfs for `RG16F`.
+    /// Filtering is linear, or nearest for integer and depth formats.
+    ///
+    pub fn new_with_data<T : Copy>(width : usize, height : usize, format : TextureFormat, data : &[T]) -> Result<GlTexture> {
+        let data_size = mem::size_of_val(data);
+        if data_size != format.image_size(width, height) {
+            return Err(GlError::new(format!("{}x{} {} texture needs {} bytes of data, got {}",
+                                            width, height, format, format.image_size(width, height), data_size)));
+        }
+        let filter = if format.is_integer() || format.is_depth() { gl::NEAREST } else { gl::LINEAR };
+        Self::new_impl(width, height, format, data.as_ptr() as *const c_void, filter)
+    }
+
+    ///
+    /// Creates a texture with undefined contents, e.g. for a render target.
+    ///
+    pub fn new_empty(width : usize, height : usize, format : TextureFormat) -> Result<GlTexture> {
+        Self::new_impl(width, height, format, ptr::null(), gl::NEAREST)
+    }
+
+    fn new_impl(width : usize, height : usize, format : TextureFormat, data : *const c_void, filter : GLenum) -> Result<GlTexture> {
         let mut tex_id : GLuint = 0;
         unsafe {
             gl::GenTextures(1, &mut tex_id);
             gl::BindTexture(gl::TEXTURE_2D, tex_id);
-            gl::TexImage2D(gl::TEXTURE_2D, 0, gl::RGBA8 as i32, width as i32, height as i32, 0,
-                           gl::RGBA, gl::UNSIGNED_BYTE, data_rgba.as_ptr() as *const c_void );
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+            // Rows of tightly packed data aren't 4-byte aligned in general.
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            gl::TexImage2D(gl::TEXTURE_2D, 0, format.internal_format() as i32, width as i32, height as i32, 0,
+                           format.upload_format(), format.upload_type(), data);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, filter as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, filter as i32);
         }
 
         match validate_gl() {
             Err(s) => Err(s),
-            _ => Ok(GlTexture{ handle: tex_id }),
+            _ => Ok(GlTexture{ handle: tex_id, width: width, height: height, format: format }),
         }
     }
 
-    fn new_empty(width : usize, height : usize, internalformat : GLenum, format : GLenum, txtype : GLenum) -> Result<GlTexture> {
-        let mut tex_id : GLuint = 0;
+    ///
+    /// Size of mip `level`, halved per level down to 1.
+    ///
+    pub fn level_size(&self, level : usize) -> (usize, usize) {
+        ((self.width >> level).max(1), (self.height >> level).max(1))
+    }
+
+    ///
+    /// Reads mip `level` back as tightly packed pixels in the upload layout
+    /// of the format, bottom row first. `T` is the element type to read into,
+    /// e.g. `u8` for `RGBA8` or `f32` for `RGBA32F`; one pixel may span
+    /// several elements.
+    ///
+    /// On OpenGL ES the pixels are read through a framebuffer and converted,
+    /// which needs a color-renderable format. Depth textures and the packed
+    /// float types of `R11G11B10F` and `RGB9E5` can't be read there.
+    ///
+    pub fn read_pixels<T : GlPixelElement>(&self, level : usize) -> Result<Vec<T>> {
+        self.read_pixels_as(level, self.format.upload_format(), self.format.upload_type())
+    }
+
+    ///
+    /// Like `read_pixels`, converting to another (format, type) pair the
+    /// driver supports for the texture, e.g. `(gl::RGBA, gl::FLOAT)`.
+    ///
+    pub fn read_pixels_as<T : GlPixelElement>(&self, level : usize, format : GLenum, data_type : GLenum)
+                                              -> Result<Vec<T>> {
+        let bytes_per_pixel = match pixel_size(format, data_type) {
+            Some(size) => size,
+            None => return Err(GlError::new(format!("Can't read pixels as format {:#x} with type {:#x}",
+                                                    format, data_type)))
+        };
+        let (width, height) = self.level_size(level);
+        let size = width * height * bytes_per_pixel;
+        if size % mem::size_of::<T>() != 0 {
+            return Err(GlError::new(format!("{} byte pixel rows can't be read into elements of {} bytes",
+                                            bytes_per_pixel, mem::size_of::<T>())));
+        }
+        let mut pixels = vec![T::default(); size / mem::size_of::<T>()];
+
+        if is_gles() {
+            let bytes = self.read_pixels_es(level, format, data_type, bytes_per_pixel)?;
+            unsafe {
+                ptr::copy_nonoverlapping(bytes.as_ptr(), pixels.as_mut_ptr() as *mut u8, size);
+            }
+            return Ok(pixels);
+        }
         unsafe {
-            gl::GenTextures(1, &mut tex_id);
-            gl::BindTexture(gl::TEXTURE_2D, tex_id);
-            gl::TexImage2D(gl::TEXTURE_2D, 0, internalformat as i32, width as i32, height as i32, 0,
-                           format, txtype, ptr::null() );
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
-            //gl::GenerateMipmap(gl::TEXTURE_2D);
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl::BindTexture(gl::TEXTURE_2D, self.handle);
+            gl::GetTexImage(gl::TEXTURE_2D, level as GLint, format, data_type, pixels.as_mut_ptr() as *mut c_void);
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 4);
         }
+        validate_gl()?;
+        Ok(pixels)
+    }
 
-        match validate_gl() {
-            Err(s) => Err(s),
-            _ => Ok(GlTexture{ handle: tex_id }),
+    ///
+    /// GLES has no glGetTexImage, and its glReadPixels reliably reads only
+    /// RGBA as unsigned bytes, floats or 32-bit integers. Reads mip `level`
+    /// through a framebuffer in one of those and converts the pixels to
+    /// (`format`, `data_type`).
+    ///
+    fn read_pixels_es(&self, level : usize, format : GLenum, data_type : GLenum, bytes_per_pixel : usize)
+                      -> Result<Vec<u8>> {
+        if self.format.is_depth() {
+            return Err(GlError::new(format!("Can't read back {} textures on OpenGL ES", self.format)));
+        }
+        let integer = is_integer_format(format);
+        if integer != self.format.is_integer() {
+            return Err(GlError::new(format!("Can't read {} texture as format {:#x}", self.format, format)));
+        }
+        let order : &[usize] = match format {
+            gl::RED | gl::RED_INTEGER => &[0],
+            gl::GREEN | gl::GREEN_INTEGER => &[1],
+            gl::BLUE | gl::BLUE_INTEGER => &[2],
+            gl::ALPHA => &[3],
+            gl::RG | gl::RG_INTEGER => &[0, 1],
+            gl::RGB | gl::RGB_INTEGER => &[0, 1, 2],
+            gl::BGR | gl::BGR_INTEGER => &[2, 1, 0],
+            gl::RGBA | gl::RGBA_INTEGER => &[0, 1, 2, 3],
+            gl::BGRA | gl::BGRA_INTEGER => &[2, 1, 0, 3],
+            _ => return Err(GlError::new(format!("Can't read pixels as format {:#x} on OpenGL ES", format)))
+        };
+
+        let (width, height) = self.level_size(level);
+        let count = width * height * 4;
+        let values : Vec<f64> = match self.format.upload_type() {
+            gl::UNSIGNED_BYTE if !integer => {
+                let mut data = vec![0u8; count];
+                self.read_framebuffer(level, gl::RGBA, gl::UNSIGNED_BYTE, data.as_mut_ptr() as *mut c_void)?;
+                data.into_iter().map(|v| v as f64 / 255.0).collect()
+            }
+            gl::BYTE | gl::SHORT | gl::INT if integer => {
+                let mut data = vec![0i32; count];
+                self.read_framebuffer(level, gl::RGBA_INTEGER, gl::INT, data.as_mut_ptr() as *mut c_void)?;
+                data.into_iter().map(|v| v as f64).collect()
+            }
+            _ if integer => {
+                let mut data = vec![0u32; count];
+                self.read_framebuffer(level, gl::RGBA_INTEGER, gl::UNSIGNED_INT, data.as_mut_ptr() as *mut c_void)?;
+                data.into_iter().map(|v| v as f64).collect()
+            }
+            _ => {
+                let mut data = vec![0f32; count];
+                self.read_framebuffer(level, gl::RGBA, gl::FLOAT, data.as_mut_ptr() as *mut c_void)?;
+                data.into_iter().map(|v| v as f64).collect()
+            }
+        };
+
+        let mut bytes = Vec::with_capacity(width * height * bytes_per_pixel);
+        for pixel in values.chunks(4) {
+            if data_type == gl::UNSIGNED_INT_2_10_10_10_REV {
+                let packed = order.iter().zip(&[(10, 0), (10, 10), (10, 20), (2, 30)])
+                    .fold(0u32, |packed, (&c, &(bits, shift))| {
+                        let max = ((1u64 << bits) - 1) as f64;
+                        packed | (quantize(pixel[c], integer, 0.0, max) as u32) << shift
+                    });
+                bytes.extend_from_slice(&packed.to_ne_bytes());
+                continue;
+            }
+            for &c in order {
+                let value = pixel[c];
+                match data_type {
+                    gl::UNSIGNED_BYTE => bytes.push(quantize(value, integer, 0.0, 255.0) as u8),
+                    gl::BYTE => bytes.push(quantize(value, integer, -128.0, 127.0) as i8 as u8),
+                    gl::UNSIGNED_SHORT => {
+                        bytes.extend_from_slice(&(quantize(value, integer, 0.0, 65535.0) as u16).to_ne_bytes());
+                    }
+                    gl::SHORT => {
+                        bytes.extend_from_slice(&(quantize(value, integer, -32768.0, 32767.0) as i16).to_ne_bytes());
+                    }
+                    gl::UNSIGNED_INT => {
+                        bytes.extend_from_slice(&(quantize(value, integer, 0.0, 4294967295.0) as u32).to_ne_bytes());
+                    }
+                    gl::INT => {
+                        let value = quantize(value, integer, -2147483648.0, 2147483647.0) as i32;
+                        bytes.extend_from_slice(&value.to_ne_bytes());
+                    }
+                    gl::FLOAT if !integer => bytes.extend_from_slice(&(value as f32).to_ne_bytes()),
+                    gl::HALF_FLOAT if !integer => bytes.extend_from_slice(&half_bits(value as f32).to_ne_bytes()),
+                    _ => return Err(GlError::new(format!("Can't read {} texture as type {:#x} on OpenGL ES",
+                                                         self.format, data_type)))
+                }
+            }
+        }
+        Ok(bytes)
+    }
+
+    ///
+    /// Reads mip `level` with glReadPixels through a temporary framebuffer.
+    /// `data` must hold a `level_size` image in (`format`, `data_type`).
+    ///
+    fn read_framebuffer(&self, level : usize, format : GLenum, data_type : GLenum, data : *mut c_void) -> Result<()> {
+        let (width, height) = self.level_size(level);
+        let status = unsafe {
+            let mut previous : GLint = 0;
+            let mut fb_handle : GLuint = 0;
+            gl::GetIntegerv(gl::READ_FRAMEBUFFER_BINDING, &mut previous);
+            gl::GenFramebuffers(1, &mut fb_handle);
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, fb_handle);
+            gl::FramebufferTexture2D(gl::READ_FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::TEXTURE_2D, self.handle,
+                                     level as GLint);
+            let status = gl::CheckFramebufferStatus(gl::READ_FRAMEBUFFER);
+            if status == gl::FRAMEBUFFER_COMPLETE {
+                gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+                gl::ReadPixels(0, 0, width as GLsizei, height as GLsizei, format, data_type, data);
+                gl::PixelStorei(gl::PACK_ALIGNMENT, 4);
+            }
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, previous as GLuint);
+            gl::DeleteFramebuffers(1, &fb_handle);
+            status
+        };
+        if status != gl::FRAMEBUFFER_COMPLETE {
+            return Err(GlError::new(format!("Can't read back {} textures: the driver can't render to them",
+                                            self.format)));
+        }
+        validate_gl()
+    }
+
+    ///
+    /// Replaces a `w` x `h` region of mip `level` at (`x`, `y`) with tightly
+    /// packed pixels in the upload layout of the format.
+    ///
+    pub fn update_region<T : Copy>(&self, x : usize, y : usize, w : usize, h : usize, data : &[T], level : usize) -> Result<()> {
+        let row_bytes = w * self.format.bytes_per_pixel();
+        self.update_region_strided(x, y, w, h, data, row_bytes, level)
+    }
+
+    ///
+    /// Like `update_region`, with rows `stride` bytes apart, e.g. a frame of
+    /// a video decoder with padded rows or a rectangle of a larger image.
+    /// The stride must be a whole number of pixels or a row padded to 2, 4
+    /// or 8 bytes.
+    ///
+    pub fn update_region_strided<T : Copy>(&self, x : usize, y : usize, w : usize, h : usize, data : &[T],
+                                           stride : usize, level : usize) -> Result<()> {
+        let (level_width, level_height) = self.level_size(level);
+        if x + w > level_width || y + h > level_height {
+            return Err(GlError::new(format!("Region {}x{} at ({}, {}) is outside the {}x{} level {}",
+                                            w, h, x, y, level_width, level_height, level)));
+        }
+        if w == 0 || h == 0 {
+            return Ok(());
+        }
+
+        let bytes_per_pixel = self.format.bytes_per_pixel();
+        let row_bytes = w * bytes_per_pixel;
+        let (alignment, row_length) = if stride % bytes_per_pixel == 0 && stride >= row_bytes {
+            (1, stride / bytes_per_pixel)
+        } else {
+            match [2, 4, 8].iter().find(|&&a| (row_bytes + a - 1) / a * a == stride) {
+                Some(&alignment) => (alignment, w),
+                None => return Err(GlError::new(format!(
+                    "Row stride of {} bytes isn't whole {} byte pixels or a padded row of {} bytes",
+                    stride, bytes_per_pixel, row_bytes)))
+            }
+        };
+        let needed = stride * (h - 1) + row_bytes;
+        if mem::size_of_val(data) < needed {
+            return Err(GlError::new(format!("Region {}x{} with stride {} needs {} bytes of data, got {}",
+                                            w, h, stride, needed, mem::size_of_val(data))));
+        }
+
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, self.handle);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, alignment as GLint);
+            gl::PixelStorei(gl::UNPACK_ROW_LENGTH, row_length as GLint);
+            gl::TexSubImage2D(gl::TEXTURE_2D, level as GLint, x as GLint, y as GLint, w as GLsizei, h as GLsizei,
+                              self.format.upload_format(), self.format.upload_type(), data.as_ptr() as *const c_void);
+            gl::PixelStorei(gl::UNPACK_ROW_LENGTH, 0);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
         }
+        validate_gl()
     }
 
     pub fn mipmapped(self) -> Self {
@@ -94,6 +369,79 @@ impl Drop for GlTexture {
     }
 }
 
+fn is_gles() -> bool {
+    gl_string(gl::VERSION).starts_with("OpenGL ES")
+}
 
+fn is_integer_format(format : GLenum) -> bool {
+    matches!(format, gl::RED_INTEGER | gl::GREEN_INTEGER | gl::BLUE_INTEGER | gl::RG_INTEGER | gl::RGB_INTEGER |
+                     gl::BGR_INTEGER | gl::RGBA_INTEGER | gl::BGRA_INTEGER)
+}
 
+///
+/// Converts a read back component to the range `min..=max` of the target
+/// type: integer values are clamped, normalized ones scaled and rounded.
+///
+fn quantize(value : f64, integer : bool, min : f64, max : f64) -> f64 {
+    if integer {
+        value.max(min).min(max)
+    } else {
+        let lowest = if min < 0.0 { -1.0 } else { 0.0 };
+        (value.max(lowest).min(1.0) * max).round()
+    }
+}
 
+///
+/// The nearest IEEE half float of `value`.
+///
+fn half_bits(value : f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+    if exponent == 0xff {
+        return sign | 0x7c00 | if mantissa != 0 { 0x200 } else { 0 };
+    }
+    let exponent = exponent - 127 + 15;
+    if exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+    if exponent <= 0 {
+        if exponent < -10 {
+            return sign;
+        }
+        // Subnormal: shift the implicit leading bit into the mantissa.
+        let mantissa = mantissa | 0x80_0000;
+        let shift = (14 - exponent) as u32;
+        return sign | ((mantissa >> shift) + ((mantissa >> (shift - 1)) & 1)) as u16;
+    }
+    // Rounding may carry into the exponent, which is the right result.
+    sign | ((((exponent as u32) << 10) | (mantissa >> 13)) + ((mantissa >> 12) & 1)) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn half_bits_round_to_nearest() {
+        assert_eq!(half_bits(0.0), 0x0000);
+        assert_eq!(half_bits(-0.0), 0x8000);
+        assert_eq!(half_bits(1.0), 0x3c00);
+        assert_eq!(half_bits(-2.0), 0xc000);
+        assert_eq!(half_bits(65504.0), 0x7bff);
+        assert_eq!(half_bits(1.0e6), 0x7c00);
+        assert_eq!(half_bits(5.960_464_5e-8), 0x0001);
+        assert_eq!(half_bits(1.0 / 3.0), 0x3555);
+        assert_eq!(half_bits(f32::NAN) & 0x7c00, 0x7c00);
+    }
+
+    #[test]
+    fn quantize_scales_normalized_values() {
+        assert_eq!(quantize(0.5, false, 0.0, 255.0), 128.0);
+        assert_eq!(quantize(-0.5, false, 0.0, 255.0), 0.0);
+        assert_eq!(quantize(-1.0, false, -128.0, 127.0), -127.0);
+        assert_eq!(quantize(300.0, true, 0.0, 255.0), 255.0);
+        assert_eq!(quantize(-5.0, true, -128.0, 127.0), -5.0);
+    }
+}
@@ -0,0 +1,287 @@
+extern crate gl;
+use gl::types::*;
+
+use std::fmt;
+
+///
+/// Sized internal format of a texture, with the (format, type) pair used to
+/// upload and read back its pixels. Pixel data is tightly packed in that
+/// layout: `bytes_per_pixel` bytes per pixel, 16-bit float formats as halfs.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TextureFormat {
+    R8,
+    RG8,
+    RGB8,
+    RGBA8,
+    R8Snorm,
+    RG8Snorm,
+    RGBA8Snorm,
+    R16,
+    RG16,
+    RGB16,
+    RGBA16,
+    SRGB8,
+    SRGB8Alpha8,
+    RGB10A2,
+    R16F,
+    RG16F,
+    RGB16F,
+    RGBA16F,
+    R32F,
+    RG32F,
+    RGB32F,
+    RGBA32F,
+    R11G11B10F,
+    RGB9E5,
+    R8UI,
+    RG8UI,
+    RGBA8UI,
+    R8I,
+    RG8I,
+    RGBA8I,
+    R16UI,
+    RG16UI,
+    RGBA16UI,
+    R16I,
+    RG16I,
+    RGBA16I,
+    R32UI,
+    RG32UI,
+    RGBA32UI,
+    R32I,
+    RG32I,
+    RGBA32I,
+    RGB10A2UI,
+    Depth16,
+    Depth24,
+    Depth32F,
+    Depth24Stencil8,
+    Depth32FStencil8
+}
+
+impl TextureFormat {
+    ///
+    /// (internal format, upload format, upload type, bytes per pixel).
+    ///
+    fn info(&self) -> (GLenum, GLenum, GLenum, usize) {
+        match *self {
+            TextureFormat::R8 => (gl::R8, gl::RED, gl::UNSIGNED_BYTE, 1),
+            TextureFormat::RG8 => (gl::RG8, gl::RG, gl::UNSIGNED_BYTE, 2),
+            TextureFormat::RGB8 => (gl::RGB8, gl::RGB, gl::UNSIGNED_BYTE, 3),
+            TextureFormat::RGBA8 => (gl::RGBA8, gl::RGBA, gl::UNSIGNED_BYTE, 4),
+            TextureFormat::R8Snorm => (gl::R8_SNORM, gl::RED, gl::BYTE, 1),
+            TextureFormat::RG8Snorm => (gl::RG8_SNORM, gl::RG, gl::BYTE, 2),
+            TextureFormat::RGBA8Snorm => (gl::RGBA8_SNORM, gl::RGBA, gl::BYTE, 4),
+            TextureFormat::R16 => (gl::R16, gl::RED, gl::UNSIGNED_SHORT, 2),
+            TextureFormat::RG16 => (gl::RG16, gl::RG, gl::UNSIGNED_SHORT, 4),
+            TextureFormat::RGB16 => (gl::RGB16, gl::RGB, gl::UNSIGNED_SHORT, 6),
+            TextureFormat::RGBA16 => (gl::RGBA16, gl::RGBA, gl::UNSIGNED_SHORT, 8),
+            TextureFormat::SRGB8 => (gl::SRGB8, gl::RGB, gl::UNSIGNED_BYTE, 3),
+            TextureFormat::SRGB8Alpha8 => (gl::SRGB8_ALPHA8, gl::RGBA, gl::UNSIGNED_BYTE, 4),
+            TextureFormat::RGB10A2 => (gl::RGB10_A2, gl::RGBA, gl::UNSIGNED_INT_2_10_10_10_REV, 4),
+            TextureFormat::R16F => (gl::R16F, gl::RED, gl::HALF_FLOAT, 2),
+            TextureFormat::RG16F => (gl::RG16F, gl::RG, gl::HALF_FLOAT, 4),
+            TextureFormat::RGB16F => (gl::RGB16F, gl::RGB, gl::HALF_FLOAT, 6),
+            TextureFormat::RGBA16F => (gl::RGBA16F, gl::RGBA, gl::HALF_FLOAT, 8),
+            TextureFormat::R32F => (gl::R32F, gl::RED, gl::FLOAT, 4),
+            TextureFormat::RG32F => (gl::RG32F, gl::RG, gl::FLOAT, 8),
+            TextureFormat::RGB32F => (gl::RGB32F, gl::RGB, gl::FLOAT, 12),
+            TextureFormat::RGBA32F => (gl::RGBA32F, gl::RGBA, gl::FLOAT, 16),
+            TextureFormat::R11G11B10F => (gl::R11F_G11F_B10F, gl::RGB, gl::UNSIGNED_INT_10F_11F_11F_REV, 4),
+            TextureFormat::RGB9E5 => (gl::RGB9_E5, gl::RGB, gl::UNSIGNED_INT_5_9_9_9_REV, 4),
+            TextureFormat::R8UI => (gl::R8UI, gl::RED_INTEGER, gl::UNSIGNED_BYTE, 1),
+            TextureFormat::RG8UI => (gl::RG8UI, gl::RG_INTEGER, gl::UNSIGNED_BYTE, 2),
+            TextureFormat::RGBA8UI => (gl::RGBA8UI, gl::RGBA_INTEGER, gl::UNSIGNED_BYTE, 4),
+            TextureFormat::R8I => (gl::R8I, gl::RED_INTEGER, gl::BYTE, 1),
+            TextureFormat::RG8I => (gl::RG8I, gl::RG_INTEGER, gl::BYTE, 2),
+            TextureFormat::RGBA8I => (gl::RGBA8I, gl::RGBA_INTEGER, gl::BYTE, 4),
+            TextureFormat::R16UI => (gl::R16UI, gl::RED_INTEGER, gl::UNSIGNED_SHORT, 2),
+            TextureFormat::RG16UI => (gl::RG16UI, gl::RG_INTEGER, gl::UNSIGNED_SHORT, 4),
+            TextureFormat::RGBA16UI => (gl::RGBA16UI, gl::RGBA_INTEGER, gl::UNSIGNED_SHORT, 8),
+            TextureFormat::R16I => (gl::R16I, gl::RED_INTEGER, gl::SHORT, 2),
+            TextureFormat::RG16I => (gl::RG16I, gl::RG_INTEGER, gl::SHORT, 4),
+            TextureFormat::RGBA16I => (gl::RGBA16I, gl::RGBA_INTEGER, gl::SHORT, 8),
+            TextureFormat::R32UI => (gl::R32UI, gl::RED_INTEGER, gl::UNSIGNED_INT, 4),
+            TextureFormat::RG32UI => (gl::RG32UI, gl::RG_INTEGER, gl::UNSIGNED_INT, 8),
+            TextureFormat::RGBA32UI => (gl::RGBA32UI, gl::RGBA_INTEGER, gl::UNSIGNED_INT, 16),
+            TextureFormat::R32I => (gl::R32I, gl::RED_INTEGER, gl::INT, 4),
+            TextureFormat::RG32I => (gl::RG32I, gl::RG_INTEGER, gl::INT, 8),
+            TextureFormat::RGBA32I => (gl::RGBA32I, gl::RGBA_INTEGER, gl::INT, 16),
+            TextureFormat::RGB10A2UI => (gl::RGB10_A2UI, gl::RGBA_INTEGER, gl::UNSIGNED_INT_2_10_10_10_REV, 4),
+            TextureFormat::Depth16 => (gl::DEPTH_COMPONENT16, gl::DEPTH_COMPONENT, gl::UNSIGNED_SHORT, 2),
+            TextureFormat::Depth24 => (gl::DEPTH_COMPONENT24, gl::DEPTH_COMPONENT, gl::UNSIGNED_INT, 4),
+            TextureFormat::Depth32F => (gl::DEPTH_COMPONENT32F, gl::DEPTH_COMPONENT, gl::FLOAT, 4),
+            TextureFormat::Depth24Stencil8 => (gl::DEPTH24_STENCIL8, gl::DEPTH_STENCIL, gl::UNSIGNED_INT_24_8, 4),
+            TextureFormat::Depth32FStencil8 =>
+                (gl::DEPTH32F_STENCIL8, gl::DEPTH_STENCIL, gl::FLOAT_32_UNSIGNED_INT_24_8_REV, 8)
+        }
+    }
+
+    pub fn internal_format(&self) -> GLenum {
+        self.info().0
+    }
+
+    ///
+    /// Pixel format of uploaded and read back data, e.g. GL_RGBA.
+    ///
+    pub fn upload_format(&self) -> GLenum {
+        self.info().1
+    }
+
+    ///
+    /// Component type of uploaded and read back data, e.g. GL_UNSIGNED_BYTE.
+    ///
+    pub fn upload_type(&self) -> GLenum {
+        self.info().2
+    }
+
+    pub fn bytes_per_pixel(&self) -> usize {
+        self.info().3
+    }
+
+    ///
+    /// Size in bytes of `width` x `height` tightly packed pixels.
+    ///
+    pub fn image_size(&self, width : usize, height : usize) -> usize {
+        width * height * self.bytes_per_pixel()
+    }
+
+    pub fn channel_count(&self) -> usize {
+        match self.upload_format() {
+            gl::RED | gl::RED_INTEGER | gl::DEPTH_COMPONENT => 1,
+            gl::RG | gl::RG_INTEGER | gl::DEPTH_STENCIL => 2,
+            gl::RGB | gl::RGB_INTEGER => 3,
+            _ => 4
+        }
+    }
+
+    ///
+    /// Whether shaders sample the format with `isampler` or `usampler`. Such
+    /// textures can't be filtered linearly.
+    ///
+    pub fn is_integer(&self) -> bool {
+        match self.upload_format() {
+            gl::RED_INTEGER | gl::RG_INTEGER | gl::RGB_INTEGER | gl::RGBA_INTEGER => true,
+            _ => false
+        }
+    }
+
+    pub fn is_depth(&self) -> bool {
+        match self.upload_format() {
+            gl::DEPTH_COMPONENT | gl::DEPTH_STENCIL => true,
+            _ => false
+        }
+    }
+
+    pub fn has_stencil(&self) -> bool {
+        self.upload_format() == gl::DEPTH_STENCIL
+    }
+
+    pub fn is_srgb(&self) -> bool {
+        match *self {
+            TextureFormat::SRGB8 | TextureFormat::SRGB8Alpha8 => true,
+            _ => false
+        }
+    }
+
+    ///
+    /// The format of an internal format enum, if it is one of these.
+    ///
+    pub fn from_gl(internal_format : GLenum) -> Option<TextureFormat> {
+        ALL_FORMATS.iter().cloned().find(|format| format.internal_format() == internal_format)
+    }
+}
+
+///
+/// Number of components of a pixel transfer `format`, e.g. 3 for GL_RGB.
+///
+pub fn component_count(format : GLenum) -> Option<usize> {
+    Some(match format {
+        gl::RED | gl::GREEN | gl::BLUE | gl::ALPHA | gl::RED_INTEGER | gl::GREEN_INTEGER | gl::BLUE_INTEGER |
+        gl::DEPTH_COMPONENT | gl::STENCIL_INDEX => 1,
+        gl::RG | gl::RG_INTEGER | gl::DEPTH_STENCIL => 2,
+        gl::RGB | gl::BGR | gl::RGB_INTEGER | gl::BGR_INTEGER => 3,
+        gl::RGBA | gl::BGRA | gl::RGBA_INTEGER | gl::BGRA_INTEGER => 4,
+        _ => return None
+    })
+}
+
+///
+/// Bytes per pixel of tightly packed pixels in the transfer layout
+/// (`format`, `data_type`), or `None` for pairs GL doesn't accept.
+///
+pub fn pixel_size(format : GLenum, data_type : GLenum) -> Option<usize> {
+    let components = component_count(format)?;
+    // (type, components, bytes per pixel) of packed types.
+    let packed : &[(GLenum, usize, usize)] = &[
+        (gl::UNSIGNED_BYTE_3_3_2, 3, 1), (gl::UNSIGNED_BYTE_2_3_3_REV, 3, 1),
+        (gl::UNSIGNED_SHORT_5_6_5, 3, 2), (gl::UNSIGNED_SHORT_5_6_5_REV, 3, 2),
+        (gl::UNSIGNED_SHORT_4_4_4_4, 4, 2), (gl::UNSIGNED_SHORT_4_4_4_4_REV, 4, 2),
+        (gl::UNSIGNED_SHORT_5_5_5_1, 4, 2), (gl::UNSIGNED_SHORT_1_5_5_5_REV, 4, 2),
+        (gl::UNSIGNED_INT_8_8_8_8, 4, 4), (gl::UNSIGNED_INT_8_8_8_8_REV, 4, 4),
+        (gl::UNSIGNED_INT_10_10_10_2, 4, 4), (gl::UNSIGNED_INT_2_10_10_10_REV, 4, 4),
+        (gl::UNSIGNED_INT_10F_11F_11F_REV, 3, 4), (gl::UNSIGNED_INT_5_9_9_9_REV, 3, 4),
+        (gl::UNSIGNED_INT_24_8, 2, 4), (gl::FLOAT_32_UNSIGNED_INT_24_8_REV, 2, 8)
+    ];
+    if let Some(&(_, packed_components, size)) = packed.iter().find(|&&(t, _, _)| t == data_type) {
+        return if packed_components == components { Some(size) } else { None };
+    }
+    if format == gl::DEPTH_STENCIL {
+        return None;
+    }
+    let component_size = match data_type {
+        gl::UNSIGNED_BYTE | gl::BYTE => 1,
+        gl::UNSIGNED_SHORT | gl::SHORT | gl::HALF_FLOAT => 2,
+        gl::UNSIGNED_INT | gl::INT | gl::FLOAT => 4,
+        _ => return None
+    };
+    Some(components * component_size)
+}
+
+impl fmt::Display for TextureFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+const ALL_FORMATS : &[TextureFormat] = &[
+    TextureFormat::R8, TextureFormat::RG8, TextureFormat::RGB8, TextureFormat::RGBA8,
+    TextureFormat::R8Snorm, TextureFormat::RG8Snorm, TextureFormat::RGBA8Snorm,
+    TextureFormat::R16, TextureFormat::RG16, TextureFormat::RGB16, TextureFormat::RGBA16,
+    TextureFormat::SRGB8, TextureFormat::SRGB8Alpha8, TextureFormat::RGB10A2,
+    TextureFormat::R16F, TextureFormat::RG16F, TextureFormat::RGB16F, TextureFormat::RGBA16F,
+    TextureFormat::R32F, TextureFormat::RG32F, TextureFormat::RGB32F, TextureFormat::RGBA32F,
+    TextureFormat::R11G11B10F, TextureFormat::RGB9E5,
+    TextureFormat::R8UI, TextureFormat::RG8UI, TextureFormat::RGBA8UI,
+    TextureFormat::R8I, TextureFormat::RG8I, TextureFormat::RGBA8I,
+    TextureFormat::R16UI, TextureFormat::RG16UI, TextureFormat::RGBA16UI,
+    TextureFormat::R16I, TextureFormat::RG16I, TextureFormat::RGBA16I,
+    TextureFormat::R32UI, TextureFormat::RG32UI, TextureFormat::RGBA32UI,
+    TextureFormat::R32I, TextureFormat::RG32I, TextureFormat::RGBA32I,
+    TextureFormat::RGB10A2UI,
+    TextureFormat::Depth16, TextureFormat::Depth24, TextureFormat::Depth32F,
+    TextureFormat::Depth24Stencil8, TextureFormat::Depth32FStencil8
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pixel_sizes_match_the_upload_layouts() {
+        for &format in ALL_FORMATS {
+            assert_eq!(pixel_size(format.upload_format(), format.upload_type()), Some(format.bytes_per_pixel()),
+                       "{}", format);
+        }
+    }
+
+    #[test]
+    fn pixel_sizes_reject_unknown_pairs() {
+        assert_eq!(pixel_size(gl::RGBA, gl::FLOAT), Some(16));
+        assert_eq!(pixel_size(gl::RGB, gl::UNSIGNED_INT_2_10_10_10_REV), None);
+        assert_eq!(pixel_size(gl::DEPTH_STENCIL, gl::FLOAT), None);
+        assert_eq!(pixel_size(gl::RGBA, gl::RGBA), None);
+        assert_eq!(pixel_size(gl::FLOAT, gl::RGBA), None);
+    }
+}
@@ -0,0 +1,170 @@
+extern crate gl;
+use gl::types::*;
+use gl_err::*;
+use gl_texture::GlTexture;
+use gl_texture_format::TextureFormat;
+use image;
+use image::DynamicImage;
+
+use std::path::Path;
+
+///
+/// How the color channels of an 8-bit image are interpreted. Float images,
+/// such as HDR files, are always linear.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GlColorSpace {
+    ///
+    /// Color textures; sampling converts to linear.
+    ///
+    Srgb,
+    ///
+    /// Data textures such as normal maps, roughness or masks.
+    ///
+    Linear
+}
+
+///
+/// Options for loading image files into textures.
+///
+#[derive(Debug, Clone, Copy)]
+pub struct GlImageOptions {
+    pub color_space : GlColorSpace,
+    ///
+    /// Flips rows so the first row of the file is at `v = 1`, as GL expects.
+    ///
+    pub flip_vertically : bool,
+    pub mipmaps : bool
+}
+
+impl Default for GlImageOptions {
+    fn default() -> GlImageOptions {
+        GlImageOptions {
+            color_space: GlColorSpace::Srgb,
+            flip_vertically: false,
+            mipmaps: true
+        }
+    }
+}
+
+impl GlTexture {
+    ///
+    /// Loads a PNG, JPEG, TGA, BMP, HDR or EXR file, detected from its contents.
+    ///
+    pub fn from_image_file<P: AsRef<Path>>(path : P, options : GlImageOptions) -> Result<GlTexture> {
+        let image = image::open(path.as_ref())
+            .map_err(|err| GlError::new(format!("Can't load image {:?}: {}", path.as_ref(), err)))?;
+        GlTexture::from_image(&image, options)
+    }
+
+    ///
+    /// Decodes an image file held in memory, e.g. from `include_bytes!`.
+    ///
+    pub fn from_image_bytes(bytes : &[u8], options : GlImageOptions) -> Result<GlTexture> {
+        GlTexture::from_image(&image::load_from_memory(bytes)?, options)
+    }
+
+    ///
+    /// Uploads a decoded image. One and two channel images are swizzled to
+    /// read as gray and gray with alpha; sRGB has no such formats, so they
+    /// are expanded to RGB(A) for `GlColorSpace::Srgb`. 16-bit sRGB images are
+    /// reduced to 8 bits, as are images of other pixel types.
+    ///
+    pub fn from_image(image : &DynamicImage, options : GlImageOptions) -> Result<GlTexture> {
+        let flipped;
+        let image = if options.flip_vertically {
+            flipped = image.flipv();
+            &flipped
+        } else {
+            image
+        };
+        let srgb = options.color_space == GlColorSpace::Srgb;
+
+        let converted;
+        let (image, format, swizzle) = match *image {
+            DynamicImage::ImageLuma8(_) if !srgb => (image, TextureFormat::R8, Some(GRAY_SWIZZLE)),
+            DynamicImage::ImageLumaA8(_) if !srgb => (image, TextureFormat::RG8, Some(GRAY_ALPHA_SWIZZLE)),
+            DynamicImage::ImageLuma16(_) if !srgb => (image, TextureFormat::R16, Some(GRAY_SWIZZLE)),
+            DynamicImage::ImageLumaA16(_) if !srgb => (image, TextureFormat::RG16, Some(GRAY_ALPHA_SWIZZLE)),
+            DynamicImage::ImageRgb8(_) => (image, if srgb { TextureFormat::SRGB8 } else { TextureFormat::RGB8 }, None),
+            DynamicImage::ImageRgba8(_) =>
+                (image, if srgb { TextureFormat::SRGB8Alpha8 } else { TextureFormat::RGBA8 }, None),
+            DynamicImage::ImageRgb16(_) if !srgb => (image, TextureFormat::RGB16, None),
+            DynamicImage::ImageRgba16(_) if !srgb => (image, TextureFormat::RGBA16, None),
+            DynamicImage::ImageRgb32F(_) => (image, TextureFormat::RGB32F, None),
+            DynamicImage::ImageRgba32F(_) => (image, TextureFormat::RGBA32F, None),
+            DynamicImage::ImageLuma8(_) | DynamicImage::ImageLuma16(_) | DynamicImage::ImageRgb16(_) => {
+                converted = DynamicImage::ImageRgb8(image.to_rgb8());
+                (&converted, if srgb { TextureFormat::SRGB8 } else { TextureFormat::RGB8 }, None)
+            }
+            _ => {
+                converted = DynamicImage::ImageRgba8(image.to_rgba8());
+                (&converted, if srgb { TextureFormat::SRGB8Alpha8 } else { TextureFormat::RGBA8 }, None)
+            }
+        };
+
+        let texture = GlTexture::new_with_data(image.width() as usize, image.height() as usize, format, image.as_bytes())?;
+        if let Some(swizzle) = swizzle {
+            unsafe {
+                gl::BindTexture(gl::TEXTURE_2D, texture.handle);
+                gl::TexParameteriv(gl::TEXTURE_2D, gl::TEXTURE_SWIZZLE_RGBA, swizzle.as_ptr());
+            }
+            validate_gl()?;
+        }
+
+        Ok(if options.mipmaps { texture.mipmapped() } else { texture })
+    }
+}
+
+impl GlTexture {
+    ///
+    /// Saves mip `level` to an image file, in the format of the extension.
+    /// Rows are flipped so the file's top row is the texture's `v = 1`.
+    /// Float and depth textures keep their values in EXR files and are
+    /// clamped to [0, 1] in others. Integer textures can't be saved.
+    ///
+    pub fn save_image<P: AsRef<Path>>(&self, path : P, level : usize) -> Result<()> {
+        let path = path.as_ref();
+        let exr = path.extension().map_or(false, |ext| ext.eq_ignore_ascii_case("exr"));
+        let (width, height) = self.level_size(level);
+        let (width, height) = (width as u32, height as u32);
+        let size_error = || GlError::new(format!("Readback of {:?} doesn't match its size", path));
+
+        let upload_type = self.format.upload_type();
+        let image = if self.format.is_integer() {
+            return Err(GlError::new(format!("Can't save {} texture to {:?}", self.format, path)));
+        } else if self.format.is_depth() {
+            let depth : Vec<f32> = self.read_pixels_as(level, gl::DEPTH_COMPONENT, gl::FLOAT)?;
+            let mut rgba = Vec::with_capacity(depth.len() * 4);
+            for d in depth {
+                rgba.extend_from_slice(&[d, d, d, 1.0]);
+            }
+            DynamicImage::ImageRgba32F(image::Rgba32FImage::from_raw(width, height, rgba).ok_or_else(size_error)?)
+        } else if upload_type == gl::FLOAT || upload_type == gl::HALF_FLOAT
+            || upload_type == gl::UNSIGNED_INT_10F_11F_11F_REV || upload_type == gl::UNSIGNED_INT_5_9_9_9_REV {
+            let rgba = self.read_pixels_as(level, gl::RGBA, gl::FLOAT)?;
+            DynamicImage::ImageRgba32F(image::Rgba32FImage::from_raw(width, height, rgba).ok_or_else(size_error)?)
+        } else if upload_type == gl::UNSIGNED_SHORT {
+            let rgba = self.read_pixels_as(level, gl::RGBA, gl::UNSIGNED_SHORT)?;
+            DynamicImage::ImageRgba16(image::ImageBuffer::from_raw(width, height, rgba).ok_or_else(size_error)?)
+        } else if self.format.channel_count() == 1 {
+            let gray = self.read_pixels_as(level, gl::RED, gl::UNSIGNED_BYTE)?;
+            DynamicImage::ImageLuma8(image::GrayImage::from_raw(width, height, gray).ok_or_else(size_error)?)
+        } else {
+            let rgba = self.read_pixels_as(level, gl::RGBA, gl::UNSIGNED_BYTE)?;
+            DynamicImage::ImageRgba8(image::RgbaImage::from_raw(width, height, rgba).ok_or_else(size_error)?)
+        };
+
+        let image = image.flipv();
+        let image = match image {
+            DynamicImage::ImageRgba32F(_) if !exr => DynamicImage::ImageRgba8(image.to_rgba8()),
+            DynamicImage::ImageRgba32F(_) => image,
+            _ if exr => DynamicImage::ImageRgba32F(image.to_rgba32f()),
+            _ => image
+        };
+        image.save(path).map_err(|err| GlError::new(format!("Can't save image {:?}: {}", path, err)))
+    }
+}
+
+const GRAY_SWIZZLE : [GLint; 4] = [gl::RED as GLint, gl::RED as GLint, gl::RED as GLint, gl::ONE as GLint];
+const GRAY_ALPHA_SWIZZLE : [GLint; 4] = [gl::RED as GLint, gl::RED as GLint, gl::RED as GLint, gl::GREEN as GLint];
@@ -0,0 +1,196 @@
+extern crate gl;
+use gl::types::*;
+use gl_err::*;
+use gl_buffer::GlBufferRaw;
+use gl_shaders::GlShaderUniform;
+use gl_vertex_array::HasGlVertexArrayHandle;
+use gl_render::GlRenderProgram;
+use gl_render::apply_uniforms_to;
+
+///
+/// How captured varyings are laid out, set with
+/// `GlShaderBuilder::transform_feedback_varyings`.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GlFeedbackMode {
+    ///
+    /// All varyings in one buffer, one vertex after another.
+    ///
+    Interleaved,
+    ///
+    /// Each varying in its own buffer, bound at the varying's index.
+    ///
+    Separate
+}
+
+///
+/// Primitive type drawn and captured by `GlTransformFeedback::capture`.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GlFeedbackPrimitive {
+    Points,
+    Lines,
+    Triangles
+}
+
+///
+/// Transform feedback object, recording vertex outputs of a program built
+/// with `GlShaderBuilder::transform_feedback_varyings` into buffers:
+///
+/// ```ignore
+/// let feedback = GlTransformFeedback::new()?;
+/// feedback.bind_buffers(&[&particles_out.0])?;
+/// let written = feedback.capture(&update_shader, &particles_in_va, count, GlFeedbackPrimitive::Points, &uniforms)?;
+/// // ... later, e.g. next frame
+/// if let Some(count) = written.try_get()? { ... }
+/// ```
+///
+/// The bound buffers must outlive their use by the feedback object.
+///
+pub struct GlTransformFeedback {
+    pub handle : GLuint
+}
+
+///
+/// Number of primitives written by a `GlTransformFeedback::capture`, which the
+/// GPU makes available once the capture has finished. Reading it with `wait`
+/// before then stalls until it has; `try_get` doesn't.
+///
+pub struct GlFeedbackQuery {
+    pub handle : GLuint
+}
+
+impl GlFeedbackMode {
+    pub fn gl_enum(&self) -> GLenum {
+        match *self {
+            GlFeedbackMode::Interleaved => gl::INTERLEAVED_ATTRIBS,
+            GlFeedbackMode::Separate => gl::SEPARATE_ATTRIBS
+        }
+    }
+}
+
+impl GlFeedbackPrimitive {
+    pub fn gl_enum(&self) -> GLenum {
+        match *self {
+            GlFeedbackPrimitive::Points => gl::POINTS,
+            GlFeedbackPrimitive::Lines => gl::LINES,
+            GlFeedbackPrimitive::Triangles => gl::TRIANGLES
+        }
+    }
+}
+
+impl GlTransformFeedback {
+    pub fn new() -> Result<GlTransformFeedback> {
+        let mut handle : GLuint = 0;
+        unsafe {
+            gl::GenTransformFeedbacks(1, &mut handle);
+        }
+        validate_gl()?;
+        Ok(GlTransformFeedback {
+            handle: handle
+        })
+    }
+
+    ///
+    /// Binds `buffers` as capture targets at consecutive indices: one buffer
+    /// for interleaved capture, one per varying for separate capture.
+    ///
+    pub fn bind_buffers(&self, buffers : &[&GlBufferRaw]) -> Result<()> {
+        unsafe {
+            gl::BindTransformFeedback(gl::TRANSFORM_FEEDBACK, self.handle);
+            for (index, buffer) in buffers.iter().enumerate() {
+                gl::BindBufferBase(gl::TRANSFORM_FEEDBACK_BUFFER, index as GLuint, buffer.buffer_id);
+            }
+            gl::BindTransformFeedback(gl::TRANSFORM_FEEDBACK, 0);
+        }
+        validate_gl()
+    }
+
+    ///
+    /// Draws `vertex_count` vertices as `primitive`s with rasterization
+    /// disabled, capturing the program's feedback varyings into the bound
+    /// buffers. Returns a query for the number of primitives written, which
+    /// is less than drawn if the buffers are too small.
+    ///
+    pub fn capture<S, V>(&self,
+                         shader        : &S,
+                         vertex_array  : &V,
+                         vertex_count  : i32,
+                         primitive     : GlFeedbackPrimitive,
+                         uniforms      : &[(&str, GlShaderUniform)]) -> Result<GlFeedbackQuery>
+        where S : GlRenderProgram + ?Sized, V : HasGlVertexArrayHandle {
+        apply_uniforms_to(&shader.programs(), uniforms)?;
+
+        let mut query = GlFeedbackQuery { handle: 0 };
+        unsafe {
+            gl::GenQueries(1, &mut query.handle);
+            gl::Enable(gl::RASTERIZER_DISCARD);
+            gl::BindVertexArray(vertex_array.gl_vao_handle());
+            shader.bind();
+            gl::BindTransformFeedback(gl::TRANSFORM_FEEDBACK, self.handle);
+            gl::BeginQuery(gl::TRANSFORM_FEEDBACK_PRIMITIVES_WRITTEN, query.handle);
+            gl::BeginTransformFeedback(primitive.gl_enum());
+            gl::DrawArrays(primitive.gl_enum(), 0, vertex_count);
+            gl::EndTransformFeedback();
+            gl::EndQuery(gl::TRANSFORM_FEEDBACK_PRIMITIVES_WRITTEN);
+            gl::BindTransformFeedback(gl::TRANSFORM_FEEDBACK, 0);
+            gl::Disable(gl::RASTERIZER_DISCARD);
+        }
+        validate_gl()?;
+        Ok(query)
+    }
+}
+
+impl GlFeedbackQuery {
+    ///
+    /// Whether the result can be read without waiting for the GPU.
+    ///
+    pub fn is_available(&self) -> Result<bool> {
+        let mut available : GLuint = 0;
+        unsafe {
+            gl::GetQueryObjectuiv(self.handle, gl::QUERY_RESULT_AVAILABLE, &mut available);
+        }
+        validate_gl()?;
+        Ok(available != 0)
+    }
+
+    ///
+    /// The number of primitives written, or `None` if the capture hasn't
+    /// finished yet.
+    ///
+    pub fn try_get(&self) -> Result<Option<u32>> {
+        if self.is_available()? {
+            self.wait().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    ///
+    /// The number of primitives written, waiting for the capture to finish.
+    ///
+    pub fn wait(&self) -> Result<u32> {
+        let mut primitives_written : GLuint = 0;
+        unsafe {
+            gl::GetQueryObjectuiv(self.handle, gl::QUERY_RESULT, &mut primitives_written);
+        }
+        validate_gl()?;
+        Ok(primitives_written)
+    }
+}
+
+impl Drop for GlTransformFeedback {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTransformFeedbacks(1, &self.handle);
+        }
+    }
+}
+
+impl Drop for GlFeedbackQuery {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteQueries(1, &self.handle);
+        }
+    }
+}
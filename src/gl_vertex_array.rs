@@ -21,6 +21,11 @@ pub trait HasGlVertexArrayHandle {
 }
 
 impl GlVertexArray {
+    ///
+    /// Creates a VAO where attribute `i` reads `vbs[i]`. Float buffers feed
+    /// float inputs; integer buffers such as `GlBuffer<u32>` feed `int`/`uint`
+    /// based inputs unconverted, as with `GlVertexFormat`.
+    ///
     pub fn new(vbs : Vec<GlBufferRaw>) -> Result<GlVertexArray> {
         let gl_handle = {
             let vbs_ref: Vec<_> = vbs.iter().map(|a| a).collect();
@@ -87,8 +92,12 @@ fn gen_va(vbs : &[&GlBufferRaw]) -> Result<GLuint> {
         for (i,vb) in vbs.iter().enumerate() {
             gl::EnableVertexAttribArray(i as u32);
             gl::BindBuffer(gl::ARRAY_BUFFER, vb.buffer_id);
-            gl::VertexAttribPointer(i as u32, vb.component_count as i32,
-                                    vb.gl_type_enum, gl::FALSE, 0, ptr::null());
+            if gl_type_is_integer(vb.gl_type_enum) {
+                gl::VertexAttribIPointer(i as u32, vb.component_count as i32, vb.gl_type_enum, 0, ptr::null());
+            } else {
+                gl::VertexAttribPointer(i as u32, vb.component_count as i32,
+                                        vb.gl_type_enum, gl::FALSE, 0, ptr::null());
+            }
         }
 
         match validate_gl() {
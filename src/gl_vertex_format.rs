@@ -0,0 +1,178 @@
+extern crate gl;
+use gl::types::*;
+use gl_buffer::*;
+use gl_err::*;
+use gl_vertex_array::HasGlVertexArrayHandle;
+
+///
+/// Format of a single vertex attribute, as passed to glVertexAttribFormat, or
+/// glVertexAttribIFormat for integer types that aren't normalized.
+/// `binding` is the vertex buffer binding index the attribute reads from.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlVertexAttribFormat {
+    pub component_count : usize,
+    pub gl_type_enum : GLenum,
+    pub normalized : bool,
+    pub relative_offset : u32,
+    pub binding : u32
+}
+
+///
+/// Vertex layout stored in a single VAO (ARB_vertex_attrib_binding).
+/// The attribute formats are specified once, and the buffers are swapped
+/// per draw with `bind_buffers`, so meshes sharing a layout share the VAO.
+///
+pub struct GlVertexFormat {
+    pub gl_handle : GLuint,
+    pub attribs : Vec<GlVertexAttribFormat>,
+    strides : Vec<GLsizei>
+}
+
+impl GlVertexAttribFormat {
+    pub fn new<T: GlBufferElementType>(binding : u32) -> GlVertexAttribFormat {
+        GlVertexAttribFormat {
+            component_count: T::components_per_element(),
+            gl_type_enum: T::gl_type_enum(),
+            normalized: false,
+            relative_offset: 0,
+            binding: binding
+        }
+    }
+
+    pub fn from_buffer(vb : &GlBufferRaw, binding : u32) -> GlVertexAttribFormat {
+        GlVertexAttribFormat {
+            component_count: vb.component_count,
+            gl_type_enum: vb.gl_type_enum,
+            normalized: false,
+            relative_offset: 0,
+            binding: binding
+        }
+    }
+
+    pub fn normalized(self) -> Self {
+        GlVertexAttribFormat { normalized: true, ..self }
+    }
+
+    pub fn at_offset(self, relative_offset : u32) -> Self {
+        GlVertexAttribFormat { relative_offset: relative_offset, ..self }
+    }
+
+    ///
+    /// Whether the attribute feeds an `int`/`uint` based input (`ivec`, `uvec`)
+    /// unconverted: integer components that aren't normalized.
+    ///
+    pub fn is_integer(&self) -> bool {
+        !self.normalized && gl_type_is_integer(self.gl_type_enum)
+    }
+
+    fn size_bytes(&self) -> usize {
+        self.component_count * gl_type_size(self.gl_type_enum)
+    }
+}
+
+impl GlVertexFormat {
+    ///
+    /// Creates a format where attribute `i` is `attribs[i]`. The stride of each
+    /// binding is the end of its last attribute, i.e. tightly packed.
+    ///
+    pub fn new(attribs : &[GlVertexAttribFormat]) -> Result<GlVertexFormat> {
+        let binding_count = attribs.iter().map(|a| a.binding as usize + 1).max().unwrap_or(0);
+        let mut strides = vec![0; binding_count];
+        for a in attribs {
+            let end = a.relative_offset as usize + a.size_bytes();
+            if end > strides[a.binding as usize] {
+                strides[a.binding as usize] = end;
+            }
+        }
+        Self::with_strides(attribs, &strides[..])
+    }
+
+    ///
+    /// Like `new`, with the stride in bytes of each binding given, e.g. for
+    /// vertex structs with padding after their last attribute.
+    ///
+    pub fn with_strides(attribs : &[GlVertexAttribFormat], strides : &[usize]) -> Result<GlVertexFormat> {
+        for a in attribs {
+            match strides.get(a.binding as usize) {
+                Some(&stride) if stride >= a.relative_offset as usize + a.size_bytes() => (),
+                Some(&stride) => return Err(GlError::new(format!(
+                    "Attribute at offset {} doesn't fit in stride {} of binding {}",
+                    a.relative_offset, stride, a.binding))),
+                None => return Err(GlError::new(format!("No stride given for binding {}", a.binding)))
+            }
+        }
+
+        let mut gl_handle : GLuint = 0;
+        unsafe {
+            gl::GenVertexArrays(1, &mut gl_handle);
+            gl::BindVertexArray(gl_handle);
+            for (i, a) in attribs.iter().enumerate() {
+                gl::EnableVertexAttribArray(i as u32);
+                if a.is_integer() {
+                    gl::VertexAttribIFormat(i as u32, a.component_count as i32, a.gl_type_enum, a.relative_offset);
+                } else {
+                    gl::VertexAttribFormat(i as u32, a.component_count as i32, a.gl_type_enum,
+                                           if a.normalized { gl::TRUE } else { gl::FALSE }, a.relative_offset);
+                }
+                gl::VertexAttribBinding(i as u32, a.binding);
+            }
+            gl::BindVertexArray(0);
+        }
+
+        match validate_gl() {
+            Err(s) => Err(s),
+            Ok(()) => Ok(GlVertexFormat {
+                gl_handle: gl_handle,
+                attribs: attribs.to_vec(),
+                strides: strides.iter().map(|&stride| stride as GLsizei).collect()
+            })
+        }
+    }
+
+    ///
+    /// Format with one non-interleaved buffer per attribute, matching the
+    /// layout `GlVertexArray::new` builds for the same buffers: integer
+    /// buffers such as `GlBuffer<u32>` feed integer inputs unconverted.
+    ///
+    pub fn from_buffers(vbs : &[&GlBufferRaw]) -> Result<GlVertexFormat> {
+        let attribs : Vec<_> = vbs.iter().enumerate()
+            .map(|(i, vb)| GlVertexAttribFormat::from_buffer(vb, i as u32))
+            .collect();
+        Self::new(&attribs[..])
+    }
+
+    ///
+    /// Binds `vbs[i]` to binding index `i`. The bindings stay in the VAO until
+    /// the next call, so this is done once per mesh before its draws.
+    ///
+    pub fn bind_buffers(&self, vbs : &[&GlBufferRaw]) -> Result<()> {
+        if vbs.len() != self.strides.len() {
+            return Err(GlError::new(format!("Vertex format has {} bindings, got {} buffers",
+                                            self.strides.len(), vbs.len())));
+        }
+        unsafe {
+            gl::BindVertexArray(self.gl_handle);
+            for (i, vb) in vbs.iter().enumerate() {
+                gl::BindVertexBuffer(i as u32, vb.buffer_id, 0, self.strides[i]);
+            }
+        }
+        validate_gl()
+    }
+}
+
+impl HasGlVertexArrayHandle for GlVertexFormat {
+    fn gl_vao_handle(&self) -> GLuint {
+        self.gl_handle
+    }
+}
+
+impl Drop for GlVertexFormat {
+    fn drop (&mut self) {
+        unsafe {
+            gl::DeleteVertexArrays(1, &self.gl_handle);
+        }
+        validate_gl().unwrap();
+        self.gl_handle = 0;
+    }
+}
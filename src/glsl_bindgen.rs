@@ -0,0 +1,1008 @@
+use gl_err::*;
+use glsl_conditionals::*;
+use glsl_parser::*;
+use glsl_preprocess::{inject_defines, preprocess, DirectoryFileSystem, ShaderFileSystem};
+use glsl_types::GlslType;
+
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+///
+/// Generates typed bindings for shader programs from a build script. Sources
+/// are parsed without a GL context, so this runs under `cargo build`:
+///
+/// ```ignore
+/// // build.rs
+/// glust::ShaderBindgen::new()
+///     .program("Mesh", "shaders/mesh.vert", "shaders/mesh.frag")
+///     .write_to_out_dir("shaders.rs")
+///     .unwrap();
+///
+/// // src/main.rs
+/// include!(concat!(env!("OUT_DIR"), "/shaders.rs"));
+/// ```
+///
+/// For a program `Mesh` this generates `MeshAttribs`, `MeshUniforms` and
+/// `MeshShader`, which implements `Shader`, plus a `#[repr(C)]` struct for
+/// each std140 or std430 block, e.g. `MeshLights` for a block `Lights`, and
+/// for the structs in them, e.g. `MeshLightStd140` for a struct `Light`.
+/// Fields are named as in GLSL, so renaming a shader variable breaks the build
+/// instead of the first draw. Paths are relative to the crate root and may
+/// `#include` other files. `#if` sections are evaluated with the defines
+/// given to `define`, which are also compiled into the generated shaders.
+///
+pub struct ShaderBindgen {
+    root : PathBuf,
+    defines : Vec<(String, String)>,
+    programs : Vec<BindgenProgram>
+}
+
+struct BindgenProgram {
+    name : String,
+    vertex : String,
+    fragment : String
+}
+
+///
+/// Output of one `generate` run: the code, the files it was generated from
+/// and the declarations that got no binding.
+///
+struct Generated {
+    code : String,
+    dependencies : Vec<PathBuf>,
+    warnings : Vec<String>
+}
+
+impl ShaderBindgen {
+    pub fn new() -> ShaderBindgen {
+        let root = env::var_os("CARGO_MANIFEST_DIR").map_or_else(|| PathBuf::from("."), PathBuf::from);
+        ShaderBindgen {
+            root: root,
+            defines: Vec::new(),
+            programs: Vec::new()
+        }
+    }
+
+    ///
+    /// Directory shader paths are relative to. Defaults to the crate root.
+    ///
+    pub fn root<P: Into<PathBuf>>(mut self, root : P) -> Self {
+        self.root = root.into();
+        self
+    }
+
+    ///
+    /// Adds `#define name value` to every shader, replacing an earlier
+    /// define of `name`. `value` may be empty.
+    ///
+    pub fn define(mut self, name : &str, value : &str) -> Self {
+        self.defines.retain(|&(ref n, _)| n != name);
+        self.defines.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn program(mut self, name : &str, vertex : &str, fragment : &str) -> Self {
+        self.programs.push(BindgenProgram {
+            name: name.to_string(),
+            vertex: vertex.to_string(),
+            fragment: fragment.to_string()
+        });
+        self
+    }
+
+    pub fn generate(&self) -> Result<String> {
+        Ok(self.generate_all()?.code)
+    }
+
+    ///
+    /// Writes the bindings to `$OUT_DIR/<file_name>` and tells cargo to rerun
+    /// the build script when a shader changes. Declarations without a binding
+    /// are reported as cargo warnings.
+    ///
+    pub fn write_to_out_dir(&self, file_name : &str) -> Result<()> {
+        let out_dir = env::var_os("OUT_DIR")
+            .ok_or_else(|| GlError::new("OUT_DIR is not set; call write_to_out_dir from a build script".to_string()))?;
+        let generated = self.generate_all()?;
+        let path = Path::new(&out_dir).join(file_name);
+        fs::write(&path, generated.code)
+            .map_err(|err| GlError::new(format!("Error writing {}: {}", path.display(), err)))?;
+        for dependency in &generated.dependencies {
+            println!("cargo:rerun-if-changed={}", dependency.display());
+        }
+        for warning in &generated.warnings {
+            println!("cargo:warning={}", warning);
+        }
+        Ok(())
+    }
+
+    fn generate_all(&self) -> Result<Generated> {
+        let mut generated = Generated {
+            code: "// Generated by glust::ShaderBindgen. Do not edit.\n".to_string(),
+            dependencies: Vec::new(),
+            warnings: Vec::new()
+        };
+        for program in &self.programs {
+            self.generate_program(program, &mut generated)?;
+        }
+        Ok(generated)
+    }
+
+    ///
+    /// Reads a shader with its includes and defines, and parses the
+    /// declarations of its active `#if` sections.
+    ///
+    fn load(&self, path : &str, fragment : bool, generated : &mut Generated) -> Result<(String, GlslTranslationUnit)> {
+        let file_system = DirectoryFileSystem::new(self.root.clone());
+        let source = file_system.read_file(path)?;
+        let preprocessed = preprocess(&file_system, path, &source)?;
+        for file in &preprocessed.files {
+            generated.dependencies.push(self.root.join(file));
+        }
+        let source = inject_defines(&preprocessed.source, &self.defines);
+        let unit = parse_active_glsl(&source, fragment)
+            .map_err(|err| GlError::new(format!("Error parsing {}", preprocessed.annotate_log(&err.to_string()))))?;
+        Ok((source, unit))
+    }
+
+    fn generate_program(&self, program : &BindgenProgram, generated : &mut Generated) -> Result<()> {
+        let (vertex_source, vertex) = self.load(&program.vertex, false, generated)?;
+        let (fragment_source, fragment) = self.load(&program.fragment, true, generated)?;
+        let name = &program.name;
+        let mut code = String::new();
+
+        // Attributes
+        let mut attribs = vertex_attribs(&vertex);
+        for attrib in attribs.iter().filter(|a| !a.array.is_empty()) {
+            generated.warnings.push(format!("{}: attribute array {:?} has no binding", program.vertex, attrib.name));
+        }
+        attribs.retain(|a| a.array.is_empty());
+        code.push_str(&format!("\npub struct {}Attribs {{\n", name));
+        for (index, attrib) in attribs.iter().enumerate() {
+            // Buffer `i` feeds location `i`, so explicit locations must be 0, 1, 2...
+            match attrib.qualifiers.layout_value("location") {
+                Some(location) if location.parse() != Ok(index) => return Err(GlError::new(format!(
+                    "{}: attribute {:?} is at location {}, but is bound to buffer {}; \
+                     attribute locations must be contiguous from 0",
+                    program.vertex, attrib.name, location, index))),
+                _ => ()
+            }
+            let rust_type = attrib_type(&attrib.type_name).ok_or_else(|| GlError::new(format!(
+                "{}: attribute {:?} of type {} has no vertex buffer type", program.vertex, attrib.name, attrib.type_name)))?;
+            code.push_str(&format!("    pub {} : {},\n", rust_ident(&attrib.name), rust_type));
+        }
+        code.push_str("}\n\n");
+        code.push_str(&format!("impl ::glust::ShaderAttribs for {}Attribs {{\n", name));
+        code.push_str("    fn attrib_names() -> &'static [&'static str] {\n        &[");
+        code.push_str(&attribs.iter().map(|a| format!("{:?}", a.name)).collect::<Vec<_>>().join(", "));
+        code.push_str("]\n    }\n\n");
+        code.push_str("    fn into_buffers(self) -> Vec<::glust::GlBufferRaw> {\n        vec![");
+        code.push_str(&attribs.iter()
+            .map(|a| format!("::glust::GlBufferRaw::from(self.{})", rust_ident(&a.name)))
+            .collect::<Vec<_>>().join(", "));
+        code.push_str("]\n    }\n}\n");
+
+        // Uniforms of the default block, with struct uniforms flattened
+        let units = [(&program.vertex, &vertex), (&program.fragment, &fragment)];
+        let mut fields = Vec::new();
+        let mut entries = Vec::new();
+        let mut struct_types = Vec::new();
+        let mut seen = HashSet::new();
+        for &(path, unit) in &units {
+            for uniform in unit.variables() {
+                if uniform.qualifiers.storage != Some(GlslStorage::Uniform) || uniform.name.starts_with("gl_")
+                    || !seen.insert(uniform.name.clone()) {
+                    continue;
+                }
+                match uniform_binding(name, unit, uniform, &mut entries, &mut struct_types) {
+                    Ok(field) => fields.push(field),
+                    Err(reason) => generated.warnings.push(format!("{}: uniform {:?} has no binding: {}",
+                                                                   path, uniform.name, reason))
+                }
+            }
+        }
+        let lifetime = if fields.iter().any(|&(_, ref ty)| ty.contains("'a")) { "<'a>" } else { "" };
+        for struct_code in &struct_types {
+            code.push_str(struct_code);
+        }
+        code.push_str(&format!("\npub struct {}Uniforms{} {{\n", name, lifetime));
+        for &(ref field, ref ty) in &fields {
+            code.push_str(&format!("    pub {} : {},\n", field, ty));
+        }
+        code.push_str("}\n\n");
+        code.push_str(&format!("impl<'a> ::glust::ShaderUniforms<'a> for {}Uniforms{} {{\n", name, lifetime));
+        code.push_str("    fn to_uniforms(&self) -> Vec<(&'static str, ::glust::GlShaderUniform<'a>)> {\n        vec![\n");
+        for &(ref glsl_name, ref value) in &entries {
+            code.push_str(&format!("            ({:?}, {}),\n", glsl_name, value));
+        }
+        code.push_str("        ]\n    }\n}\n");
+
+        // Blocks
+        let mut seen_blocks = HashSet::new();
+        let mut block_structs = BlockStructs { program: name, names: HashSet::new(), code: String::new() };
+        for &(path, unit) in &units {
+            for (block, layout, row_major) in blocks_with_layout(unit) {
+                if !seen_blocks.insert(block.name.clone()) {
+                    continue;
+                }
+                match block_struct(unit, block, layout, row_major, &mut block_structs) {
+                    Ok(block_code) => code.push_str(&block_code),
+                    Err(reason) => generated.warnings.push(format!("{}: block {:?} has no binding: {}",
+                                                                   path, block.name, reason))
+                }
+            }
+        }
+        code.push_str(&block_structs.code);
+
+        // Shader
+        code.push_str(&format!("\npub struct {}Shader(pub ::glust::ShaderProgram);\n\n", name));
+        code.push_str(&format!("impl {}Shader {{\n", name));
+        code.push_str(&format!("    pub const VERTEX_SOURCE : &'static str = {:?};\n", vertex_source));
+        code.push_str(&format!("    pub const FRAGMENT_SOURCE : &'static str = {:?};\n", fragment_source));
+        code.push_str("}\n");
+        code.push_str(&SHADER_IMPL
+            .replace("$NAME", name)
+            .replace("$LIFETIME", if lifetime.is_empty() { "" } else { "<'a>" }));
+
+        generated.code.push_str(&code);
+        Ok(())
+    }
+}
+
+///
+/// Parses the declarations the compiler sees in `source`.
+///
+fn parse_active_glsl(source : &str, fragment : bool) -> Result<GlslTranslationUnit> {
+    let mut macros = predefined_macros(find_version(source), fragment);
+    parse_glsl(&evaluate_conditionals(source, &mut macros)?)
+}
+
+const SHADER_IMPL : &str = "
+impl<'a> ::glust::Shader<'a> for $NAMEShader {
+    type Attribs = $NAMEAttribs;
+    type Uniforms = $NAMEUniforms$LIFETIME;
+
+    fn new() -> Self {
+        let shader = ::glust::GlShaderBuilder::new()
+            .stage(::glust::GlShaderStage::Vertex, $NAMEShader::VERTEX_SOURCE)
+            .stage(::glust::GlShaderStage::Fragment, $NAMEShader::FRAGMENT_SOURCE)
+            .bind_attrib_locations(<$NAMEAttribs as ::glust::ShaderAttribs>::attrib_names())
+            .build()
+            .unwrap_or_else(|err| panic!(\"Error compiling $NAME: {}\", err));
+        $NAMEShader(::glust::ShaderProgram::new(shader))
+    }
+
+    fn create_va(attribs : Self::Attribs) -> ::std::result::Result<::glust::GlVertexArray, ::glust::GlError> {
+        ::glust::GlVertexArray::new(::glust::ShaderAttribs::into_buffers(attribs))
+    }
+
+    fn render(&self,
+              vertex_array : &::glust::GlVertexArray,
+              rendertarget : &::glust::RenderTarget,
+              uniforms : Self::Uniforms) -> ::std::result::Result<(), ::glust::GlError> {
+        self.0.render(vertex_array, rendertarget,
+                      <$NAMEAttribs as ::glust::ShaderAttribs>::attrib_names(),
+                      &::glust::ShaderUniforms::to_uniforms(&uniforms))
+    }
+
+    fn render_indexed(&self,
+                      vertex_array : &::glust::GlVertexArray,
+                      index_buffer : &::glust::GlBufferRaw,
+                      rendertarget : &::glust::RenderTarget,
+                      uniforms : Self::Uniforms) -> ::std::result::Result<(), ::glust::GlError> {
+        self.0.render_indexed(vertex_array, index_buffer, rendertarget,
+                              <$NAMEAttribs as ::glust::ShaderAttribs>::attrib_names(),
+                              &::glust::ShaderUniforms::to_uniforms(&uniforms))
+    }
+}
+";
+
+///
+/// Vertex shader inputs in location order: by `layout(location)` if every
+/// input has one, else in declaration order, which the generated shader
+/// binds them to.
+///
+fn vertex_attribs(unit : &GlslTranslationUnit) -> Vec<GlslVariable> {
+    let mut attribs : Vec<GlslVariable> = unit.variables().into_iter()
+        .filter(|v| match v.qualifiers.storage {
+            Some(GlslStorage::In) | Some(GlslStorage::Attribute) => !v.name.starts_with("gl_"),
+            _ => false
+        })
+        .cloned()
+        .collect();
+    let locations : Vec<Option<u32>> = attribs.iter()
+        .map(|a| a.qualifiers.layout_value("location").and_then(|l| l.parse().ok()))
+        .collect();
+    if locations.iter().all(|l| l.is_some()) {
+        let mut sorted : Vec<_> = locations.into_iter().zip(attribs.drain(..)).collect();
+        sorted.sort_by_key(|&(location, _)| location);
+        attribs = sorted.into_iter().map(|(_, a)| a).collect();
+    }
+    attribs
+}
+
+///
+/// Buffer type of a vertex shader input, if there is a buffer element type
+/// `GlVertexArray` feeds to it unconverted: float buffers to float inputs and
+/// integer buffers to `uint`/`uvec3` inputs.
+///
+fn attrib_type(type_name : &str) -> Option<&'static str> {
+    match GlslType::from_name(type_name) {
+        Some(GlslType::Float) => Some("::glust::GlBuffer<f32>"),
+        Some(GlslType::Vec2) => Some("::glust::GlBuffer<[f32;2]>"),
+        Some(GlslType::Vec3) => Some("::glust::GlBuffer<[f32;3]>"),
+        Some(GlslType::Vec4) => Some("::glust::GlBuffer<[f32;4]>"),
+        Some(GlslType::UInt) => Some("::glust::GlBuffer<u32>"),
+        Some(GlslType::UVec3) => Some("::glust::GlBuffer<[u32;3]>"),
+        _ => None
+    }
+}
+
+///
+/// Rust type and `GlShaderUniform` variant of a plain-data GLSL type. The
+/// variant has the same name in `GlShaderUniformArray`, if it has one there.
+///
+fn uniform_value(glsl_type : GlslType) -> Option<(&'static str, &'static str)> {
+    Some(match glsl_type {
+        GlslType::Float => ("f32", "Float"),
+        GlslType::Vec2 => ("[f32;2]", "Vec2"),
+        GlslType::Vec3 => ("[f32;3]", "Vec3"),
+        GlslType::Vec4 => ("[f32;4]", "Vec4"),
+        GlslType::Double => ("f64", "Double"),
+        GlslType::DVec2 => ("[f64;2]", "DVec2"),
+        GlslType::DVec3 => ("[f64;3]", "DVec3"),
+        GlslType::DVec4 => ("[f64;4]", "DVec4"),
+        GlslType::Int => ("i32", "Int"),
+        GlslType::IVec2 => ("[i32;2]", "IVec2"),
+        GlslType::IVec3 => ("[i32;3]", "IVec3"),
+        GlslType::IVec4 => ("[i32;4]", "IVec4"),
+        GlslType::UInt => ("u32", "UInt"),
+        GlslType::UVec2 => ("[u32;2]", "UVec2"),
+        GlslType::UVec3 => ("[u32;3]", "UVec3"),
+        GlslType::UVec4 => ("[u32;4]", "UVec4"),
+        GlslType::Bool => ("bool", "Bool"),
+        GlslType::BVec2 => ("[bool;2]", "BVec2"),
+        GlslType::BVec3 => ("[bool;3]", "BVec3"),
+        GlslType::BVec4 => ("[bool;4]", "BVec4"),
+        GlslType::Mat2 => ("[f32;4]", "Mat2x2"),
+        GlslType::Mat3 => ("[f32;9]", "Mat3x3"),
+        GlslType::Mat4 => ("[f32;16]", "Mat4x4"),
+        GlslType::Mat2x3 => ("[f32;6]", "Mat2x3"),
+        GlslType::Mat2x4 => ("[f32;8]", "Mat2x4"),
+        GlslType::Mat3x2 => ("[f32;6]", "Mat3x2"),
+        GlslType::Mat3x4 => ("[f32;12]", "Mat3x4"),
+        GlslType::Mat4x2 => ("[f32;8]", "Mat4x2"),
+        GlslType::Mat4x3 => ("[f32;12]", "Mat4x3"),
+        GlslType::DMat2 => ("[f64;4]", "DMat2x2"),
+        GlslType::DMat3 => ("[f64;9]", "DMat3x3"),
+        GlslType::DMat4 => ("[f64;16]", "DMat4x4"),
+        GlslType::DMat2x3 => ("[f64;6]", "DMat2x3"),
+        GlslType::DMat2x4 => ("[f64;8]", "DMat2x4"),
+        GlslType::DMat3x2 => ("[f64;6]", "DMat3x2"),
+        GlslType::DMat3x4 => ("[f64;12]", "DMat3x4"),
+        GlslType::DMat4x2 => ("[f64;8]", "DMat4x2"),
+        GlslType::DMat4x3 => ("[f64;12]", "DMat4x3"),
+        _ => return None
+    })
+}
+
+///
+/// Field of the uniforms struct for `uniform`, as (field name, Rust type).
+/// Adds the (GLSL name, value expression) pairs it sets to `entries`, and
+/// generated struct types to `struct_types`.
+///
+fn uniform_binding(program : &str,
+                   unit : &GlslTranslationUnit,
+                   uniform : &GlslVariable,
+                   entries : &mut Vec<(String, String)>,
+                   struct_types : &mut Vec<String>) -> ::std::result::Result<(String, String), String> {
+    let field = rust_ident(&uniform.name);
+    let expr = format!("self.{}", field);
+    if uniform.array.len() > 1 {
+        return Err("arrays of arrays are not supported".to_string());
+    }
+    let is_array = uniform.array.len() == 1;
+
+    if let Some(glsl_struct) = unit.structs().into_iter().find(|s| s.name == uniform.type_name) {
+        if is_array {
+            return Err("arrays of structs are not supported".to_string());
+        }
+        let type_name = struct_type(program, unit, glsl_struct, struct_types)?;
+        flatten_struct(unit, glsl_struct, &uniform.name, &expr, entries);
+        return Ok((field, type_name));
+    }
+
+    let glsl_type = GlslType::from_name(&uniform.type_name);
+    let (rust_type, value) = match (glsl_type, is_array) {
+        (Some(t), false) if t.is_sampler() =>
+            ("&'a ::glust::GlTexture".to_string(), format!("::glust::GlShaderUniform::TextureHandle({}.handle)", expr)),
+        (Some(t), true) if t.is_sampler() =>
+            ("&'a [u32]".to_string(),
+             format!("::glust::GlShaderUniform::Array(::glust::GlShaderUniformArray::TextureHandle({}))", expr)),
+        (Some(t), false) if uniform_value(t).is_some() => {
+            let (rust_type, variant) = uniform_value(t).unwrap();
+            (rust_type.to_string(), format!("::glust::GlShaderUniform::{}({})", variant, expr))
+        }
+        (Some(t), true) if uniform_value(t).is_some() && !variant_without_array(t) => {
+            let (rust_type, variant) = uniform_value(t).unwrap();
+            (format!("&'a [{}]", rust_type),
+             format!("::glust::GlShaderUniform::Array(::glust::GlShaderUniformArray::{}({}))", variant, expr))
+        }
+        // Images and anything else without a typed field take any value.
+        _ => ("::glust::GlShaderUniform<'a>".to_string(), expr)
+    };
+    entries.push((uniform.name.clone(), value));
+    Ok((field, rust_type))
+}
+
+fn variant_without_array(glsl_type : GlslType) -> bool {
+    match glsl_type {
+        GlslType::BVec2 | GlslType::BVec3 | GlslType::BVec4 => true,
+        _ => false
+    }
+}
+
+///
+/// Generates the Rust struct for a GLSL struct used as a uniform and returns
+/// its name. Members must be plain data or other structs, without arrays.
+///
+fn struct_type(program : &str,
+               unit : &GlslTranslationUnit,
+               glsl_struct : &GlslStruct,
+               struct_types : &mut Vec<String>) -> ::std::result::Result<String, String> {
+    let type_name = format!("{}{}", program, glsl_struct.name);
+    let header = format!("pub struct {} {{", type_name);
+    if struct_types.iter().any(|s| s.contains(&header)) {
+        return Ok(type_name);
+    }
+    let mut code = format!("\n#[derive(Debug, Clone, Copy)]\n{}\n", header);
+    for member in &glsl_struct.members {
+        if !member.array.is_empty() {
+            return Err(format!("struct member {:?} is an array", member.name));
+        }
+        let member_type = match unit.structs().into_iter().find(|s| s.name == member.type_name) {
+            Some(nested) => struct_type(program, unit, nested, struct_types)?,
+            None => match GlslType::from_name(&member.type_name).and_then(uniform_value) {
+                Some((rust_type, _)) => rust_type.to_string(),
+                None => return Err(format!("struct member {:?} has type {}", member.name, member.type_name))
+            }
+        };
+        code.push_str(&format!("    pub {} : {},\n", rust_ident(&member.name), member_type));
+    }
+    code.push_str("}\n");
+    struct_types.push(code);
+    Ok(type_name)
+}
+
+fn flatten_struct(unit : &GlslTranslationUnit,
+                  glsl_struct : &GlslStruct,
+                  glsl_prefix : &str,
+                  expr_prefix : &str,
+                  entries : &mut Vec<(String, String)>) {
+    for member in &glsl_struct.members {
+        let glsl_name = format!("{}.{}", glsl_prefix, member.name);
+        let expr = format!("{}.{}", expr_prefix, rust_ident(&member.name));
+        match unit.structs().into_iter().find(|s| s.name == member.type_name) {
+            Some(nested) => flatten_struct(unit, nested, &glsl_name, &expr, entries),
+            None => {
+                // struct_type already checked every member has a value type.
+                let (_, variant) = GlslType::from_name(&member.type_name).and_then(uniform_value).unwrap();
+                entries.push((glsl_name, format!("::glust::GlShaderUniform::{}({})", variant, expr)));
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BlockLayout {
+    Std140,
+    Std430
+}
+
+///
+/// Uniform and storage blocks with an explicit memory layout, including one
+/// set by a default declaration like `layout(std140) uniform;`, and whether
+/// their matrices default to row-major.
+///
+fn blocks_with_layout(unit : &GlslTranslationUnit) -> Vec<(&GlslBlock, BlockLayout, bool)> {
+    let mut defaults = [(None, false), (None, false)];
+    let mut blocks = Vec::new();
+    for declaration in &unit.declarations {
+        let (qualifiers, block) = match *declaration {
+            GlslDeclaration::Qualifiers(ref q) => (q, None),
+            GlslDeclaration::Block(ref b) => (&b.qualifiers, Some(b)),
+            _ => continue
+        };
+        let slot = match qualifiers.storage {
+            Some(GlslStorage::Uniform) => 0,
+            Some(GlslStorage::Buffer) => 1,
+            _ => continue
+        };
+        let mut layout = defaults[slot].0;
+        let mut row_major = defaults[slot].1;
+        for &(ref name, _) in &qualifiers.layout {
+            match &name[..] {
+                "std140" => layout = Some(BlockLayout::Std140),
+                "std430" => layout = Some(BlockLayout::Std430),
+                "shared" | "packed" => layout = None,
+                "row_major" => row_major = true,
+                "column_major" => row_major = false,
+                _ => ()
+            }
+        }
+        match block {
+            Some(block) => if let Some(layout) = layout {
+                blocks.push((block, layout, row_major));
+            },
+            None => defaults[slot] = (layout, row_major)
+        }
+    }
+    blocks
+}
+
+struct MemberLayout {
+    rust_type : String,
+    size : usize,
+    align : usize,
+    ///
+    /// Scalar type and size, used to pad array elements.
+    ///
+    scalar : (&'static str, usize)
+}
+
+///
+/// Fields of a block or struct laid out in memory: the Rust fields with
+/// `_pad` fields for the gaps, the offset after the last member, and the size
+/// and alignment of the whole, including its trailing padding.
+///
+struct FieldsLayout {
+    fields : String,
+    end : usize,
+    size : usize,
+    align : usize
+}
+
+///
+/// `#[repr(C)]` types generated for the struct members of a program's blocks,
+/// one per struct and layout.
+///
+struct BlockStructs<'a> {
+    program : &'a str,
+    names : HashSet<String>,
+    code : String
+}
+
+fn round_up(value : usize, align : usize) -> usize {
+    (value + align - 1) / align * align
+}
+
+///
+/// Rust element type, its size, component count and column count of a
+/// plain-data type as stored in a block. Bools are stored as 32-bit values.
+///
+fn block_components(glsl_type : GlslType) -> Option<(&'static str, usize, usize, usize)> {
+    let (base, size) = match glsl_type {
+        GlslType::Double | GlslType::DVec2 | GlslType::DVec3 | GlslType::DVec4 |
+        GlslType::DMat2 | GlslType::DMat3 | GlslType::DMat4 | GlslType::DMat2x3 | GlslType::DMat2x4 |
+        GlslType::DMat3x2 | GlslType::DMat3x4 | GlslType::DMat4x2 | GlslType::DMat4x3 => ("f64", 8),
+        GlslType::Int | GlslType::IVec2 | GlslType::IVec3 | GlslType::IVec4 => ("i32", 4),
+        GlslType::UInt | GlslType::UVec2 | GlslType::UVec3 | GlslType::UVec4 |
+        GlslType::Bool | GlslType::BVec2 | GlslType::BVec3 | GlslType::BVec4 => ("u32", 4),
+        _ => ("f32", 4)
+    };
+    let (rows, columns) = match glsl_type {
+        GlslType::Float | GlslType::Double | GlslType::Int | GlslType::UInt | GlslType::Bool => (1, 1),
+        GlslType::Vec2 | GlslType::DVec2 | GlslType::IVec2 | GlslType::UVec2 | GlslType::BVec2 => (2, 1),
+        GlslType::Vec3 | GlslType::DVec3 | GlslType::IVec3 | GlslType::UVec3 | GlslType::BVec3 => (3, 1),
+        GlslType::Vec4 | GlslType::DVec4 | GlslType::IVec4 | GlslType::UVec4 | GlslType::BVec4 => (4, 1),
+        GlslType::Mat2 | GlslType::DMat2 => (2, 2),
+        GlslType::Mat3 | GlslType::DMat3 => (3, 3),
+        GlslType::Mat4 | GlslType::DMat4 => (4, 4),
+        GlslType::Mat2x3 | GlslType::DMat2x3 => (3, 2),
+        GlslType::Mat2x4 | GlslType::DMat2x4 => (4, 2),
+        GlslType::Mat3x2 | GlslType::DMat3x2 => (2, 3),
+        GlslType::Mat3x4 | GlslType::DMat3x4 => (4, 3),
+        GlslType::Mat4x2 | GlslType::DMat4x2 => (2, 4),
+        GlslType::Mat4x3 | GlslType::DMat4x3 => (3, 4),
+        _ => return None
+    };
+    Some((base, size, rows, columns))
+}
+
+fn vector_layout(base : &'static str, base_size : usize, components : usize) -> MemberLayout {
+    MemberLayout {
+        rust_type: if components == 1 { base.to_string() } else { format!("[{};{}]", base, components) },
+        size: base_size * components,
+        align: base_size * if components == 3 { 4 } else { components },
+        scalar: (base, base_size)
+    }
+}
+
+///
+/// Layout of a block member by the std140 / std430 rules. Arrays and matrix
+/// columns are padded elements, e.g. `vec3 v[2]` in std140 is `[[f32;4];2]`.
+///
+fn member_layout(unit : &GlslTranslationUnit,
+                 member : &GlslVariable,
+                 layout : BlockLayout,
+                 row_major : bool,
+                 structs : &mut BlockStructs) -> ::std::result::Result<MemberLayout, String> {
+    let std140 = layout == BlockLayout::Std140;
+    let row_major = (row_major && !member.qualifiers.has_layout("column_major")) || member.qualifiers.has_layout("row_major");
+    let element = match GlslType::from_name(&member.type_name).and_then(block_components) {
+        Some((base, base_size, rows, columns)) => {
+            let (rows, columns) = if row_major { (columns, rows) } else { (rows, columns) };
+            let element = vector_layout(base, base_size, rows);
+            if columns > 1 {
+                let column_align = if std140 { round_up(element.align, 16) } else { element.align };
+                let stride = round_up(element.size, column_align);
+                MemberLayout {
+                    rust_type: format!("[[{};{}];{}]", base, stride / base_size, columns),
+                    size: stride * columns,
+                    align: column_align,
+                    scalar: (base, base_size)
+                }
+            } else {
+                element
+            }
+        }
+        None => match unit.structs().into_iter().find(|s| s.name == member.type_name) {
+            Some(glsl_struct) => struct_layout(unit, glsl_struct, layout, row_major, structs)?,
+            None => return Err(format!("member {:?} has type {}", member.name, member.type_name))
+        }
+    };
+
+    match member.array.len() {
+        0 => Ok(element),
+        1 => {
+            let count : usize = member.array[0].parse()
+                .map_err(|_| format!("member {:?} has array size {:?}", member.name, member.array[0]))?;
+            let align = if std140 { round_up(element.align, 16) } else { element.align };
+            let stride = round_up(element.size, align);
+            let (scalar, scalar_size) = element.scalar;
+            let element_type = if stride == element.size {
+                element.rust_type
+            } else {
+                format!("[{};{}]", scalar, stride / scalar_size)
+            };
+            Ok(MemberLayout {
+                rust_type: format!("[{};{}]", element_type, count),
+                size: stride * count,
+                align: align,
+                scalar: element.scalar
+            })
+        }
+        _ => Err(format!("member {:?} is an array of arrays", member.name))
+    }
+}
+
+///
+/// Layout of a struct member: a generated `#[repr(C)]` struct, aligned to its
+/// largest member and in std140 to at least 16 bytes.
+///
+fn struct_layout(unit : &GlslTranslationUnit,
+                 glsl_struct : &GlslStruct,
+                 layout : BlockLayout,
+                 row_major : bool,
+                 structs : &mut BlockStructs) -> ::std::result::Result<MemberLayout, String> {
+    let type_name = format!("{}{}{}", structs.program, glsl_struct.name,
+                            if layout == BlockLayout::Std140 { "Std140" } else { "Std430" });
+    let fields = layout_fields(unit, &glsl_struct.members, layout, row_major, structs)?;
+    if structs.names.insert(type_name.clone()) {
+        structs.code.push_str(&format!("\n#[repr(C)]\n#[derive(Debug, Clone, Copy)]\npub struct {} {{\n{}}}\n",
+                                       type_name, fields.fields));
+    }
+    Ok(MemberLayout {
+        rust_type: type_name,
+        size: fields.size,
+        align: fields.align,
+        scalar: ("u8", 1)
+    })
+}
+
+fn layout_fields(unit : &GlslTranslationUnit,
+                 members : &[GlslVariable],
+                 layout : BlockLayout,
+                 row_major : bool,
+                 structs : &mut BlockStructs) -> ::std::result::Result<FieldsLayout, String> {
+    let mut fields = String::new();
+    let mut offset = 0;
+    let mut max_align = if layout == BlockLayout::Std140 { 16 } else { 4 };
+    let mut padding_count = 0;
+    for member in members {
+        let member_layout = member_layout(unit, member, layout, row_major, structs)?;
+        let aligned = round_up(offset, member_layout.align);
+        if aligned > offset {
+            fields.push_str(&format!("    pub _pad{} : [u8;{}],\n", padding_count, aligned - offset));
+            padding_count += 1;
+        }
+        let array = member.array.iter().map(|d| format!("[{}]", d)).collect::<String>();
+        fields.push_str(&format!("    /// `{} {}{}`\n", member.type_name, member.name, array));
+        fields.push_str(&format!("    pub {} : {},\n", rust_ident(&member.name), member_layout.rust_type));
+        offset = aligned + member_layout.size;
+        max_align = max_align.max(member_layout.align);
+    }
+    let size = round_up(offset, max_align);
+    if size > offset {
+        fields.push_str(&format!("    pub _pad{} : [u8;{}],\n", padding_count, size - offset));
+    }
+    Ok(FieldsLayout {
+        fields: fields,
+        end: offset,
+        size: size,
+        align: max_align
+    })
+}
+
+fn block_struct(unit : &GlslTranslationUnit,
+                block : &GlslBlock,
+                layout : BlockLayout,
+                row_major : bool,
+                structs : &mut BlockStructs) -> ::std::result::Result<String, String> {
+    let program = structs.program;
+    let type_name = format!("{}{}", program, block.name);
+    let (layout_name, storage) = match (layout, block.qualifiers.storage) {
+        (BlockLayout::Std140, Some(GlslStorage::Buffer)) => ("std140", "buffer"),
+        (BlockLayout::Std430, Some(GlslStorage::Buffer)) => ("std430", "buffer"),
+        (BlockLayout::Std140, _) => ("std140", "uniform"),
+        (BlockLayout::Std430, _) => ("std430", "uniform")
+    };
+
+    let unsized_member = block.members.iter().position(|m| m.array.len() == 1 && m.array[0].is_empty());
+    let sized_members = match unsized_member {
+        Some(i) if i + 1 != block.members.len() => {
+            return Err(format!("unsized member {:?} isn't last", block.members[i].name));
+        }
+        Some(i) => &block.members[..i],
+        None => &block.members[..]
+    };
+    let fields = layout_fields(unit, sized_members, layout, row_major, structs)?;
+
+    let mut trailing = String::new();
+    if let Some(i) = unsized_member {
+        let member = &block.members[i];
+        let mut element = member.clone();
+        element.array.clear();
+        let element = member_layout(unit, &element, layout, row_major, structs)?;
+        let align = if layout == BlockLayout::Std140 { round_up(element.align, 16) } else { element.align };
+        trailing = format!("/// Followed by the runtime-sized `{} {}[]` at offset {}, with a stride of {} bytes.\n///\n",
+                           member.type_name, member.name, round_up(fields.end, align), round_up(element.size, align));
+    }
+
+    let mut code = format!("\n///\n/// `layout({}) {} {}` of {}, {} bytes.\n///\n{}",
+                           layout_name, storage, block.name, program, fields.size, trailing);
+    code.push_str(&format!("#[repr(C)]\n#[derive(Debug, Clone, Copy)]\npub struct {} {{\n{}}}\n\n", type_name, fields.fields));
+    code.push_str(&format!("impl {} {{\n    pub fn zeroed() -> {} {{\n        unsafe {{ ::std::mem::zeroed() }}\n    }}\n}}\n",
+                           type_name, type_name));
+    Ok(code)
+}
+
+const RUST_KEYWORDS : &[&str] = &[
+    "as", "box", "break", "const", "continue", "dyn", "else", "enum", "extern", "false", "fn", "for",
+    "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "static",
+    "struct", "trait", "true", "type", "unsafe", "use", "where", "while", "abstract", "async", "await",
+    "become", "do", "final", "macro", "override", "priv", "try", "typeof", "unsized", "virtual", "yield"
+];
+
+///
+/// Keywords that can't be raw identifiers, so fields named after them get a
+/// trailing `_` instead.
+///
+const RUST_PATH_KEYWORDS : &[&str] = &["crate", "self", "Self", "super"];
+
+fn rust_ident(name : &str) -> String {
+    if RUST_PATH_KEYWORDS.contains(&name) {
+        format!("{}_", name)
+    } else if RUST_KEYWORDS.contains(&name) {
+        format!("r#{}", name)
+    } else {
+        name.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    ///
+    /// The generated block structs as `struct <name>` and `<field> : <type>`
+    /// lines, and the block sizes from their doc comments.
+    ///
+    type Layouts = (Vec<String>, Vec<usize>);
+
+    fn block_layouts(source : &str) -> Layouts {
+        let unit = parse_glsl(source).unwrap();
+        let mut structs = BlockStructs { program: "P", names: HashSet::new(), code: String::new() };
+        let mut code = String::new();
+        for (block, layout, row_major) in blocks_with_layout(&unit) {
+            code.push_str(&block_struct(&unit, block, layout, row_major, &mut structs).unwrap());
+        }
+        code.push_str(&structs.code);
+
+        let fields = code.lines()
+            .map(|line| line.trim())
+            .filter(|line| line.starts_with("pub ") && !line.starts_with("pub fn"))
+            .map(|line| line["pub ".len()..].trim_end_matches([',', '{']).trim().to_string())
+            .collect();
+        let sizes = code.lines()
+            .filter(|line| line.ends_with(" bytes."))
+            .map(|line| line.rsplit(", ").next().unwrap().trim_end_matches(" bytes.").parse().unwrap())
+            .collect();
+        (fields, sizes)
+    }
+
+    fn lines(lines : &[&str]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    fn both_layouts(members : &str) -> (Layouts, Layouts) {
+        (block_layouts(&format!("layout(std140) uniform B {{ {} }};", members)),
+         block_layouts(&format!("layout(std430) buffer B {{ {} }};", members)))
+    }
+
+    #[test]
+    fn vec3_after_float_is_aligned_to_16() {
+        let (std140, std430) = both_layouts("float a; vec3 b;");
+        let expected = lines(&["struct PB", "a : f32", "_pad0 : [u8;12]", "b : [f32;3]", "_pad1 : [u8;4]"]);
+        assert_eq!(std140, (expected.clone(), vec![32]));
+        assert_eq!(std430, (expected, vec![32]));
+    }
+
+    #[test]
+    fn float_after_vec3_fills_its_last_component() {
+        let (std140, std430) = both_layouts("vec3 a; float b;");
+        assert_eq!(std140, (lines(&["struct PB", "a : [f32;3]", "b : f32"]), vec![16]));
+        assert_eq!(std430, std140);
+    }
+
+    #[test]
+    fn mat3_columns_are_padded_vec4s() {
+        let (std140, std430) = both_layouts("mat3 m; float f;");
+        assert_eq!(std140, (lines(&["struct PB", "m : [[f32;4];3]", "f : f32", "_pad0 : [u8;12]"]), vec![64]));
+        assert_eq!(std430, std140);
+    }
+
+    #[test]
+    fn mat2_columns_are_padded_only_in_std140() {
+        let (std140, std430) = both_layouts("mat2 m;");
+        assert_eq!(std140, (lines(&["struct PB", "m : [[f32;4];2]"]), vec![32]));
+        assert_eq!(std430, (lines(&["struct PB", "m : [[f32;2];2]"]), vec![16]));
+    }
+
+    #[test]
+    fn row_major_matrices_store_rows() {
+        let (std140, _) = both_layouts("layout(row_major) mat2x3 m;");
+        assert_eq!(std140, (lines(&["struct PB", "m : [[f32;4];3]"]), vec![48]));
+    }
+
+    #[test]
+    fn float_arrays_have_a_16_byte_stride_only_in_std140() {
+        let (std140, std430) = both_layouts("float f[3]; float g;");
+        assert_eq!(std140, (lines(&["struct PB", "f : [[f32;4];3]", "g : f32", "_pad0 : [u8;12]"]), vec![64]));
+        assert_eq!(std430, (lines(&["struct PB", "f : [f32;3]", "g : f32"]), vec![16]));
+    }
+
+    #[test]
+    fn vec3_arrays_have_a_16_byte_stride() {
+        let (std140, std430) = both_layouts("float a; vec3 v[2]; float b;");
+        let expected = lines(&["struct PB", "a : f32", "_pad0 : [u8;12]", "v : [[f32;4];2]", "b : f32", "_pad1 : [u8;12]"]);
+        assert_eq!(std140, (expected.clone(), vec![64]));
+        assert_eq!(std430, (expected, vec![64]));
+    }
+
+    #[test]
+    fn nested_structs_get_a_type_per_layout() {
+        let structs = "struct Light { vec3 color; float intensity; }; \
+                       struct Scene { Light key; float t[2]; };";
+        let std140 = block_layouts(&format!("{} layout(std140) uniform B {{ float a; Scene scene; }};", structs));
+        assert_eq!(std140, (lines(&["struct PB", "a : f32", "_pad0 : [u8;12]", "scene : PSceneStd140",
+                                    "struct PLightStd140", "color : [f32;3]", "intensity : f32",
+                                    "struct PSceneStd140", "key : PLightStd140", "t : [[f32;4];2]"]),
+                            vec![64]));
+
+        let std430 = block_layouts(&format!("{} layout(std430) buffer B {{ float a; Scene scene; Light lights[2]; }};",
+                                            structs));
+        assert_eq!(std430, (lines(&["struct PB", "a : f32", "_pad0 : [u8;12]", "scene : PSceneStd430",
+                                    "lights : [PLightStd430;2]",
+                                    "struct PLightStd430", "color : [f32;3]", "intensity : f32",
+                                    "struct PSceneStd430", "key : PLightStd430", "t : [f32;2]", "_pad0 : [u8;8]"]),
+                            vec![80]));
+    }
+
+    #[test]
+    fn unsized_arrays_are_documented_after_the_struct() {
+        let unit = parse_glsl("layout(std430) buffer B { float count; vec3 points[]; };").unwrap();
+        let mut structs = BlockStructs { program: "P", names: HashSet::new(), code: String::new() };
+        let (block, layout, row_major) = blocks_with_layout(&unit)[0];
+        let code = block_struct(&unit, block, layout, row_major, &mut structs).unwrap();
+        assert!(code.contains("runtime-sized `vec3 points[]` at offset 16, with a stride of 16 bytes"), "{}", code);
+    }
+
+    #[test]
+    fn attributes_follow_conditionals_and_locations() {
+        let source = "#version 330\n\
+                      layout(location = 1) in vec3 pos;\n\
+                      #ifdef SKINNED\n\
+                      layout(location = 2) in vec4 weights;\n\
+                      #endif\n\
+                      layout(location = 0) in vec2 uv;\n";
+        let names = |source : &str| -> Vec<String> {
+            vertex_attribs(&parse_active_glsl(source, false).unwrap()).into_iter().map(|a| a.name).collect()
+        };
+        assert_eq!(names(source), vec!["uv", "pos"]);
+        let skinned = inject_defines(source, &[("SKINNED".to_string(), String::new())]);
+        assert_eq!(names(&skinned), vec!["uv", "pos", "weights"]);
+    }
+
+    fn generate_vertex(name : &str, vertex : &str) -> Result<String> {
+        let root = env::temp_dir().join(format!("glust_bindgen_{}_{}", name, ::std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("test.vert"), vertex).unwrap();
+        fs::write(root.join("test.frag"), "#version 330\nout vec4 color;\nvoid main() {}\n").unwrap();
+        let generated = ShaderBindgen::new().root(root.clone()).program("Test", "test.vert", "test.frag").generate();
+        fs::remove_dir_all(&root).unwrap();
+        generated
+    }
+
+    #[test]
+    fn sparse_attribute_locations_are_rejected() {
+        let err = generate_vertex("sparse", "#version 330\n\
+                                             layout(location = 0) in vec3 pos;\n\
+                                             layout(location = 1) in vec3 normal;\n\
+                                             layout(location = 3) in vec2 uv;\n\
+                                             void main() {}\n").unwrap_err();
+        assert!(err.to_string().contains("attribute \"uv\" is at location 3, but is bound to buffer 2"), "{}", err);
+    }
+
+    #[test]
+    fn integer_attributes_need_a_buffer_type() {
+        let code = generate_vertex("uint", "#version 330\nin uint id;\nin uvec3 tri;\nvoid main() {}\n").unwrap();
+        assert!(code.contains("pub id : ::glust::GlBuffer<u32>"), "{}", code);
+        assert!(code.contains("pub tri : ::glust::GlBuffer<[u32;3]>"), "{}", code);
+        let err = generate_vertex("ivec", "#version 330\nin ivec2 cell;\nvoid main() {}\n").unwrap_err();
+        assert!(err.to_string().contains("attribute \"cell\" of type ivec2 has no vertex buffer type"), "{}", err);
+    }
+
+    #[test]
+    fn keywords_become_valid_identifiers() {
+        assert_eq!(rust_ident("type"), "r#type");
+        assert_eq!(rust_ident("self"), "self_");
+        assert_eq!(rust_ident("Self"), "Self_");
+        assert_eq!(rust_ident("crate"), "crate_");
+        assert_eq!(rust_ident("super"), "super_");
+        assert_eq!(rust_ident("pos"), "pos");
+    }
+
+    #[test]
+    fn defines_select_bindings_and_are_compiled_in() {
+        let root = env::temp_dir().join(format!("glust_bindgen_test_{}", ::std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("skin.vert"), "#version 330\nin vec3 pos;\n#ifdef SKINNED\nin vec4 weights;\n#endif\n\
+                                           void main() {}\n").unwrap();
+        fs::write(root.join("skin.frag"), "#version 330\nout vec4 color;\nvoid main() {}\n").unwrap();
+        let bindgen = ShaderBindgen::new().root(root.clone()).program("Skin", "skin.vert", "skin.frag");
+
+        let plain = bindgen.generate().unwrap();
+        assert!(!plain.contains("pub weights"), "{}", plain);
+        let skinned = bindgen.define("SKINNED", "").generate().unwrap();
+        assert!(skinned.contains("pub weights : ::glust::GlBuffer<[f32;4]>"), "{}", skinned);
+        assert!(skinned.contains("#define SKINNED\\n"), "{}", skinned);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn struct_uniforms_are_flattened() {
+        let unit = parse_glsl("struct Light { vec3 color; float intensity; }; \
+                               struct Scene { Light key; mat4 view; }; \
+                               uniform Scene scene;").unwrap();
+        let mut entries = Vec::new();
+        let mut struct_types = Vec::new();
+        let (field, rust_type) = uniform_binding("P", &unit, unit.variables()[0], &mut entries, &mut struct_types).unwrap();
+        assert_eq!((&field[..], &rust_type[..]), ("scene", "PScene"));
+        let names : Vec<_> = entries.iter().map(|&(ref name, _)| &name[..]).collect();
+        assert_eq!(names, vec!["scene.key.color", "scene.key.intensity", "scene.view"]);
+        assert_eq!(entries[0].1, "::glust::GlShaderUniform::Vec3(self.scene.key.color)");
+        assert_eq!(struct_types.len(), 2);
+    }
+}
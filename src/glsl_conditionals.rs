@@ -0,0 +1,441 @@
+use gl_err::*;
+use glsl_parser::*;
+use glsl_preprocess::ends_in_comment;
+
+use std::collections::HashMap;
+
+///
+/// The `#version` of a source, 1.10 if there is none.
+///
+pub fn find_version(source : &str) -> GlslVersion {
+    for line in source.lines() {
+        let line = line.trim();
+        if line.starts_with("#version") {
+            let mut words = line["#version".len()..].split_whitespace();
+            let number = words.next().and_then(|v| v.parse::<u32>().ok()).unwrap_or(110);
+            let profile = words.next();
+            return GlslVersion {
+                number: number,
+                es: profile == Some("es") || number == 100,
+                compatibility: profile == Some("compatibility")
+            };
+        }
+    }
+    GlslVersion { number: 110, es: false, compatibility: false }
+}
+
+///
+/// Macros GLSL defines before the first line of a source of `version`, with
+/// `fragment` for fragment shaders.
+///
+pub fn predefined_macros(version : GlslVersion, fragment : bool) -> HashMap<String, Option<String>> {
+    let mut macros : HashMap<String, Option<String>> = HashMap::new();
+    macros.insert("__VERSION__".to_string(), Some(version.number.to_string()));
+    macros.insert("__LINE__".to_string(), Some("0".to_string()));
+    macros.insert("__FILE__".to_string(), Some("0".to_string()));
+    if version.es {
+        macros.insert("GL_ES".to_string(), Some("1".to_string()));
+        if fragment {
+            macros.insert("GL_FRAGMENT_PRECISION_HIGH".to_string(), Some("1".to_string()));
+        }
+    } else if version.number >= 150 {
+        let profile = if version.compatibility { "GL_compatibility_profile" } else { "GL_core_profile" };
+        macros.insert(profile.to_string(), Some("1".to_string()));
+    }
+    macros
+}
+
+///
+/// Evaluates the `#if`, `#ifdef`, `#ifndef`, `#elif` and `#else` sections of
+/// `source`. Lines of inactive sections and all directives other than
+/// `#version`, `#extension` and `#line` are blanked, keeping line numbers,
+/// and object-like macros are expanded in the active lines, so the result
+/// holds the declarations the compiler sees. Function-like macros are left
+/// as written.
+///
+/// `macros` holds the macros defined before the source, e.g. from
+/// `predefined_macros`, and on return the ones defined at its end, with
+/// `None` for function-like macros. Errors name the line as in compiler logs.
+///
+pub fn evaluate_conditionals(source : &str, macros : &mut HashMap<String, Option<String>>) -> Result<String> {
+    let version = find_version(source);
+    // Before GLSL 3.30 the line after `#line n` is n + 1.
+    let next_line_numbering = version.number >= 330 || (version.es && version.number >= 300);
+    let mut line_delta : isize = 0;
+    let mut source_number = 0;
+    let mut in_comment = false;
+
+    // Stack of (section is active, a branch was taken, enclosing section is active).
+    let mut stack : Vec<(bool, bool, bool)> = Vec::new();
+    let mut out = String::with_capacity(source.len());
+    let mut lines = source.lines().enumerate();
+    while let Some((index, line)) = lines.next() {
+        // Join continued directive lines, emitting blanks for the joined ones.
+        let mut text = line.to_string();
+        let mut joined = 0;
+        while text.ends_with('\\') && text.trim_start().starts_with('#') {
+            text.pop();
+            match lines.next() {
+                Some((_, next)) => text.push_str(next),
+                None => break
+            }
+            joined += 1;
+        }
+        let active = stack.last().map_or(true, |&(active, _, _)| active);
+        let trimmed = text.trim_start();
+        let is_directive = !in_comment && trimmed.starts_with('#');
+        if !is_directive {
+            if active {
+                out.push_str(&expand_macros(&text, macros, in_comment));
+            }
+            in_comment = ends_in_comment(&text, in_comment);
+            out.push('\n');
+            continue;
+        }
+        in_comment = ends_in_comment(&text, in_comment);
+
+        let directive = trimmed[1..].trim_start();
+        let name_len = directive.find(|c : char| !c.is_alphanumeric() && c != '_').unwrap_or(directive.len());
+        let (name, args) = (&directive[..name_len], directive[name_len..].trim());
+        let reported_line = index as isize + 1 + line_delta;
+        let error = |message : String| GlError::new(format!("ERROR: {}:{}: {}", source_number, reported_line, message));
+        let mut keep = false;
+        match name {
+            "if" | "ifdef" | "ifndef" => {
+                let condition = active && match name {
+                    "if" => evaluate_condition(args, macros).map_err(&error)?,
+                    "ifdef" => macros.contains_key(args),
+                    _ => !macros.contains_key(args)
+                };
+                stack.push((condition, condition, active));
+            }
+            "elif" => {
+                let &mut (ref mut section, ref mut taken, outer) = match stack.last_mut() {
+                    Some(entry) => entry,
+                    None => return Err(error("#elif without #if".to_string()))
+                };
+                let condition = outer && !*taken && evaluate_condition(args, macros).map_err(&error)?;
+                *section = condition;
+                *taken = *taken || condition;
+            }
+            "else" => {
+                let &mut (ref mut section, ref mut taken, outer) = match stack.last_mut() {
+                    Some(entry) => entry,
+                    None => return Err(error("#else without #if".to_string()))
+                };
+                *section = outer && !*taken;
+                *taken = true;
+            }
+            "endif" => {
+                if stack.pop().is_none() {
+                    return Err(error("#endif without #if".to_string()));
+                }
+            }
+            "define" if active => {
+                let macro_len = args.find(|c : char| !c.is_alphanumeric() && c != '_').unwrap_or(args.len());
+                let macro_name = &args[..macro_len];
+                if macro_name.is_empty() {
+                    return Err(error("#define without a name".to_string()));
+                }
+                let value = &args[macro_len..];
+                let value = if value.starts_with('(') { None } else { Some(value.trim().to_string()) };
+                macros.insert(macro_name.to_string(), value);
+            }
+            "undef" if active => {
+                macros.remove(args);
+            }
+            "error" if active => {
+                return Err(error(format!("#error {}", args)));
+            }
+            "line" if active => {
+                let mut words = args.split_whitespace();
+                if let Some(number) = words.next().and_then(|w| w.parse::<isize>().ok()) {
+                    let next_line = if next_line_numbering { number } else { number + 1 };
+                    line_delta = next_line - (index as isize + joined as isize + 2);
+                    if let Some(number) = words.next().and_then(|w| w.parse::<usize>().ok()) {
+                        source_number = number;
+                    }
+                }
+                keep = true;
+            }
+            "version" | "extension" if active => keep = true,
+            _ => ()
+        }
+        if keep {
+            out.push_str(&text);
+        }
+        out.push('\n');
+        for _ in 0..joined {
+            out.push('\n');
+        }
+    }
+    if !stack.is_empty() {
+        return Err(GlError::new(format!("ERROR: {}:0: unterminated #if", source_number)));
+    }
+    Ok(out)
+}
+
+///
+/// Evaluates an `#if` expression. Undefined identifiers are 0.
+///
+fn evaluate_condition(expression : &str, macros : &HashMap<String, Option<String>>) -> ::std::result::Result<bool, String> {
+    let tokens = expand_condition(expression, macros, 0)?;
+    let mut pos = 0;
+    let value = evaluate_binary(&tokens, &mut pos, 1)?;
+    if pos != tokens.len() {
+        return Err(format!("unexpected `{}` in #if", token_text(&tokens[pos])));
+    }
+    Ok(value != 0)
+}
+
+///
+/// Tokenizes an `#if` expression, resolving `defined` and replacing macros
+/// by their tokens.
+///
+fn expand_condition(expression : &str, macros : &HashMap<String, Option<String>>, depth : usize)
+                    -> ::std::result::Result<Vec<TokenKind>, String> {
+    if depth > 32 {
+        return Err("macro expansion too deep in #if".to_string());
+    }
+    let tokens : Vec<TokenKind> = tokenize(expression)
+        .map_err(|err| err.to_string())?
+        .0.into_iter().map(|t| t.kind).collect();
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        match tokens[i] {
+            TokenKind::Identifier(ref word) if word == "defined" => {
+                let parenthesized = tokens.get(i + 1) == Some(&TokenKind::Punct("("));
+                let name_index = if parenthesized { i + 2 } else { i + 1 };
+                let name = match tokens.get(name_index) {
+                    Some(&TokenKind::Identifier(ref name)) => name,
+                    _ => return Err("expected a macro name after `defined`".to_string())
+                };
+                let defined = macros.contains_key(name);
+                out.push(TokenKind::IntConstant(if defined { "1" } else { "0" }.to_string()));
+                i = name_index + if parenthesized { 2 } else { 1 };
+                continue;
+            }
+            TokenKind::Identifier(ref word) => match macros.get(word) {
+                Some(&Some(ref value)) if !value.is_empty() => out.extend(expand_condition(value, macros, depth + 1)?),
+                _ => out.push(TokenKind::IntConstant("0".to_string()))
+            },
+            ref token => out.push(token.clone())
+        }
+        i += 1;
+    }
+    Ok(out)
+}
+
+///
+/// Replaces the object-like macros of a source line by their values,
+/// recursively, skipping comments. A macro isn't expanded inside its own
+/// expansion.
+///
+fn expand_macros(line : &str, macros : &HashMap<String, Option<String>>, in_comment : bool) -> String {
+    let mut expanding = Vec::new();
+    expand_line(line, macros, in_comment, &mut expanding)
+}
+
+fn expand_line(line : &str, macros : &HashMap<String, Option<String>>, mut in_comment : bool,
+               expanding : &mut Vec<String>) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut rest = line;
+    while !rest.is_empty() {
+        if in_comment {
+            match rest.find("*/") {
+                Some(end) => {
+                    out.push_str(&rest[..end + 2]);
+                    rest = &rest[end + 2..];
+                    in_comment = false;
+                    continue;
+                }
+                None => {
+                    out.push_str(rest);
+                    break;
+                }
+            }
+        }
+        if rest.starts_with("//") {
+            out.push_str(rest);
+            break;
+        }
+        if rest.starts_with("/*") {
+            in_comment = true;
+            out.push_str("/*");
+            rest = &rest[2..];
+            continue;
+        }
+        let first = rest.chars().next().unwrap();
+        let word_len = if first.is_alphabetic() || first == '_' || first.is_ascii_digit() {
+            // Numbers are skipped whole so suffixes and exponents aren't taken as names.
+            rest.find(|c : char| !c.is_alphanumeric() && c != '_' && !(first.is_ascii_digit() && c == '.'))
+                .unwrap_or(rest.len())
+        } else {
+            first.len_utf8()
+        };
+        let word = &rest[..word_len];
+        match macros.get(word) {
+            Some(&Some(ref value)) if !first.is_ascii_digit() && !expanding.iter().any(|name| name == word) => {
+                expanding.push(word.to_string());
+                out.push_str(&expand_line(value, macros, false, expanding));
+                expanding.pop();
+            }
+            _ => out.push_str(word)
+        }
+        rest = &rest[word_len..];
+    }
+    out
+}
+
+fn evaluate_binary(tokens : &[TokenKind], pos : &mut usize, min_precedence : u32) -> ::std::result::Result<i64, String> {
+    let mut left = evaluate_unary(tokens, pos)?;
+    loop {
+        let (operator, precedence) = match tokens.get(*pos) {
+            Some(&TokenKind::Punct(p)) => match condition_precedence(p) {
+                Some(precedence) if precedence >= min_precedence => (p, precedence),
+                _ => return Ok(left)
+            },
+            _ => return Ok(left)
+        };
+        *pos += 1;
+        let right = evaluate_binary(tokens, pos, precedence + 1)?;
+        left = match operator {
+            "||" => ((left != 0) || (right != 0)) as i64,
+            "&&" => ((left != 0) && (right != 0)) as i64,
+            "|" => left | right,
+            "^" => left ^ right,
+            "&" => left & right,
+            "==" => (left == right) as i64,
+            "!=" => (left != right) as i64,
+            "<" => (left < right) as i64,
+            ">" => (left > right) as i64,
+            "<=" => (left <= right) as i64,
+            ">=" => (left >= right) as i64,
+            "<<" => left.wrapping_shl(right as u32),
+            ">>" => left.wrapping_shr(right as u32),
+            "+" => left.wrapping_add(right),
+            "-" => left.wrapping_sub(right),
+            "*" => left.wrapping_mul(right),
+            "/" | "%" if right == 0 => return Err("division by zero in #if".to_string()),
+            "/" => left / right,
+            _ => left % right
+        };
+    }
+}
+
+fn condition_precedence(operator : &str) -> Option<u32> {
+    Some(match operator {
+        "||" => 1, "&&" => 2, "|" => 3, "^" => 4, "&" => 5,
+        "==" | "!=" => 6, "<" | ">" | "<=" | ">=" => 7, "<<" | ">>" => 8,
+        "+" | "-" => 9, "*" | "/" | "%" => 10,
+        _ => return None
+    })
+}
+
+fn evaluate_unary(tokens : &[TokenKind], pos : &mut usize) -> ::std::result::Result<i64, String> {
+    let token = match tokens.get(*pos) {
+        Some(token) => token.clone(),
+        None => return Err("expected a value in #if".to_string())
+    };
+    *pos += 1;
+    match token {
+        TokenKind::IntConstant(text) => {
+            let digits = text.trim_end_matches(&['u', 'U'][..]);
+            let value = if digits.starts_with("0x") || digits.starts_with("0X") {
+                i64::from_str_radix(&digits[2..], 16)
+            } else if digits.len() > 1 && digits.starts_with('0') {
+                i64::from_str_radix(&digits[1..], 8)
+            } else {
+                digits.parse::<i64>()
+            };
+            value.map_err(|_| format!("invalid integer `{}` in #if", text))
+        }
+        TokenKind::BoolConstant(value) => Ok(value as i64),
+        TokenKind::Punct("(") => {
+            let value = evaluate_binary(tokens, pos, 1)?;
+            match tokens.get(*pos) {
+                Some(&TokenKind::Punct(")")) => {
+                    *pos += 1;
+                    Ok(value)
+                }
+                _ => Err("expected `)` in #if".to_string())
+            }
+        }
+        TokenKind::Punct("!") => Ok((evaluate_unary(tokens, pos)? == 0) as i64),
+        TokenKind::Punct("-") => Ok(evaluate_unary(tokens, pos)?.wrapping_neg()),
+        TokenKind::Punct("+") => evaluate_unary(tokens, pos),
+        TokenKind::Punct("~") => Ok(!evaluate_unary(tokens, pos)?),
+        token => Err(format!("unexpected `{}` in #if", token_text(&token)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn active(source : &str) -> String {
+        let mut macros = predefined_macros(find_version(source), false);
+        evaluate_conditionals(source, &mut macros).unwrap()
+    }
+
+    #[test]
+    fn if_and_elif_take_the_first_true_branch() {
+        let source = "#version 330\n#define QUALITY 2\n#if QUALITY > 2\nhigh\n#elif QUALITY == 2\nmedium\n\
+                      #elif QUALITY == 1\nlow\n#else\nnone\n#endif\n";
+        assert_eq!(active(source), "#version 330\n\n\n\n\nmedium\n\n\n\n\n\n");
+    }
+
+    #[test]
+    fn nested_sections_in_inactive_ones_stay_inactive() {
+        let source = "#if 0\n#if 1\na\n#else\nb\n#endif\n#else\nc\n#endif\n";
+        assert_eq!(active(source), "\n\n\n\n\n\n\nc\n\n");
+    }
+
+    #[test]
+    fn defined_works_with_and_without_parentheses() {
+        let source = "#define A\n#if defined(A) && !defined B\na\n#endif\n#undef A\n#ifdef A\nb\n#endif\n";
+        assert_eq!(active(source), "\n\na\n\n\n\n\n\n");
+    }
+
+    #[test]
+    fn version_macros_gate_sections() {
+        let source = "#version 300 es\n#if __VERSION__ >= 300 && defined(GL_ES)\nes3\n#endif\n\
+                      #ifdef GL_core_profile\ncore\n#endif\n";
+        assert_eq!(active(source), "#version 300 es\n\nes3\n\n\n\n\n");
+        let source = "#version 150\n#if __VERSION__ >= 300\nes3\n#endif\n#ifdef GL_core_profile\ncore\n#endif\n";
+        assert_eq!(active(source), "#version 150\n\n\n\n\ncore\n\n");
+    }
+
+    #[test]
+    fn object_like_macros_are_expanded() {
+        let source = "#define VARYING out\n#define UV VARYING vec2\n#define SELF SELF + 1\n\
+                      UV uv; // VARYING\nint x = SELF;\n/* VARYING\nVARYING */ VARYING float f1;\n";
+        assert_eq!(active(source), "\n\n\nout vec2 uv; // VARYING\nint x = SELF + 1;\n/* VARYING\nVARYING */ out float f1;\n");
+    }
+
+    #[test]
+    fn function_like_macros_are_left_as_written() {
+        let mut macros = HashMap::new();
+        let out = evaluate_conditionals("#define SQUARE(x) ((x) * (x))\nfloat a = SQUARE(2.0);\n", &mut macros).unwrap();
+        assert_eq!(out, "\nfloat a = SQUARE(2.0);\n");
+        assert_eq!(macros.get("SQUARE"), Some(&None));
+    }
+
+    #[test]
+    fn extension_and_line_directives_are_kept() {
+        let source = "#version 150\n#extension GL_ARB_explicit_attrib_location : require\n#line 10\n#pragma debug\n";
+        assert_eq!(active(source), "#version 150\n#extension GL_ARB_explicit_attrib_location : require\n#line 10\n\n");
+    }
+
+    #[test]
+    fn errors_name_the_mapped_line() {
+        let mut macros = HashMap::new();
+        let err = evaluate_conditionals("#version 330\n#line 20 3\n#error broken\n", &mut macros).unwrap_err();
+        assert_eq!(err.to_string(), "ERROR: 3:20: #error broken");
+        let err = evaluate_conditionals("#if 1\n", &mut macros).unwrap_err();
+        assert_eq!(err.to_string(), "ERROR: 0:0: unterminated #if");
+        let err = evaluate_conditionals("#else\n", &mut macros).unwrap_err();
+        assert_eq!(err.to_string(), "ERROR: 0:1: #else without #if");
+    }
+}
@@ -0,0 +1,1376 @@
+use gl_err::*;
+
+///
+/// Declarations of a GLSL source, parsed without a GL context. Preprocessor
+/// directives other than `#version` and `#line` are not evaluated, so
+/// declarations in every branch of an `#ifdef` are included.
+///
+#[derive(Debug, Clone)]
+pub struct GlslTranslationUnit {
+    pub version : Option<GlslVersion>,
+    pub declarations : Vec<GlslDeclaration>
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlslVersion {
+    pub number : u32,
+    pub es : bool,
+    pub compatibility : bool
+}
+
+///
+/// Position in the source. `source` is the source string number of the last
+/// `#line` directive, as used in compiler logs.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct GlslPosition {
+    pub source : usize,
+    pub line : usize,
+    pub column : usize
+}
+
+#[derive(Debug, Clone)]
+pub enum GlslDeclaration {
+    Variable(GlslVariable),
+    Block(GlslBlock),
+    Struct(GlslStruct),
+    Function(GlslFunction),
+    ///
+    /// Declaration with qualifiers only, e.g. `layout(local_size_x = 8) in;`.
+    ///
+    Qualifiers(GlslQualifiers),
+    ///
+    /// Default precision, e.g. `precision mediump float;`.
+    ///
+    Precision(String, String)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GlslStorage {
+    Const,
+    In,
+    Out,
+    InOut,
+    Attribute,
+    Varying,
+    Uniform,
+    Buffer,
+    Shared
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct GlslQualifiers {
+    pub storage : Option<GlslStorage>,
+    ///
+    /// `layout(...)` entries as (name, value); `std140` has no value.
+    ///
+    pub layout : Vec<(String, Option<String>)>,
+    ///
+    /// `flat`, `smooth` or `noperspective`.
+    ///
+    pub interpolation : Option<String>,
+    ///
+    /// Every other qualifier, e.g. `centroid`, `patch`, `highp`, `readonly`.
+    ///
+    pub other : Vec<String>,
+    ///
+    /// The subroutine types of `subroutine(A, B)`.
+    ///
+    pub subroutine_types : Vec<String>
+}
+
+#[derive(Debug, Clone)]
+pub struct GlslVariable {
+    pub qualifiers : GlslQualifiers,
+    pub type_name : String,
+    pub name : String,
+    ///
+    /// Size expressions of each array dimension; empty for unsized dimensions.
+    ///
+    pub array : Vec<String>,
+    pub initializer : Option<GlslExpression>,
+    pub position : GlslPosition
+}
+
+#[derive(Debug, Clone)]
+pub struct GlslBlock {
+    pub qualifiers : GlslQualifiers,
+    pub name : String,
+    pub members : Vec<GlslVariable>,
+    pub instance_name : Option<String>,
+    pub instance_array : Vec<String>,
+    pub position : GlslPosition
+}
+
+#[derive(Debug, Clone)]
+pub struct GlslStruct {
+    pub name : String,
+    pub members : Vec<GlslVariable>,
+    pub position : GlslPosition
+}
+
+#[derive(Debug, Clone)]
+pub struct GlslFunction {
+    ///
+    /// Qualifiers before the return type, e.g. `subroutine(Shade)`.
+    ///
+    pub qualifiers : GlslQualifiers,
+    pub return_type : String,
+    pub name : String,
+    pub parameters : Vec<GlslVariable>,
+    ///
+    /// `None` for a prototype.
+    ///
+    pub body : Option<Vec<GlslStatement>>,
+    pub position : GlslPosition
+}
+
+#[derive(Debug, Clone)]
+pub enum GlslStatement {
+    ///
+    /// Local variables and struct definitions.
+    ///
+    Declaration(Vec<GlslDeclaration>),
+    Expression(GlslExpression),
+    Compound(Vec<GlslStatement>),
+    If(GlslExpression, Box<GlslStatement>, Option<Box<GlslStatement>>),
+    ///
+    /// `for (init; condition; step) body`, where `init` is a declaration or
+    /// expression statement.
+    ///
+    For(Box<GlslStatement>, Option<GlslExpression>, Option<GlslExpression>, Box<GlslStatement>),
+    While(GlslExpression, Box<GlslStatement>),
+    DoWhile(Box<GlslStatement>, GlslExpression),
+    Switch(GlslExpression, Vec<GlslStatement>, GlslPosition),
+    Case(GlslExpression),
+    Default,
+    Return(Option<GlslExpression>),
+    Break,
+    Continue,
+    Discard(GlslPosition),
+    Empty
+}
+
+#[derive(Debug, Clone)]
+pub struct GlslExpression {
+    pub kind : GlslExpressionKind,
+    pub position : GlslPosition
+}
+
+#[derive(Debug, Clone)]
+pub enum GlslExpressionKind {
+    Identifier(String),
+    IntConstant(String),
+    FloatConstant(String),
+    BoolConstant(bool),
+    Unary(&'static str, Box<GlslExpression>),
+    ///
+    /// Postfix `++` or `--`.
+    ///
+    Postfix(&'static str, Box<GlslExpression>),
+    Binary(&'static str, Box<GlslExpression>, Box<GlslExpression>),
+    Assign(&'static str, Box<GlslExpression>, Box<GlslExpression>),
+    Conditional(Box<GlslExpression>, Box<GlslExpression>, Box<GlslExpression>),
+    Comma(Box<GlslExpression>, Box<GlslExpression>),
+    ///
+    /// Function call or constructor. Array constructors like `float[3](...)`
+    /// are named by their element type.
+    ///
+    Call(String, Vec<GlslExpression>),
+    ///
+    /// Method call, i.e. `array.length()`.
+    ///
+    MethodCall(Box<GlslExpression>, String, Vec<GlslExpression>),
+    Index(Box<GlslExpression>, Box<GlslExpression>),
+    ///
+    /// Struct member or swizzle.
+    ///
+    Field(Box<GlslExpression>, String),
+    ///
+    /// Initializer list, e.g. `{ 1.0, 2.0 }`.
+    ///
+    List(Vec<GlslExpression>)
+}
+
+impl GlslQualifiers {
+    pub fn layout_value(&self, name : &str) -> Option<&str> {
+        self.layout.iter()
+            .find(|&&(ref n, _)| n == name)
+            .and_then(|&(_, ref v)| v.as_ref().map(|v| &v[..]))
+    }
+
+    pub fn has_layout(&self, name : &str) -> bool {
+        self.layout.iter().any(|&(ref n, _)| n == name)
+    }
+
+    pub fn is_subroutine(&self) -> bool {
+        self.other.iter().any(|q| q == "subroutine")
+    }
+}
+
+impl GlslTranslationUnit {
+    pub fn variables(&self) -> Vec<&GlslVariable> {
+        self.declarations.iter().filter_map(|d| match *d {
+            GlslDeclaration::Variable(ref v) => Some(v),
+            _ => None
+        }).collect()
+    }
+
+    pub fn blocks(&self) -> Vec<&GlslBlock> {
+        self.declarations.iter().filter_map(|d| match *d {
+            GlslDeclaration::Block(ref b) => Some(b),
+            _ => None
+        }).collect()
+    }
+
+    pub fn structs(&self) -> Vec<&GlslStruct> {
+        self.declarations.iter().filter_map(|d| match *d {
+            GlslDeclaration::Struct(ref s) => Some(s),
+            _ => None
+        }).collect()
+    }
+}
+
+///
+/// Parses a GLSL source. Errors are reported as `source:line:column: message`.
+///
+pub fn parse_glsl(source : &str) -> Result<GlslTranslationUnit> {
+    let (tokens, version) = tokenize(source)?;
+    let mut parser = Parser { tokens: tokens, pos: 0 };
+    let mut declarations = Vec::new();
+    while !parser.at_end() {
+        parser.parse_external_declaration(&mut declarations)?;
+    }
+    Ok(GlslTranslationUnit {
+        version: version,
+        declarations: declarations
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    Identifier(String),
+    IntConstant(String),
+    FloatConstant(String),
+    BoolConstant(bool),
+    Punct(&'static str)
+}
+
+#[derive(Debug, Clone)]
+pub struct Token {
+    pub kind : TokenKind,
+    pub position : GlslPosition
+}
+
+const PUNCTUATORS : &[&str] = &[
+    "<<=", ">>=",
+    "++", "--", "<<", ">>", "<=", ">=", "==", "!=", "&&", "||", "^^",
+    "+=", "-=", "*=", "/=", "%=", "&=", "^=", "|=",
+    "(", ")", "[", "]", "{", "}", ".", ",", ":", "=", ";", "!", "-", "~", "+",
+    "*", "/", "%", "<", ">", "|", "^", "&", "?"
+];
+
+const ASSIGNMENT_OPERATORS : &[&str] = &["=", "+=", "-=", "*=", "/=", "%=", "<<=", ">>=", "&=", "^=", "|="];
+
+const BINARY_OPERATORS : &[(&str, u32)] = &[
+    ("||", 1), ("^^", 2), ("&&", 3), ("|", 4), ("^", 5), ("&", 6),
+    ("==", 7), ("!=", 7), ("<", 8), (">", 8), ("<=", 8), (">=", 8),
+    ("<<", 9), (">>", 9), ("+", 10), ("-", 10), ("*", 11), ("/", 11), ("%", 11)
+];
+
+///
+/// Splits the source into tokens, dropping comments and preprocessor lines.
+/// Follows `#line` directives and returns the `#version` directive if there
+/// is one.
+///
+pub fn tokenize(source : &str) -> Result<(Vec<Token>, Option<GlslVersion>)> {
+    let chars : Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut version : Option<GlslVersion> = None;
+    let mut i = 0;
+    let mut line = 1;
+    let mut line_start = 0;
+    let mut at_line_start = true;
+    // Reported line is the physical line plus line_delta.
+    let mut line_delta : isize = 0;
+    let mut source_number = 0;
+
+    macro_rules! position {
+        ($column:expr) => (GlslPosition {
+            source: source_number,
+            line: (line as isize + line_delta) as usize,
+            column: $column
+        })
+    }
+
+    while i < chars.len() {
+        let c = chars[i];
+        let column = i - line_start + 1;
+        if c == '\n' {
+            i += 1;
+            line += 1;
+            line_start = i;
+            at_line_start = true;
+            continue;
+        }
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        if c == '\\' && i + 1 < chars.len() && chars[i + 1] == '\n' {
+            i += 2;
+            line += 1;
+            line_start = i;
+            continue;
+        }
+        if c == '/' && i + 1 < chars.len() && chars[i + 1] == '/' {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+            continue;
+        }
+        if c == '/' && i + 1 < chars.len() && chars[i + 1] == '*' {
+            let start = position!(column);
+            i += 2;
+            loop {
+                if i + 1 >= chars.len() {
+                    return Err(GlError::new(format!("{}:{}:{}: unterminated comment",
+                                                    start.source, start.line, start.column)));
+                }
+                if chars[i] == '*' && chars[i + 1] == '/' {
+                    i += 2;
+                    break;
+                }
+                if chars[i] == '\n' {
+                    line += 1;
+                    line_start = i + 1;
+                }
+                i += 1;
+            }
+            continue;
+        }
+        if c == '#' && at_line_start {
+            let directive_position = position!(column);
+            let start = i;
+            while i < chars.len() && chars[i] != '\n' {
+                if chars[i] == '\\' && i + 1 < chars.len() && chars[i + 1] == '\n' {
+                    line += 1;
+                    line_start = i + 2;
+                    i += 1;
+                }
+                i += 1;
+            }
+            let directive : String = chars[start + 1..i].iter().collect();
+            let directive = directive.trim();
+            if directive.starts_with("version") {
+                version = Some(parse_version(&directive["version".len()..], directive_position)?);
+            } else if directive.starts_with("line") {
+                let mut words = directive["line".len()..].split_whitespace();
+                if let Some(number) = words.next().and_then(|w| w.parse::<isize>().ok()) {
+                    // Before GLSL 3.30 the line after `#line n` is n + 1.
+                    let next_line_numbering = version.map_or(false, |v| v.number >= 330 || (v.es && v.number >= 300));
+                    let next_line = if next_line_numbering { number } else { number + 1 };
+                    line_delta = next_line - (line as isize + 1);
+                    if let Some(number) = words.next().and_then(|w| w.parse::<usize>().ok()) {
+                        source_number = number;
+                    }
+                }
+            }
+            continue;
+        }
+        at_line_start = false;
+
+        if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let word : String = chars[start..i].iter().collect();
+            let kind = match &word[..] {
+                "true" => TokenKind::BoolConstant(true),
+                "false" => TokenKind::BoolConstant(false),
+                _ => TokenKind::Identifier(word)
+            };
+            tokens.push(Token { kind: kind, position: position!(column) });
+            continue;
+        }
+
+        if c.is_ascii_digit() || (c == '.' && i + 1 < chars.len() && chars[i + 1].is_ascii_digit()) {
+            let start = i;
+            let mut is_float = false;
+            if c == '0' && i + 1 < chars.len() && (chars[i + 1] == 'x' || chars[i + 1] == 'X') {
+                i += 2;
+                while i < chars.len() && chars[i].is_ascii_hexdigit() {
+                    i += 1;
+                }
+            } else {
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+                if i < chars.len() && chars[i] == '.' {
+                    is_float = true;
+                    i += 1;
+                    while i < chars.len() && chars[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+                if i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+                    is_float = true;
+                    i += 1;
+                    if i < chars.len() && (chars[i] == '+' || chars[i] == '-') {
+                        i += 1;
+                    }
+                    while i < chars.len() && chars[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+            }
+            // Suffixes: u/U for unsigned, f/F or lf/LF for floats.
+            while i < chars.len() && (chars[i] == 'u' || chars[i] == 'U' || chars[i] == 'f' || chars[i] == 'F'
+                                      || chars[i] == 'l' || chars[i] == 'L') {
+                if chars[i] != 'u' && chars[i] != 'U' {
+                    is_float = true;
+                }
+                i += 1;
+            }
+            let text : String = chars[start..i].iter().collect();
+            let kind = if is_float { TokenKind::FloatConstant(text) } else { TokenKind::IntConstant(text) };
+            tokens.push(Token { kind: kind, position: position!(column) });
+            continue;
+        }
+
+        let punct = PUNCTUATORS.iter().find(|p| {
+            let p : Vec<char> = p.chars().collect();
+            chars[i..].starts_with(&p)
+        });
+        match punct {
+            Some(p) => {
+                i += p.len();
+                tokens.push(Token { kind: TokenKind::Punct(p), position: position!(column) });
+            }
+            None => {
+                let position = position!(column);
+                return Err(GlError::new(format!("{}:{}:{}: unexpected character {:?}",
+                                                position.source, position.line, position.column, c)));
+            }
+        }
+    }
+    Ok((tokens, version))
+}
+
+fn parse_version(args : &str, position : GlslPosition) -> Result<GlslVersion> {
+    let mut words = args.split_whitespace();
+    let number = words.next()
+        .and_then(|w| w.parse::<u32>().ok())
+        .ok_or_else(|| GlError::new(format!("{}:{}:{}: malformed #version", position.source, position.line, position.column)))?;
+    let profile = words.next();
+    Ok(GlslVersion {
+        number: number,
+        es: profile == Some("es") || number == 100,
+        compatibility: profile == Some("compatibility")
+    })
+}
+
+const STORAGE_QUALIFIERS : &[(&str, GlslStorage)] = &[
+    ("const", GlslStorage::Const),
+    ("in", GlslStorage::In),
+    ("out", GlslStorage::Out),
+    ("inout", GlslStorage::InOut),
+    ("attribute", GlslStorage::Attribute),
+    ("varying", GlslStorage::Varying),
+    ("uniform", GlslStorage::Uniform),
+    ("buffer", GlslStorage::Buffer),
+    ("shared", GlslStorage::Shared)
+];
+
+const INTERPOLATION_QUALIFIERS : &[&str] = &["flat", "smooth", "noperspective"];
+
+const OTHER_QUALIFIERS : &[&str] = &[
+    "centroid", "sample", "patch", "invariant", "precise", "highp", "mediump", "lowp",
+    "coherent", "volatile", "restrict", "readonly", "writeonly", "subroutine"
+];
+
+pub struct Parser {
+    pub tokens : Vec<Token>,
+    pub pos : usize
+}
+
+impl Parser {
+    pub fn at_end(&self) -> bool {
+        self.pos >= self.tokens.len()
+    }
+
+    pub fn peek(&self) -> Option<&TokenKind> {
+        self.tokens.get(self.pos).map(|t| &t.kind)
+    }
+
+    pub fn peek_at(&self, offset : usize) -> Option<&TokenKind> {
+        self.tokens.get(self.pos + offset).map(|t| &t.kind)
+    }
+
+    pub fn position(&self) -> GlslPosition {
+        self.tokens.get(self.pos).or_else(|| self.tokens.last()).map_or(GlslPosition::default(), |t| t.position)
+    }
+
+    pub fn error<T>(&self, message : &str) -> Result<T> {
+        let position = self.position();
+        let found = match self.tokens.get(self.pos) {
+            Some(token) => format!("`{}`", token_text(&token.kind)),
+            None => "end of file".to_string()
+        };
+        Err(GlError::new(format!("{}:{}:{}: {}, found {}", position.source, position.line, position.column,
+                                 message, found)))
+    }
+
+    pub fn is_punct(&self, punct : &str) -> bool {
+        match self.peek() {
+            Some(&TokenKind::Punct(p)) => p == punct,
+            _ => false
+        }
+    }
+
+    pub fn is_identifier(&self, word : &str) -> bool {
+        match self.peek() {
+            Some(&TokenKind::Identifier(ref w)) => w == word,
+            _ => false
+        }
+    }
+
+    pub fn eat_punct(&mut self, punct : &str) -> bool {
+        if self.is_punct(punct) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn expect_punct(&mut self, punct : &str) -> Result<()> {
+        if self.eat_punct(punct) {
+            Ok(())
+        } else {
+            self.error(&format!("expected `{}`", punct))
+        }
+    }
+
+    pub fn expect_identifier(&mut self) -> Result<String> {
+        let word = match self.peek() {
+            Some(&TokenKind::Identifier(ref w)) => w.clone(),
+            _ => return self.error("expected an identifier")
+        };
+        self.pos += 1;
+        Ok(word)
+    }
+
+    fn parse_external_declaration(&mut self, declarations : &mut Vec<GlslDeclaration>) -> Result<()> {
+        if self.eat_punct(";") {
+            return Ok(());
+        }
+        let position = self.position();
+        if self.is_identifier("precision") {
+            declarations.push(self.parse_precision()?);
+            return Ok(());
+        }
+
+        let qualifiers = self.parse_qualifiers()?;
+        if self.eat_punct(";") {
+            declarations.push(GlslDeclaration::Qualifiers(qualifiers));
+            return Ok(());
+        }
+
+        // Interface block: qualifiers BlockName { ... } instance;
+        let is_block = qualifiers.storage.map_or(false, |s| s != GlslStorage::Const)
+            && match (self.peek(), self.peek_at(1)) {
+                (Some(&TokenKind::Identifier(_)), Some(&TokenKind::Punct("{"))) => true,
+                _ => false
+            };
+        if is_block {
+            let block = self.parse_block(qualifiers, position)?;
+            declarations.push(GlslDeclaration::Block(block));
+            return Ok(());
+        }
+
+        let type_name = self.parse_type(declarations)?;
+        if self.eat_punct(";") {
+            // Bare struct definition.
+            return Ok(());
+        }
+        let name_position = self.position();
+        let name = self.expect_identifier()?;
+
+        if self.is_punct("(") {
+            let function = self.parse_function(qualifiers, type_name, name, position)?;
+            declarations.push(GlslDeclaration::Function(function));
+            return Ok(());
+        }
+
+        self.parse_declarators(qualifiers, type_name, name, name_position, declarations)
+    }
+
+    ///
+    /// Parses `name[..] = init, name2 ...;` after the type of a declaration.
+    ///
+    fn parse_declarators(&mut self,
+                         qualifiers : GlslQualifiers,
+                         type_name : String,
+                         first_name : String,
+                         first_position : GlslPosition,
+                         declarations : &mut Vec<GlslDeclaration>) -> Result<()> {
+        let mut name = first_name;
+        let mut position = first_position;
+        loop {
+            let array = self.parse_array_dims()?;
+            let initializer = if self.eat_punct("=") {
+                Some(self.parse_initializer()?)
+            } else {
+                None
+            };
+            declarations.push(GlslDeclaration::Variable(GlslVariable {
+                qualifiers: qualifiers.clone(),
+                type_name: type_name.clone(),
+                name: name,
+                array: array,
+                initializer: initializer,
+                position: position
+            }));
+            if self.eat_punct(",") {
+                position = self.position();
+                name = self.expect_identifier()?;
+                continue;
+            }
+            return self.expect_punct(";");
+        }
+    }
+
+    fn parse_precision(&mut self) -> Result<GlslDeclaration> {
+        self.pos += 1;
+        let precision = self.expect_identifier()?;
+        let type_name = self.expect_identifier()?;
+        self.expect_punct(";")?;
+        Ok(GlslDeclaration::Precision(precision, type_name))
+    }
+
+    pub fn parse_qualifiers(&mut self) -> Result<GlslQualifiers> {
+        let mut qualifiers = GlslQualifiers::default();
+        loop {
+            let word = match self.peek() {
+                Some(&TokenKind::Identifier(ref w)) => w.clone(),
+                _ => return Ok(qualifiers)
+            };
+            if word == "layout" {
+                self.pos += 1;
+                self.parse_layout(&mut qualifiers)?;
+            } else if let Some(&(_, storage)) = STORAGE_QUALIFIERS.iter().find(|&&(q, _)| q == word) {
+                self.pos += 1;
+                // `const in` parameters keep `in` as the storage.
+                if qualifiers.storage.is_none() || qualifiers.storage == Some(GlslStorage::Const) {
+                    qualifiers.storage = Some(storage);
+                }
+            } else if INTERPOLATION_QUALIFIERS.contains(&&word[..]) {
+                self.pos += 1;
+                qualifiers.interpolation = Some(word);
+            } else if OTHER_QUALIFIERS.contains(&&word[..]) {
+                self.pos += 1;
+                // Subroutine functions list their types: `subroutine(A, B)`.
+                if word == "subroutine" && self.eat_punct("(") {
+                    loop {
+                        qualifiers.subroutine_types.push(self.expect_identifier()?);
+                        if self.eat_punct(")") {
+                            break;
+                        }
+                        self.expect_punct(",")?;
+                    }
+                }
+                qualifiers.other.push(word);
+            } else {
+                return Ok(qualifiers);
+            }
+        }
+    }
+
+    fn parse_layout(&mut self, qualifiers : &mut GlslQualifiers) -> Result<()> {
+        self.expect_punct("(")?;
+        loop {
+            let name = self.expect_identifier()?;
+            let value = if self.eat_punct("=") {
+                Some(self.collect_until(&[",", ")"])?)
+            } else {
+                None
+            };
+            qualifiers.layout.push((name, value));
+            if self.eat_punct(")") {
+                return Ok(());
+            }
+            self.expect_punct(",")?;
+        }
+    }
+
+    ///
+    /// Parses a type specifier. Struct definitions are added to `declarations`
+    /// and their name is returned as the type.
+    ///
+    fn parse_type(&mut self, declarations : &mut Vec<GlslDeclaration>) -> Result<String> {
+        if self.is_identifier("struct") {
+            let position = self.position();
+            self.pos += 1;
+            let name = self.expect_identifier()?;
+            let members = self.parse_members()?;
+            declarations.push(GlslDeclaration::Struct(GlslStruct {
+                name: name.clone(),
+                members: members,
+                position: position
+            }));
+            return Ok(name);
+        }
+        let mut type_name = self.expect_identifier()?;
+        // Array types, e.g. `float[4] weights;`
+        while self.is_punct("[") {
+            let dims = self.parse_array_dims()?;
+            for dim in dims {
+                type_name = format!("{}[{}]", type_name, dim);
+            }
+        }
+        Ok(type_name)
+    }
+
+    fn parse_members(&mut self) -> Result<Vec<GlslVariable>> {
+        self.expect_punct("{")?;
+        let mut members = Vec::new();
+        let mut nested = Vec::new();
+        while !self.eat_punct("}") {
+            let qualifiers = self.parse_qualifiers()?;
+            let type_name = self.parse_type(&mut nested)?;
+            loop {
+                let position = self.position();
+                let name = self.expect_identifier()?;
+                let array = self.parse_array_dims()?;
+                members.push(GlslVariable {
+                    qualifiers: qualifiers.clone(),
+                    type_name: type_name.clone(),
+                    name: name,
+                    array: array,
+                    initializer: None,
+                    position: position
+                });
+                if !self.eat_punct(",") {
+                    break;
+                }
+            }
+            self.expect_punct(";")?;
+        }
+        Ok(members)
+    }
+
+    fn parse_block(&mut self, qualifiers : GlslQualifiers, position : GlslPosition) -> Result<GlslBlock> {
+        let name = self.expect_identifier()?;
+        let members = self.parse_members()?;
+        let (instance_name, instance_array) = if self.eat_punct(";") {
+            (None, Vec::new())
+        } else {
+            let instance = self.expect_identifier()?;
+            let array = self.parse_array_dims()?;
+            self.expect_punct(";")?;
+            (Some(instance), array)
+        };
+        Ok(GlslBlock {
+            qualifiers: qualifiers,
+            name: name,
+            members: members,
+            instance_name: instance_name,
+            instance_array: instance_array,
+            position: position
+        })
+    }
+
+    fn parse_function(&mut self, qualifiers : GlslQualifiers, return_type : String, name : String,
+                      position : GlslPosition) -> Result<GlslFunction> {
+        self.expect_punct("(")?;
+        let mut parameters = Vec::new();
+        if !self.eat_punct(")") {
+            loop {
+                let param_position = self.position();
+                let qualifiers = self.parse_qualifiers()?;
+                let mut ignored = Vec::new();
+                let type_name = self.parse_type(&mut ignored)?;
+                let (param_name, array) = match self.peek() {
+                    Some(&TokenKind::Identifier(_)) => {
+                        let param_name = self.expect_identifier()?;
+                        (param_name, self.parse_array_dims()?)
+                    }
+                    _ => (String::new(), Vec::new())
+                };
+                // `void` as the only parameter means no parameters.
+                if !(type_name == "void" && param_name.is_empty()) {
+                    parameters.push(GlslVariable {
+                        qualifiers: qualifiers,
+                        type_name: type_name,
+                        name: param_name,
+                        array: array,
+                        initializer: None,
+                        position: param_position
+                    });
+                }
+                if self.eat_punct(")") {
+                    break;
+                }
+                self.expect_punct(",")?;
+            }
+        }
+        let body = if self.eat_punct(";") {
+            None
+        } else if self.is_punct("{") {
+            Some(self.parse_compound()?)
+        } else {
+            return self.error("expected `;` or a function body");
+        };
+        Ok(GlslFunction {
+            qualifiers: qualifiers,
+            return_type: return_type,
+            name: name,
+            parameters: parameters,
+            body: body,
+            position: position
+        })
+    }
+
+    pub fn parse_array_dims(&mut self) -> Result<Vec<String>> {
+        let mut dims = Vec::new();
+        while self.eat_punct("[") {
+            if self.eat_punct("]") {
+                dims.push(String::new());
+            } else {
+                dims.push(self.collect_until(&["]"])?);
+                self.expect_punct("]")?;
+            }
+        }
+        Ok(dims)
+    }
+
+    ///
+    /// Joins the tokens up to (not including) the first of `ends` at nesting
+    /// depth zero.
+    ///
+    fn collect_until(&mut self, ends : &[&str]) -> Result<String> {
+        let mut text = String::new();
+        let mut depth = 0;
+        loop {
+            let token = match self.tokens.get(self.pos) {
+                Some(token) => token.kind.clone(),
+                None => return self.error("unexpected end of file")
+            };
+            if let TokenKind::Punct(p) = token {
+                if depth == 0 && ends.contains(&p) {
+                    return Ok(text);
+                }
+                match p {
+                    "(" | "[" | "{" => depth += 1,
+                    ")" | "]" | "}" => depth -= 1,
+                    _ => ()
+                }
+            }
+            if !text.is_empty() {
+                text.push(' ');
+            }
+            text.push_str(&token_text(&token));
+            self.pos += 1;
+        }
+    }
+
+    fn parse_compound(&mut self) -> Result<Vec<GlslStatement>> {
+        self.expect_punct("{")?;
+        let mut statements = Vec::new();
+        while !self.eat_punct("}") {
+            if self.at_end() {
+                return self.error("expected `}`");
+            }
+            statements.push(self.parse_statement()?);
+        }
+        Ok(statements)
+    }
+
+    fn parse_statement(&mut self) -> Result<GlslStatement> {
+        if self.is_punct("{") {
+            return Ok(GlslStatement::Compound(self.parse_compound()?));
+        }
+        if self.eat_punct(";") {
+            return Ok(GlslStatement::Empty);
+        }
+        let position = self.position();
+        let keyword = match self.peek() {
+            Some(&TokenKind::Identifier(ref w)) => w.clone(),
+            _ => String::new()
+        };
+        match &keyword[..] {
+            "if" => {
+                self.pos += 1;
+                self.expect_punct("(")?;
+                let condition = self.parse_expression()?;
+                self.expect_punct(")")?;
+                let then = Box::new(self.parse_statement()?);
+                let otherwise = if self.is_identifier("else") {
+                    self.pos += 1;
+                    Some(Box::new(self.parse_statement()?))
+                } else {
+                    None
+                };
+                Ok(GlslStatement::If(condition, then, otherwise))
+            }
+            "for" => {
+                self.pos += 1;
+                self.expect_punct("(")?;
+                let init = Box::new(self.parse_simple_statement()?);
+                let condition = if self.is_punct(";") { None } else { Some(self.parse_expression()?) };
+                self.expect_punct(";")?;
+                let step = if self.is_punct(")") { None } else { Some(self.parse_expression()?) };
+                self.expect_punct(")")?;
+                let body = Box::new(self.parse_statement()?);
+                Ok(GlslStatement::For(init, condition, step, body))
+            }
+            "while" => {
+                self.pos += 1;
+                self.expect_punct("(")?;
+                let condition = self.parse_expression()?;
+                self.expect_punct(")")?;
+                Ok(GlslStatement::While(condition, Box::new(self.parse_statement()?)))
+            }
+            "do" => {
+                self.pos += 1;
+                let body = Box::new(self.parse_statement()?);
+                if !self.is_identifier("while") {
+                    return self.error("expected `while`");
+                }
+                self.pos += 1;
+                self.expect_punct("(")?;
+                let condition = self.parse_expression()?;
+                self.expect_punct(")")?;
+                self.expect_punct(";")?;
+                Ok(GlslStatement::DoWhile(body, condition))
+            }
+            "switch" => {
+                self.pos += 1;
+                self.expect_punct("(")?;
+                let value = self.parse_expression()?;
+                self.expect_punct(")")?;
+                Ok(GlslStatement::Switch(value, self.parse_compound()?, position))
+            }
+            "case" => {
+                self.pos += 1;
+                let value = self.parse_expression()?;
+                self.expect_punct(":")?;
+                Ok(GlslStatement::Case(value))
+            }
+            "default" => {
+                self.pos += 1;
+                self.expect_punct(":")?;
+                Ok(GlslStatement::Default)
+            }
+            "return" => {
+                self.pos += 1;
+                let value = if self.is_punct(";") { None } else { Some(self.parse_expression()?) };
+                self.expect_punct(";")?;
+                Ok(GlslStatement::Return(value))
+            }
+            "break" | "continue" | "discard" => {
+                self.pos += 1;
+                self.expect_punct(";")?;
+                Ok(match &keyword[..] {
+                    "break" => GlslStatement::Break,
+                    "continue" => GlslStatement::Continue,
+                    _ => GlslStatement::Discard(position)
+                })
+            }
+            _ => self.parse_simple_statement()
+        }
+    }
+
+    ///
+    /// Declaration or expression statement, including its `;`.
+    ///
+    fn parse_simple_statement(&mut self) -> Result<GlslStatement> {
+        if self.eat_punct(";") {
+            return Ok(GlslStatement::Empty);
+        }
+        if self.is_identifier("precision") {
+            return Ok(GlslStatement::Declaration(vec![self.parse_precision()?]));
+        }
+        if self.is_declaration_start() {
+            let mut declarations = Vec::new();
+            let qualifiers = self.parse_qualifiers()?;
+            let type_name = self.parse_type(&mut declarations)?;
+            if !self.eat_punct(";") {
+                let position = self.position();
+                let name = self.expect_identifier()?;
+                self.parse_declarators(qualifiers, type_name, name, position, &mut declarations)?;
+            }
+            return Ok(GlslStatement::Declaration(declarations));
+        }
+        let expression = self.parse_expression()?;
+        self.expect_punct(";")?;
+        Ok(GlslStatement::Expression(expression))
+    }
+
+    ///
+    /// Whether a statement starts with a declaration: qualifiers, `struct`,
+    /// or a type followed by a name, e.g. `vec3 x` or `float[2] y`.
+    ///
+    fn is_declaration_start(&self) -> bool {
+        let word = match self.peek() {
+            Some(&TokenKind::Identifier(ref w)) => w,
+            _ => return false
+        };
+        if word == "struct" || word == "layout"
+            || STORAGE_QUALIFIERS.iter().any(|&(q, _)| q == word)
+            || INTERPOLATION_QUALIFIERS.contains(&&word[..])
+            || OTHER_QUALIFIERS.contains(&&word[..]) {
+            return true;
+        }
+        let mut offset = 1;
+        while let Some(&TokenKind::Punct("[")) = self.peek_at(offset) {
+            let mut depth = 0;
+            loop {
+                match self.peek_at(offset) {
+                    Some(&TokenKind::Punct("[")) => depth += 1,
+                    Some(&TokenKind::Punct("]")) => depth -= 1,
+                    None => return false,
+                    _ => ()
+                }
+                offset += 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+        }
+        match self.peek_at(offset) {
+            Some(&TokenKind::Identifier(_)) => true,
+            _ => false
+        }
+    }
+
+    fn parse_initializer(&mut self) -> Result<GlslExpression> {
+        if self.is_punct("{") {
+            let position = self.position();
+            self.pos += 1;
+            let mut items = Vec::new();
+            while !self.eat_punct("}") {
+                items.push(self.parse_initializer()?);
+                if !self.eat_punct(",") {
+                    self.expect_punct("}")?;
+                    break;
+                }
+            }
+            return Ok(GlslExpression { kind: GlslExpressionKind::List(items), position: position });
+        }
+        self.parse_assignment()
+    }
+
+    pub fn parse_expression(&mut self) -> Result<GlslExpression> {
+        let mut expression = self.parse_assignment()?;
+        while self.is_punct(",") {
+            let position = self.position();
+            self.pos += 1;
+            let right = self.parse_assignment()?;
+            expression = GlslExpression {
+                kind: GlslExpressionKind::Comma(Box::new(expression), Box::new(right)),
+                position: position
+            };
+        }
+        Ok(expression)
+    }
+
+    fn parse_assignment(&mut self) -> Result<GlslExpression> {
+        let left = self.parse_conditional()?;
+        let operator = match self.peek() {
+            Some(&TokenKind::Punct(p)) if ASSIGNMENT_OPERATORS.contains(&p) => p,
+            _ => return Ok(left)
+        };
+        let position = self.position();
+        self.pos += 1;
+        let right = self.parse_assignment()?;
+        Ok(GlslExpression {
+            kind: GlslExpressionKind::Assign(operator, Box::new(left), Box::new(right)),
+            position: position
+        })
+    }
+
+    fn parse_conditional(&mut self) -> Result<GlslExpression> {
+        let condition = self.parse_binary(1)?;
+        if !self.is_punct("?") {
+            return Ok(condition);
+        }
+        let position = self.position();
+        self.pos += 1;
+        let then = self.parse_expression()?;
+        self.expect_punct(":")?;
+        let otherwise = self.parse_assignment()?;
+        Ok(GlslExpression {
+            kind: GlslExpressionKind::Conditional(Box::new(condition), Box::new(then), Box::new(otherwise)),
+            position: position
+        })
+    }
+
+    fn parse_binary(&mut self, min_precedence : u32) -> Result<GlslExpression> {
+        let mut left = self.parse_unary()?;
+        loop {
+            let (operator, precedence) = match self.peek() {
+                Some(&TokenKind::Punct(p)) => match BINARY_OPERATORS.iter().find(|&&(o, _)| o == p) {
+                    Some(&(o, precedence)) if precedence >= min_precedence => (o, precedence),
+                    _ => return Ok(left)
+                },
+                _ => return Ok(left)
+            };
+            let position = self.position();
+            self.pos += 1;
+            let right = self.parse_binary(precedence + 1)?;
+            left = GlslExpression {
+                kind: GlslExpressionKind::Binary(operator, Box::new(left), Box::new(right)),
+                position: position
+            };
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<GlslExpression> {
+        let operator = match self.peek() {
+            Some(&TokenKind::Punct(p)) if ["++", "--", "+", "-", "!", "~"].contains(&p) => p,
+            _ => return self.parse_postfix()
+        };
+        let position = self.position();
+        self.pos += 1;
+        let operand = self.parse_unary()?;
+        Ok(GlslExpression {
+            kind: GlslExpressionKind::Unary(operator, Box::new(operand)),
+            position: position
+        })
+    }
+
+    fn parse_postfix(&mut self) -> Result<GlslExpression> {
+        let mut expression = self.parse_primary()?;
+        loop {
+            let position = self.position();
+            let kind = if self.eat_punct("[") {
+                let index = self.parse_expression()?;
+                self.expect_punct("]")?;
+                GlslExpressionKind::Index(Box::new(expression), Box::new(index))
+            } else if self.eat_punct(".") {
+                let name = self.expect_identifier()?;
+                if self.is_punct("(") {
+                    let arguments = self.parse_arguments()?;
+                    GlslExpressionKind::MethodCall(Box::new(expression), name, arguments)
+                } else {
+                    GlslExpressionKind::Field(Box::new(expression), name)
+                }
+            } else if self.is_punct("++") || self.is_punct("--") {
+                let operator = if self.is_punct("++") { "++" } else { "--" };
+                self.pos += 1;
+                GlslExpressionKind::Postfix(operator, Box::new(expression))
+            } else {
+                return Ok(expression);
+            };
+            expression = GlslExpression { kind: kind, position: position };
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<GlslExpression> {
+        let position = self.position();
+        let token = match self.peek() {
+            Some(token) => token.clone(),
+            None => return self.error("expected an expression")
+        };
+        let kind = match token {
+            TokenKind::IntConstant(text) => {
+                self.pos += 1;
+                GlslExpressionKind::IntConstant(text)
+            }
+            TokenKind::FloatConstant(text) => {
+                self.pos += 1;
+                GlslExpressionKind::FloatConstant(text)
+            }
+            TokenKind::BoolConstant(value) => {
+                self.pos += 1;
+                GlslExpressionKind::BoolConstant(value)
+            }
+            TokenKind::Punct("(") => {
+                self.pos += 1;
+                let expression = self.parse_expression()?;
+                self.expect_punct(")")?;
+                return Ok(expression);
+            }
+            TokenKind::Identifier(name) => {
+                self.pos += 1;
+                if self.is_punct("(") {
+                    GlslExpressionKind::Call(name, self.parse_arguments()?)
+                } else if self.is_punct("[") && self.is_array_constructor() {
+                    self.parse_array_dims()?;
+                    GlslExpressionKind::Call(name, self.parse_arguments()?)
+                } else {
+                    GlslExpressionKind::Identifier(name)
+                }
+            }
+            _ => return self.error("expected an expression")
+        };
+        Ok(GlslExpression { kind: kind, position: position })
+    }
+
+    ///
+    /// Whether the brackets at the current token are followed by `(`, as in
+    /// `float[3](...)`.
+    ///
+    fn is_array_constructor(&self) -> bool {
+        let mut offset = 0;
+        let mut depth = 0;
+        loop {
+            match self.peek_at(offset) {
+                Some(&TokenKind::Punct("[")) => depth += 1,
+                Some(&TokenKind::Punct("]")) => {
+                    depth -= 1;
+                    if depth == 0 && self.peek_at(offset + 1) != Some(&TokenKind::Punct("[")) {
+                        return self.peek_at(offset + 1) == Some(&TokenKind::Punct("("));
+                    }
+                }
+                None => return false,
+                _ => ()
+            }
+            offset += 1;
+        }
+    }
+
+    fn parse_arguments(&mut self) -> Result<Vec<GlslExpression>> {
+        self.expect_punct("(")?;
+        let mut arguments = Vec::new();
+        if self.eat_punct(")") {
+            return Ok(arguments);
+        }
+        // `f(void)` has no arguments.
+        if self.is_identifier("void") && self.peek_at(1) == Some(&TokenKind::Punct(")")) {
+            self.pos += 2;
+            return Ok(arguments);
+        }
+        loop {
+            arguments.push(self.parse_assignment()?);
+            if self.eat_punct(")") {
+                return Ok(arguments);
+            }
+            self.expect_punct(",")?;
+        }
+    }
+}
+
+pub fn token_text(kind : &TokenKind) -> String {
+    match *kind {
+        TokenKind::Identifier(ref s) | TokenKind::IntConstant(ref s) | TokenKind::FloatConstant(ref s) => s.clone(),
+        TokenKind::BoolConstant(b) => b.to_string(),
+        TokenKind::Punct(p) => p.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_qualified_variables() {
+        let unit = parse_glsl("#version 330 core\n\
+                               layout(location = 2) in vec3 pos, normal;\n\
+                               flat out int id;\n\
+                               uniform highp float weights[4];\n").unwrap();
+        assert_eq!(unit.version, Some(GlslVersion { number: 330, es: false, compatibility: false }));
+        let variables = unit.variables();
+        assert_eq!(variables.len(), 4);
+        assert_eq!((&variables[1].name[..], &variables[1].type_name[..]), ("normal", "vec3"));
+        assert_eq!(variables[1].qualifiers.layout_value("location"), Some("2"));
+        assert_eq!(variables[2].qualifiers.storage, Some(GlslStorage::Out));
+        assert_eq!(variables[2].qualifiers.interpolation, Some("flat".to_string()));
+        assert_eq!(variables[3].array, vec!["4"]);
+        assert_eq!(variables[3].qualifiers.other, vec!["highp"]);
+    }
+
+    #[test]
+    fn parses_blocks_and_structs() {
+        let unit = parse_glsl("struct Light { vec3 color; float radius[2]; };\n\
+                               layout(std140, binding = 1) uniform Lights { Light lights[8]; mat4 view; } lighting;\n\
+                               buffer Points { vec4 points[]; };\n\
+                               uniform Light sun;\n").unwrap();
+        let structs = unit.structs();
+        assert_eq!(structs.len(), 1);
+        assert_eq!(structs[0].members[1].array, vec!["2"]);
+
+        let blocks = unit.blocks();
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].name, "Lights");
+        assert!(blocks[0].qualifiers.has_layout("std140"));
+        assert_eq!(blocks[0].qualifiers.layout_value("binding"), Some("1"));
+        assert_eq!(blocks[0].instance_name, Some("lighting".to_string()));
+        assert_eq!(blocks[0].members[0].type_name, "Light");
+        assert_eq!(blocks[1].qualifiers.storage, Some(GlslStorage::Buffer));
+        assert_eq!(blocks[1].members[0].array, vec![""]);
+
+        assert_eq!(unit.variables()[0].type_name, "Light");
+    }
+
+    #[test]
+    fn parses_functions_and_qualifier_declarations() {
+        let unit = parse_glsl("layout(local_size_x = 8) in;\n\
+                               precision mediump float;\n\
+                               float square(float x);\n\
+                               float square(float x) { for (int i = 0; i < 2; i++) { x *= x; } return x; }\n").unwrap();
+        match unit.declarations[0] {
+            GlslDeclaration::Qualifiers(ref q) => assert_eq!(q.layout_value("local_size_x"), Some("8")),
+            ref other => panic!("{:?}", other)
+        }
+        match unit.declarations[1] {
+            GlslDeclaration::Precision(ref precision, ref type_name) => assert_eq!((&precision[..], &type_name[..]), ("mediump", "float")),
+            ref other => panic!("{:?}", other)
+        }
+        match (&unit.declarations[2], &unit.declarations[3]) {
+            (&GlslDeclaration::Function(ref prototype), &GlslDeclaration::Function(ref definition)) => {
+                assert!(prototype.body.is_none());
+                assert_eq!(definition.parameters[0].name, "x");
+                assert_eq!(definition.body.as_ref().map(|body| body.len()), Some(2));
+            }
+            ref other => panic!("{:?}", other)
+        }
+    }
+
+    #[test]
+    fn parses_subroutines() {
+        let unit = parse_glsl("subroutine vec4 Shade();\n\
+                               subroutine(Shade, Tint) vec4 red() { return vec4(1.0); }\n\
+                               subroutine uniform Shade shade;\n").unwrap();
+        match (&unit.declarations[0], &unit.declarations[1]) {
+            (&GlslDeclaration::Function(ref prototype), &GlslDeclaration::Function(ref definition)) => {
+                assert_eq!(prototype.qualifiers.other, vec!["subroutine"]);
+                assert!(prototype.qualifiers.subroutine_types.is_empty());
+                assert_eq!(definition.qualifiers.subroutine_types, vec!["Shade", "Tint"]);
+            }
+            ref other => panic!("{:?}", other)
+        }
+        let shade = unit.variables()[0];
+        assert_eq!((&shade.type_name[..], shade.qualifiers.storage), ("Shade", Some(GlslStorage::Uniform)));
+    }
+
+    #[test]
+    fn positions_follow_line_directives() {
+        let unit = parse_glsl("#version 330\nuniform float a;\n#line 10 2\nuniform float b;\n").unwrap();
+        let variables = unit.variables();
+        assert_eq!(variables[0].position, GlslPosition { source: 0, line: 2, column: 15 });
+        assert_eq!(variables[1].position, GlslPosition { source: 2, line: 10, column: 15 });
+
+        // Before GLSL 3.30 the line after `#line n` is n + 1.
+        let unit = parse_glsl("#version 150\n#line 10 1\nuniform float b;\n").unwrap();
+        assert_eq!(unit.variables()[0].position.line, 11);
+    }
+
+    #[test]
+    fn errors_name_their_position() {
+        let err = parse_glsl("uniform float a;\nuniform vec3 b c;\n").unwrap_err();
+        assert_eq!(err.to_string(), "0:2:16: expected `;`, found `c`");
+        let err = parse_glsl("/* open\n").unwrap_err();
+        assert_eq!(err.to_string(), "0:1:1: unterminated comment");
+    }
+
+    #[test]
+    fn ignores_comments_and_other_directives() {
+        let unit = parse_glsl("// uniform float a;\n/* uniform float b; */\n#define X 1\n#ifdef X\nuniform float c;\n#endif\n").unwrap();
+        let names : Vec<_> = unit.variables().iter().map(|v| v.name.clone()).collect();
+        assert_eq!(names, vec!["c"]);
+    }
+}
@@ -0,0 +1,425 @@
+use gl_err::*;
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+///
+/// Source of files for `#include` directives. Paths use `/` as separator and
+/// are relative to the root of the file system.
+///
+pub trait ShaderFileSystem {
+    fn read_file(&self, path : &str) -> Result<String>;
+}
+
+///
+/// Reads includes from a directory on disk.
+///
+pub struct DirectoryFileSystem {
+    pub root : PathBuf
+}
+
+///
+/// Reads includes from sources compiled into the binary, e.g. with `include_str!`.
+///
+pub struct EmbeddedFileSystem {
+    files : HashMap<&'static str, &'static str>
+}
+
+///
+/// Reads includes from sources built at runtime.
+///
+pub struct MemoryFileSystem {
+    files : HashMap<String, String>
+}
+
+///
+/// Preprocessed source with `#line` directives. The source string numbers in
+/// the directives index `files`; the root file is number 0.
+///
+#[derive(Debug, Clone)]
+pub struct PreprocessedSource {
+    pub source : String,
+    pub files : Vec<String>
+}
+
+impl DirectoryFileSystem {
+    pub fn new<P: Into<PathBuf>>(root : P) -> DirectoryFileSystem {
+        DirectoryFileSystem { root: root.into() }
+    }
+}
+
+impl ShaderFileSystem for DirectoryFileSystem {
+    fn read_file(&self, path : &str) -> Result<String> {
+        let full_path = self.root.join(path);
+        fs::read_to_string(&full_path)
+            .map_err(|err| GlError::new(format!("Error reading {}: {}", full_path.display(), err)))
+    }
+}
+
+impl EmbeddedFileSystem {
+    pub fn new(files : &[(&'static str, &'static str)]) -> EmbeddedFileSystem {
+        EmbeddedFileSystem { files: files.iter().cloned().collect() }
+    }
+}
+
+impl ShaderFileSystem for EmbeddedFileSystem {
+    fn read_file(&self, path : &str) -> Result<String> {
+        match self.files.get(path) {
+            Some(source) => Ok(source.to_string()),
+            None => Err(GlError::new(format!("No embedded shader file {:?}", path)))
+        }
+    }
+}
+
+impl MemoryFileSystem {
+    pub fn new() -> MemoryFileSystem {
+        MemoryFileSystem { files: HashMap::new() }
+    }
+
+    pub fn insert(&mut self, path : &str, source : &str) {
+        self.files.insert(path.to_string(), source.to_string());
+    }
+}
+
+impl ShaderFileSystem for MemoryFileSystem {
+    fn read_file(&self, path : &str) -> Result<String> {
+        match self.files.get(path) {
+            Some(source) => Ok(source.clone()),
+            None => Err(GlError::new(format!("No shader file {:?}", path)))
+        }
+    }
+}
+
+impl PreprocessedSource {
+    ///
+    /// Replaces source string numbers in a compiler log with file names, so
+    /// `0(12) : error` or `ERROR: 1:12: ...` name the file the line came from.
+    ///
+    pub fn annotate_log(&self, log : &str) -> String {
+        let lines : Vec<_> = log.lines().map(|line| self.annotate_line(line)).collect();
+        lines.join("\n")
+    }
+
+    fn annotate_line(&self, line : &str) -> String {
+        let mut prefix_len = 0;
+        for prefix in &["ERROR: ", "WARNING: ", "error: ", "warning: "] {
+            if line.starts_with(prefix) {
+                prefix_len = prefix.len();
+                break;
+            }
+        }
+        let rest = &line[prefix_len..];
+        let digits = rest.bytes().take_while(|c| c.is_ascii_digit()).count();
+        if digits == 0 {
+            return line.to_string();
+        }
+        let after = &rest[digits..];
+        let is_location = (after.starts_with('(') || after.starts_with(':'))
+            && after[1..].bytes().next().map_or(false, |c| c.is_ascii_digit());
+        match rest[..digits].parse::<usize>().ok().and_then(|i| self.files.get(i)) {
+            Some(file) if is_location => format!("{}{}{}", &line[..prefix_len], file, after),
+            _ => line.to_string()
+        }
+    }
+}
+
+///
+/// Resolves `#include "file"` directives in `source`, reading includes from
+/// `file_system`. Include paths are relative to the including file. `name` is
+/// the path of `source` itself, used for relative includes and in errors.
+///
+/// Directives inside `/* */` comments are left alone. Conditionals aren't
+/// evaluated, since defines may still be added before compiling, so includes
+/// in every branch are read. One that can't be read inside an `#if` block is
+/// replaced by an `#error`, which fails the compile only if the branch is taken.
+///
+pub fn preprocess(file_system : &dyn ShaderFileSystem, name : &str, source : &str) -> Result<PreprocessedSource> {
+    let mut out = PreprocessedSource {
+        source: String::with_capacity(source.len()),
+        files: vec![name.to_string()]
+    };
+    let line_offset = if version_uses_next_line_numbering(source) { 0 } else { 1 };
+    let mut stack = vec![name.to_string()];
+    expand(file_system, source, 0, line_offset, false, &mut stack, &mut out)?;
+    Ok(out)
+}
+
+///
+/// Inserts a `#define` for each `(name, value)` after the `#version` line, or
+/// at the top if there is none. A `#line` directive follows the defines, so
+/// line numbers in compile errors still match the original source.
+///
+pub fn inject_defines(source : &str, defines : &[(String, String)]) -> String {
+    if defines.is_empty() {
+        return source.to_string();
+    }
+    let line_offset = if version_uses_next_line_numbering(source) { 0 } else { 1 };
+    let version_line = source.lines().position(|line| line.trim_start().starts_with("#version"));
+    let insert_at = version_line.map_or(0, |i| i + 1);
+
+    let mut out = String::with_capacity(source.len() + defines.len() * 32);
+    for (i, line) in source.lines().enumerate() {
+        if i == insert_at {
+            push_defines(&mut out, defines, insert_at + 1 - line_offset);
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    if insert_at >= source.lines().count() {
+        push_defines(&mut out, defines, insert_at + 1 - line_offset);
+    }
+    out
+}
+
+fn push_defines(out : &mut String, defines : &[(String, String)], next_line : usize) {
+    for &(ref name, ref value) in defines {
+        if value.is_empty() {
+            out.push_str(&format!("#define {}\n", name));
+        } else {
+            out.push_str(&format!("#define {} {}\n", name, value));
+        }
+    }
+    out.push_str(&format!("#line {} 0\n", next_line));
+}
+
+fn expand(file_system : &dyn ShaderFileSystem,
+          source : &str,
+          file_index : usize,
+          line_offset : usize,
+          conditional : bool,
+          stack : &mut Vec<String>,
+          out : &mut PreprocessedSource) -> Result<()> {
+    let current = stack[stack.len() - 1].clone();
+    let mut in_comment = false;
+    let mut depth = 0;
+    for (line_no, line) in source.lines().enumerate() {
+        let is_directive = !in_comment;
+        in_comment = ends_in_comment(line, in_comment);
+        let parsed = if is_directive { parse_include(line) } else { None };
+        let include = match parsed {
+            Some(Ok(include)) => include,
+            Some(Err(())) => {
+                return Err(GlError::new(format!("{}:{}: malformed #include: {}", current, line_no + 1, line.trim())));
+            }
+            None => {
+                if is_directive {
+                    match directive_name(line) {
+                        Some("if") | Some("ifdef") | Some("ifndef") => depth += 1,
+                        Some("endif") if depth > 0 => depth -= 1,
+                        Some("version") if file_index != 0 => {
+                            return Err(GlError::new(format!("{}:{}: #version in included file", current, line_no + 1)));
+                        }
+                        _ => ()
+                    }
+                }
+                out.source.push_str(line);
+                out.source.push('\n');
+                continue;
+            }
+        };
+
+        let path = resolve_path(&current, include);
+        if let Some(pos) = stack.iter().position(|p| *p == path) {
+            let mut cycle = stack[pos..].to_vec();
+            cycle.push(path);
+            return Err(GlError::new(format!("{}:{}: include cycle: {}", current, line_no + 1, cycle.join(" -> "))));
+        }
+        let in_conditional = conditional || depth > 0;
+        let included = match file_system.read_file(&path) {
+            Ok(included) => included,
+            Err(_) if in_conditional => {
+                out.source.push_str(&format!("#error can't read include {}\n", path));
+                continue;
+            }
+            Err(err) => return Err(GlError::new(format!("{}:{}: {}", current, line_no + 1, err)))
+        };
+
+        let included_index = out.files.len();
+        out.files.push(path.clone());
+        out.source.push_str(&format!("#line {} {}\n", 1 - line_offset, included_index));
+        stack.push(path);
+        expand(file_system, &included, included_index, line_offset, in_conditional, stack, out)?;
+        stack.pop();
+        out.source.push_str(&format!("#line {} {}\n", line_no + 2 - line_offset, file_index));
+    }
+    Ok(())
+}
+
+///
+/// Name of the preprocessor directive on a line, e.g. `ifdef`.
+///
+fn directive_name(line : &str) -> Option<&str> {
+    let line = line.trim_start();
+    if !line.starts_with('#') {
+        return None;
+    }
+    let directive = line[1..].trim_start();
+    let end = directive.find(|c : char| !c.is_ascii_alphanumeric() && c != '_').unwrap_or(directive.len());
+    Some(&directive[..end])
+}
+
+///
+/// Whether a `/* */` comment is still open at the end of `line`, given
+/// whether one was open at its start.
+///
+pub fn ends_in_comment(line : &str, mut in_comment : bool) -> bool {
+    let bytes = line.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        if in_comment {
+            if bytes[i..].starts_with(b"*/") {
+                in_comment = false;
+                i += 1;
+            }
+        } else if bytes[i..].starts_with(b"//") {
+            break;
+        } else if bytes[i..].starts_with(b"/*") {
+            in_comment = true;
+            i += 1;
+        }
+        i += 1;
+    }
+    in_comment
+}
+
+///
+/// Returns the quoted path of an `#include` line, `Some(Err)` if the line is
+/// an `#include` without a valid path and `None` for any other line.
+///
+fn parse_include(line : &str) -> Option<::std::result::Result<&str, ()>> {
+    let line = line.trim();
+    if !line.starts_with('#') {
+        return None;
+    }
+    let directive = line[1..].trim_start();
+    if !directive.starts_with("include") {
+        return None;
+    }
+    let arg = directive["include".len()..].trim();
+    let close = match arg.chars().next() {
+        Some('"') => '"',
+        Some('<') => '>',
+        _ => return Some(Err(()))
+    };
+    match arg[1..].find(close) {
+        Some(end) if end > 0 && arg[end + 2..].trim().is_empty() => Some(Ok(&arg[1..end + 1])),
+        _ => Some(Err(()))
+    }
+}
+
+fn resolve_path(including : &str, include : &str) -> String {
+    let mut parts : Vec<&str> = if include.starts_with('/') {
+        Vec::new()
+    } else {
+        let mut dir : Vec<_> = including.split('/').collect();
+        dir.pop();
+        dir
+    };
+    for part in include.split('/') {
+        match part {
+            "" | "." => (),
+            ".." => { parts.pop(); },
+            part => parts.push(part)
+        }
+    }
+    parts.join("/")
+}
+
+///
+/// Before GLSL 3.30 `#line n` numbered the line after the directive n + 1;
+/// from 3.30 (and ES 3.00) on it is numbered n.
+///
+fn version_uses_next_line_numbering(source : &str) -> bool {
+    for line in source.lines() {
+        let line = line.trim();
+        if line.starts_with("#version") {
+            let mut words = line["#version".len()..].split_whitespace();
+            let version = words.next().and_then(|v| v.parse::<u32>().ok()).unwrap_or(110);
+            let es = words.next() == Some("es");
+            return version >= 330 || (es && version >= 300);
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file_system() -> MemoryFileSystem {
+        let mut fs = MemoryFileSystem::new();
+        fs.insert("lib/common.glsl", "float common() { return 1.0; }\n#include \"util.glsl\"\n");
+        fs.insert("lib/util.glsl", "float util() { return 2.0; }\n");
+        fs
+    }
+
+    #[test]
+    fn line_directives_map_included_lines() {
+        let source = "#version 330\n#include \"lib/common.glsl\"\nvoid main() {}\n";
+        let out = preprocess(&file_system(), "main.frag", source).unwrap();
+        assert_eq!(out.files, vec!["main.frag", "lib/common.glsl", "lib/util.glsl"]);
+        assert_eq!(out.source, "#version 330\n\
+                                #line 1 1\n\
+                                float common() { return 1.0; }\n\
+                                #line 1 2\n\
+                                float util() { return 2.0; }\n\
+                                #line 3 1\n\
+                                #line 3 0\n\
+                                void main() {}\n");
+    }
+
+    #[test]
+    fn line_directives_before_glsl_330_number_the_next_line() {
+        let source = "#version 150\n#include \"lib/util.glsl\"\nvoid main() {}\n";
+        let out = preprocess(&file_system(), "main.frag", source).unwrap();
+        assert_eq!(out.source, "#version 150\n#line 0 1\nfloat util() { return 2.0; }\n#line 2 0\nvoid main() {}\n");
+    }
+
+    #[test]
+    fn includes_in_comments_are_ignored() {
+        let source = "/* example:\n#include \"missing.glsl\"\n*/\n// #include \"missing.glsl\"\n";
+        let out = preprocess(&file_system(), "main.frag", source).unwrap();
+        assert_eq!(out.source, source);
+    }
+
+    #[test]
+    fn missing_includes_in_conditionals_become_errors() {
+        let source = "#ifdef USE_SHADOWS\n#include \"shadows.glsl\"\n#endif\n";
+        let out = preprocess(&file_system(), "main.frag", source).unwrap();
+        assert_eq!(out.source, "#ifdef USE_SHADOWS\n#error can't read include shadows.glsl\n#endif\n");
+
+        assert!(preprocess(&file_system(), "main.frag", "#include \"shadows.glsl\"\n").is_err());
+    }
+
+    #[test]
+    fn include_cycles_are_errors() {
+        let mut fs = MemoryFileSystem::new();
+        fs.insert("a.glsl", "#include \"b.glsl\"\n");
+        fs.insert("b.glsl", "#include \"a.glsl\"\n");
+        let err = preprocess(&fs, "main.frag", "#include \"a.glsl\"\n").unwrap_err();
+        assert!(err.to_string().contains("a.glsl -> b.glsl -> a.glsl"), "{}", err);
+    }
+
+    #[test]
+    fn annotate_log_names_files() {
+        let out = preprocess(&file_system(), "main.frag", "#include \"lib/common.glsl\"\n").unwrap();
+        let log = "0(12) : error C0000: syntax error\n\
+                   ERROR: 1:3: 'x' : undeclared identifier\n\
+                   WARNING: 2:1: unused\n\
+                   ERROR: 7:1: out of range\n\
+                   ERROR: 2 compilation errors.";
+        assert_eq!(out.annotate_log(log), "main.frag(12) : error C0000: syntax error\n\
+                                           ERROR: lib/common.glsl:3: 'x' : undeclared identifier\n\
+                                           WARNING: lib/util.glsl:1: unused\n\
+                                           ERROR: 7:1: out of range\n\
+                                           ERROR: 2 compilation errors.");
+    }
+
+    #[test]
+    fn defines_follow_version() {
+        let defines = vec![("A".to_string(), "1".to_string()), ("B".to_string(), String::new())];
+        assert_eq!(inject_defines("#version 330\nvoid main() {}\n", &defines),
+                   "#version 330\n#define A 1\n#define B\n#line 2 0\nvoid main() {}\n");
+    }
+}
@@ -0,0 +1,705 @@
+extern crate gl;
+use gl::types::*;
+
+use std::fmt;
+
+///
+/// Type of a GLSL variable, as reported by program reflection.
+/// Types this crate doesn't know are kept as `Unknown` with their GLenum.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GlslType {
+    Float,
+    Vec2,
+    Vec3,
+    Vec4,
+    Double,
+    DVec2,
+    DVec3,
+    DVec4,
+    Int,
+    IVec2,
+    IVec3,
+    IVec4,
+    UInt,
+    UVec2,
+    UVec3,
+    UVec4,
+    Bool,
+    BVec2,
+    BVec3,
+    BVec4,
+    Mat2,
+    Mat3,
+    Mat4,
+    Mat2x3,
+    Mat2x4,
+    Mat3x2,
+    Mat3x4,
+    Mat4x2,
+    Mat4x3,
+    DMat2,
+    DMat3,
+    DMat4,
+    DMat2x3,
+    DMat2x4,
+    DMat3x2,
+    DMat3x4,
+    DMat4x2,
+    DMat4x3,
+    Sampler1D,
+    Sampler2D,
+    Sampler3D,
+    SamplerCube,
+    Sampler1DArray,
+    Sampler2DArray,
+    SamplerCubeArray,
+    Sampler2DMS,
+    Sampler2DMSArray,
+    Sampler2DRect,
+    SamplerBuffer,
+    Sampler1DShadow,
+    Sampler2DShadow,
+    SamplerCubeShadow,
+    Sampler1DArrayShadow,
+    Sampler2DArrayShadow,
+    SamplerCubeArrayShadow,
+    Sampler2DRectShadow,
+    ISampler1D,
+    ISampler2D,
+    ISampler3D,
+    ISamplerCube,
+    ISampler1DArray,
+    ISampler2DArray,
+    ISamplerCubeArray,
+    ISampler2DMS,
+    ISampler2DMSArray,
+    ISampler2DRect,
+    ISamplerBuffer,
+    USampler1D,
+    USampler2D,
+    USampler3D,
+    USamplerCube,
+    USampler1DArray,
+    USampler2DArray,
+    USamplerCubeArray,
+    USampler2DMS,
+    USampler2DMSArray,
+    USampler2DRect,
+    USamplerBuffer,
+    Image1D,
+    Image2D,
+    Image3D,
+    ImageCube,
+    Image1DArray,
+    Image2DArray,
+    ImageCubeArray,
+    Image2DMS,
+    Image2DMSArray,
+    Image2DRect,
+    ImageBuffer,
+    IImage1D,
+    IImage2D,
+    IImage3D,
+    IImageCube,
+    IImage1DArray,
+    IImage2DArray,
+    IImageCubeArray,
+    IImage2DMS,
+    IImage2DMSArray,
+    IImage2DRect,
+    IImageBuffer,
+    UImage1D,
+    UImage2D,
+    UImage3D,
+    UImageCube,
+    UImage1DArray,
+    UImage2DArray,
+    UImageCubeArray,
+    UImage2DMS,
+    UImage2DMSArray,
+    UImage2DRect,
+    UImageBuffer,
+    AtomicUint,
+    Unknown(GLenum)
+}
+
+impl GlslType {
+    pub fn from_gl(gl_type : GLenum) -> GlslType {
+        match gl_type {
+            gl::FLOAT => GlslType::Float,
+            gl::FLOAT_VEC2 => GlslType::Vec2,
+            gl::FLOAT_VEC3 => GlslType::Vec3,
+            gl::FLOAT_VEC4 => GlslType::Vec4,
+            gl::DOUBLE => GlslType::Double,
+            gl::DOUBLE_VEC2 => GlslType::DVec2,
+            gl::DOUBLE_VEC3 => GlslType::DVec3,
+            gl::DOUBLE_VEC4 => GlslType::DVec4,
+            gl::INT => GlslType::Int,
+            gl::INT_VEC2 => GlslType::IVec2,
+            gl::INT_VEC3 => GlslType::IVec3,
+            gl::INT_VEC4 => GlslType::IVec4,
+            gl::UNSIGNED_INT => GlslType::UInt,
+            gl::UNSIGNED_INT_VEC2 => GlslType::UVec2,
+            gl::UNSIGNED_INT_VEC3 => GlslType::UVec3,
+            gl::UNSIGNED_INT_VEC4 => GlslType::UVec4,
+            gl::BOOL => GlslType::Bool,
+            gl::BOOL_VEC2 => GlslType::BVec2,
+            gl::BOOL_VEC3 => GlslType::BVec3,
+            gl::BOOL_VEC4 => GlslType::BVec4,
+            gl::FLOAT_MAT2 => GlslType::Mat2,
+            gl::FLOAT_MAT3 => GlslType::Mat3,
+            gl::FLOAT_MAT4 => GlslType::Mat4,
+            gl::FLOAT_MAT2x3 => GlslType::Mat2x3,
+            gl::FLOAT_MAT2x4 => GlslType::Mat2x4,
+            gl::FLOAT_MAT3x2 => GlslType::Mat3x2,
+            gl::FLOAT_MAT3x4 => GlslType::Mat3x4,
+            gl::FLOAT_MAT4x2 => GlslType::Mat4x2,
+            gl::FLOAT_MAT4x3 => GlslType::Mat4x3,
+            gl::DOUBLE_MAT2 => GlslType::DMat2,
+            gl::DOUBLE_MAT3 => GlslType::DMat3,
+            gl::DOUBLE_MAT4 => GlslType::DMat4,
+            gl::DOUBLE_MAT2x3 => GlslType::DMat2x3,
+            gl::DOUBLE_MAT2x4 => GlslType::DMat2x4,
+            gl::DOUBLE_MAT3x2 => GlslType::DMat3x2,
+            gl::DOUBLE_MAT3x4 => GlslType::DMat3x4,
+            gl::DOUBLE_MAT4x2 => GlslType::DMat4x2,
+            gl::DOUBLE_MAT4x3 => GlslType::DMat4x3,
+            gl::SAMPLER_1D => GlslType::Sampler1D,
+            gl::SAMPLER_2D => GlslType::Sampler2D,
+            gl::SAMPLER_3D => GlslType::Sampler3D,
+            gl::SAMPLER_CUBE => GlslType::SamplerCube,
+            gl::SAMPLER_1D_ARRAY => GlslType::Sampler1DArray,
+            gl::SAMPLER_2D_ARRAY => GlslType::Sampler2DArray,
+            gl::SAMPLER_CUBE_MAP_ARRAY => GlslType::SamplerCubeArray,
+            gl::SAMPLER_2D_MULTISAMPLE => GlslType::Sampler2DMS,
+            gl::SAMPLER_2D_MULTISAMPLE_ARRAY => GlslType::Sampler2DMSArray,
+            gl::SAMPLER_2D_RECT => GlslType::Sampler2DRect,
+            gl::SAMPLER_BUFFER => GlslType::SamplerBuffer,
+            gl::SAMPLER_1D_SHADOW => GlslType::Sampler1DShadow,
+            gl::SAMPLER_2D_SHADOW => GlslType::Sampler2DShadow,
+            gl::SAMPLER_CUBE_SHADOW => GlslType::SamplerCubeShadow,
+            gl::SAMPLER_1D_ARRAY_SHADOW => GlslType::Sampler1DArrayShadow,
+            gl::SAMPLER_2D_ARRAY_SHADOW => GlslType::Sampler2DArrayShadow,
+            gl::SAMPLER_CUBE_MAP_ARRAY_SHADOW => GlslType::SamplerCubeArrayShadow,
+            gl::SAMPLER_2D_RECT_SHADOW => GlslType::Sampler2DRectShadow,
+            gl::INT_SAMPLER_1D => GlslType::ISampler1D,
+            gl::INT_SAMPLER_2D => GlslType::ISampler2D,
+            gl::INT_SAMPLER_3D => GlslType::ISampler3D,
+            gl::INT_SAMPLER_CUBE => GlslType::ISamplerCube,
+            gl::INT_SAMPLER_1D_ARRAY => GlslType::ISampler1DArray,
+            gl::INT_SAMPLER_2D_ARRAY => GlslType::ISampler2DArray,
+            gl::INT_SAMPLER_CUBE_MAP_ARRAY => GlslType::ISamplerCubeArray,
+            gl::INT_SAMPLER_2D_MULTISAMPLE => GlslType::ISampler2DMS,
+            gl::INT_SAMPLER_2D_MULTISAMPLE_ARRAY => GlslType::ISampler2DMSArray,
+            gl::INT_SAMPLER_2D_RECT => GlslType::ISampler2DRect,
+            gl::INT_SAMPLER_BUFFER => GlslType::ISamplerBuffer,
+            gl::UNSIGNED_INT_SAMPLER_1D => GlslType::USampler1D,
+            gl::UNSIGNED_INT_SAMPLER_2D => GlslType::USampler2D,
+            gl::UNSIGNED_INT_SAMPLER_3D => GlslType::USampler3D,
+            gl::UNSIGNED_INT_SAMPLER_CUBE => GlslType::USamplerCube,
+            gl::UNSIGNED_INT_SAMPLER_1D_ARRAY => GlslType::USampler1DArray,
+            gl::UNSIGNED_INT_SAMPLER_2D_ARRAY => GlslType::USampler2DArray,
+            gl::UNSIGNED_INT_SAMPLER_CUBE_MAP_ARRAY => GlslType::USamplerCubeArray,
+            gl::UNSIGNED_INT_SAMPLER_2D_MULTISAMPLE => GlslType::USampler2DMS,
+            gl::UNSIGNED_INT_SAMPLER_2D_MULTISAMPLE_ARRAY => GlslType::USampler2DMSArray,
+            gl::UNSIGNED_INT_SAMPLER_2D_RECT => GlslType::USampler2DRect,
+            gl::UNSIGNED_INT_SAMPLER_BUFFER => GlslType::USamplerBuffer,
+            gl::IMAGE_1D => GlslType::Image1D,
+            gl::IMAGE_2D => GlslType::Image2D,
+            gl::IMAGE_3D => GlslType::Image3D,
+            gl::IMAGE_CUBE => GlslType::ImageCube,
+            gl::IMAGE_1D_ARRAY => GlslType::Image1DArray,
+            gl::IMAGE_2D_ARRAY => GlslType::Image2DArray,
+            gl::IMAGE_CUBE_MAP_ARRAY => GlslType::ImageCubeArray,
+            gl::IMAGE_2D_MULTISAMPLE => GlslType::Image2DMS,
+            gl::IMAGE_2D_MULTISAMPLE_ARRAY => GlslType::Image2DMSArray,
+            gl::IMAGE_2D_RECT => GlslType::Image2DRect,
+            gl::IMAGE_BUFFER => GlslType::ImageBuffer,
+            gl::INT_IMAGE_1D => GlslType::IImage1D,
+            gl::INT_IMAGE_2D => GlslType::IImage2D,
+            gl::INT_IMAGE_3D => GlslType::IImage3D,
+            gl::INT_IMAGE_CUBE => GlslType::IImageCube,
+            gl::INT_IMAGE_1D_ARRAY => GlslType::IImage1DArray,
+            gl::INT_IMAGE_2D_ARRAY => GlslType::IImage2DArray,
+            gl::INT_IMAGE_CUBE_MAP_ARRAY => GlslType::IImageCubeArray,
+            gl::INT_IMAGE_2D_MULTISAMPLE => GlslType::IImage2DMS,
+            gl::INT_IMAGE_2D_MULTISAMPLE_ARRAY => GlslType::IImage2DMSArray,
+            gl::INT_IMAGE_2D_RECT => GlslType::IImage2DRect,
+            gl::INT_IMAGE_BUFFER => GlslType::IImageBuffer,
+            gl::UNSIGNED_INT_IMAGE_1D => GlslType::UImage1D,
+            gl::UNSIGNED_INT_IMAGE_2D => GlslType::UImage2D,
+            gl::UNSIGNED_INT_IMAGE_3D => GlslType::UImage3D,
+            gl::UNSIGNED_INT_IMAGE_CUBE => GlslType::UImageCube,
+            gl::UNSIGNED_INT_IMAGE_1D_ARRAY => GlslType::UImage1DArray,
+            gl::UNSIGNED_INT_IMAGE_2D_ARRAY => GlslType::UImage2DArray,
+            gl::UNSIGNED_INT_IMAGE_CUBE_MAP_ARRAY => GlslType::UImageCubeArray,
+            gl::UNSIGNED_INT_IMAGE_2D_MULTISAMPLE => GlslType::UImage2DMS,
+            gl::UNSIGNED_INT_IMAGE_2D_MULTISAMPLE_ARRAY => GlslType::UImage2DMSArray,
+            gl::UNSIGNED_INT_IMAGE_2D_RECT => GlslType::UImage2DRect,
+            gl::UNSIGNED_INT_IMAGE_BUFFER => GlslType::UImageBuffer,
+            gl::UNSIGNED_INT_ATOMIC_COUNTER => GlslType::AtomicUint,
+            other => GlslType::Unknown(other)
+        }
+    }
+
+    pub fn to_gl(&self) -> GLenum {
+        match *self {
+            GlslType::Float => gl::FLOAT,
+            GlslType::Vec2 => gl::FLOAT_VEC2,
+            GlslType::Vec3 => gl::FLOAT_VEC3,
+            GlslType::Vec4 => gl::FLOAT_VEC4,
+            GlslType::Double => gl::DOUBLE,
+            GlslType::DVec2 => gl::DOUBLE_VEC2,
+            GlslType::DVec3 => gl::DOUBLE_VEC3,
+            GlslType::DVec4 => gl::DOUBLE_VEC4,
+            GlslType::Int => gl::INT,
+            GlslType::IVec2 => gl::INT_VEC2,
+            GlslType::IVec3 => gl::INT_VEC3,
+            GlslType::IVec4 => gl::INT_VEC4,
+            GlslType::UInt => gl::UNSIGNED_INT,
+            GlslType::UVec2 => gl::UNSIGNED_INT_VEC2,
+            GlslType::UVec3 => gl::UNSIGNED_INT_VEC3,
+            GlslType::UVec4 => gl::UNSIGNED_INT_VEC4,
+            GlslType::Bool => gl::BOOL,
+            GlslType::BVec2 => gl::BOOL_VEC2,
+            GlslType::BVec3 => gl::BOOL_VEC3,
+            GlslType::BVec4 => gl::BOOL_VEC4,
+            GlslType::Mat2 => gl::FLOAT_MAT2,
+            GlslType::Mat3 => gl::FLOAT_MAT3,
+            GlslType::Mat4 => gl::FLOAT_MAT4,
+            GlslType::Mat2x3 => gl::FLOAT_MAT2x3,
+            GlslType::Mat2x4 => gl::FLOAT_MAT2x4,
+            GlslType::Mat3x2 => gl::FLOAT_MAT3x2,
+            GlslType::Mat3x4 => gl::FLOAT_MAT3x4,
+            GlslType::Mat4x2 => gl::FLOAT_MAT4x2,
+            GlslType::Mat4x3 => gl::FLOAT_MAT4x3,
+            GlslType::DMat2 => gl::DOUBLE_MAT2,
+            GlslType::DMat3 => gl::DOUBLE_MAT3,
+            GlslType::DMat4 => gl::DOUBLE_MAT4,
+            GlslType::DMat2x3 => gl::DOUBLE_MAT2x3,
+            GlslType::DMat2x4 => gl::DOUBLE_MAT2x4,
+            GlslType::DMat3x2 => gl::DOUBLE_MAT3x2,
+            GlslType::DMat3x4 => gl::DOUBLE_MAT3x4,
+            GlslType::DMat4x2 => gl::DOUBLE_MAT4x2,
+            GlslType::DMat4x3 => gl::DOUBLE_MAT4x3,
+            GlslType::Sampler1D => gl::SAMPLER_1D,
+            GlslType::Sampler2D => gl::SAMPLER_2D,
+            GlslType::Sampler3D => gl::SAMPLER_3D,
+            GlslType::SamplerCube => gl::SAMPLER_CUBE,
+            GlslType::Sampler1DArray => gl::SAMPLER_1D_ARRAY,
+            GlslType::Sampler2DArray => gl::SAMPLER_2D_ARRAY,
+            GlslType::SamplerCubeArray => gl::SAMPLER_CUBE_MAP_ARRAY,
+            GlslType::Sampler2DMS => gl::SAMPLER_2D_MULTISAMPLE,
+            GlslType::Sampler2DMSArray => gl::SAMPLER_2D_MULTISAMPLE_ARRAY,
+            GlslType::Sampler2DRect => gl::SAMPLER_2D_RECT,
+            GlslType::SamplerBuffer => gl::SAMPLER_BUFFER,
+            GlslType::Sampler1DShadow => gl::SAMPLER_1D_SHADOW,
+            GlslType::Sampler2DShadow => gl::SAMPLER_2D_SHADOW,
+            GlslType::SamplerCubeShadow => gl::SAMPLER_CUBE_SHADOW,
+            GlslType::Sampler1DArrayShadow => gl::SAMPLER_1D_ARRAY_SHADOW,
+            GlslType::Sampler2DArrayShadow => gl::SAMPLER_2D_ARRAY_SHADOW,
+            GlslType::SamplerCubeArrayShadow => gl::SAMPLER_CUBE_MAP_ARRAY_SHADOW,
+            GlslType::Sampler2DRectShadow => gl::SAMPLER_2D_RECT_SHADOW,
+            GlslType::ISampler1D => gl::INT_SAMPLER_1D,
+            GlslType::ISampler2D => gl::INT_SAMPLER_2D,
+            GlslType::ISampler3D => gl::INT_SAMPLER_3D,
+            GlslType::ISamplerCube => gl::INT_SAMPLER_CUBE,
+            GlslType::ISampler1DArray => gl::INT_SAMPLER_1D_ARRAY,
+            GlslType::ISampler2DArray => gl::INT_SAMPLER_2D_ARRAY,
+            GlslType::ISamplerCubeArray => gl::INT_SAMPLER_CUBE_MAP_ARRAY,
+            GlslType::ISampler2DMS => gl::INT_SAMPLER_2D_MULTISAMPLE,
+            GlslType::ISampler2DMSArray => gl::INT_SAMPLER_2D_MULTISAMPLE_ARRAY,
+            GlslType::ISampler2DRect => gl::INT_SAMPLER_2D_RECT,
+            GlslType::ISamplerBuffer => gl::INT_SAMPLER_BUFFER,
+            GlslType::USampler1D => gl::UNSIGNED_INT_SAMPLER_1D,
+            GlslType::USampler2D => gl::UNSIGNED_INT_SAMPLER_2D,
+            GlslType::USampler3D => gl::UNSIGNED_INT_SAMPLER_3D,
+            GlslType::USamplerCube => gl::UNSIGNED_INT_SAMPLER_CUBE,
+            GlslType::USampler1DArray => gl::UNSIGNED_INT_SAMPLER_1D_ARRAY,
+            GlslType::USampler2DArray => gl::UNSIGNED_INT_SAMPLER_2D_ARRAY,
+            GlslType::USamplerCubeArray => gl::UNSIGNED_INT_SAMPLER_CUBE_MAP_ARRAY,
+            GlslType::USampler2DMS => gl::UNSIGNED_INT_SAMPLER_2D_MULTISAMPLE,
+            GlslType::USampler2DMSArray => gl::UNSIGNED_INT_SAMPLER_2D_MULTISAMPLE_ARRAY,
+            GlslType::USampler2DRect => gl::UNSIGNED_INT_SAMPLER_2D_RECT,
+            GlslType::USamplerBuffer => gl::UNSIGNED_INT_SAMPLER_BUFFER,
+            GlslType::Image1D => gl::IMAGE_1D,
+            GlslType::Image2D => gl::IMAGE_2D,
+            GlslType::Image3D => gl::IMAGE_3D,
+            GlslType::ImageCube => gl::IMAGE_CUBE,
+            GlslType::Image1DArray => gl::IMAGE_1D_ARRAY,
+            GlslType::Image2DArray => gl::IMAGE_2D_ARRAY,
+            GlslType::ImageCubeArray => gl::IMAGE_CUBE_MAP_ARRAY,
+            GlslType::Image2DMS => gl::IMAGE_2D_MULTISAMPLE,
+            GlslType::Image2DMSArray => gl::IMAGE_2D_MULTISAMPLE_ARRAY,
+            GlslType::Image2DRect => gl::IMAGE_2D_RECT,
+            GlslType::ImageBuffer => gl::IMAGE_BUFFER,
+            GlslType::IImage1D => gl::INT_IMAGE_1D,
+            GlslType::IImage2D => gl::INT_IMAGE_2D,
+            GlslType::IImage3D => gl::INT_IMAGE_3D,
+            GlslType::IImageCube => gl::INT_IMAGE_CUBE,
+            GlslType::IImage1DArray => gl::INT_IMAGE_1D_ARRAY,
+            GlslType::IImage2DArray => gl::INT_IMAGE_2D_ARRAY,
+            GlslType::IImageCubeArray => gl::INT_IMAGE_CUBE_MAP_ARRAY,
+            GlslType::IImage2DMS => gl::INT_IMAGE_2D_MULTISAMPLE,
+            GlslType::IImage2DMSArray => gl::INT_IMAGE_2D_MULTISAMPLE_ARRAY,
+            GlslType::IImage2DRect => gl::INT_IMAGE_2D_RECT,
+            GlslType::IImageBuffer => gl::INT_IMAGE_BUFFER,
+            GlslType::UImage1D => gl::UNSIGNED_INT_IMAGE_1D,
+            GlslType::UImage2D => gl::UNSIGNED_INT_IMAGE_2D,
+            GlslType::UImage3D => gl::UNSIGNED_INT_IMAGE_3D,
+            GlslType::UImageCube => gl::UNSIGNED_INT_IMAGE_CUBE,
+            GlslType::UImage1DArray => gl::UNSIGNED_INT_IMAGE_1D_ARRAY,
+            GlslType::UImage2DArray => gl::UNSIGNED_INT_IMAGE_2D_ARRAY,
+            GlslType::UImageCubeArray => gl::UNSIGNED_INT_IMAGE_CUBE_MAP_ARRAY,
+            GlslType::UImage2DMS => gl::UNSIGNED_INT_IMAGE_2D_MULTISAMPLE,
+            GlslType::UImage2DMSArray => gl::UNSIGNED_INT_IMAGE_2D_MULTISAMPLE_ARRAY,
+            GlslType::UImage2DRect => gl::UNSIGNED_INT_IMAGE_2D_RECT,
+            GlslType::UImageBuffer => gl::UNSIGNED_INT_IMAGE_BUFFER,
+            GlslType::AtomicUint => gl::UNSIGNED_INT_ATOMIC_COUNTER,
+            GlslType::Unknown(gl_type) => gl_type
+        }
+    }
+
+    ///
+    /// The type as written in GLSL, e.g. `mat4` or `usampler2DArray`.
+    ///
+    pub fn name(&self) -> &'static str {
+        match *self {
+            GlslType::Float => "float",
+            GlslType::Vec2 => "vec2",
+            GlslType::Vec3 => "vec3",
+            GlslType::Vec4 => "vec4",
+            GlslType::Double => "double",
+            GlslType::DVec2 => "dvec2",
+            GlslType::DVec3 => "dvec3",
+            GlslType::DVec4 => "dvec4",
+            GlslType::Int => "int",
+            GlslType::IVec2 => "ivec2",
+            GlslType::IVec3 => "ivec3",
+            GlslType::IVec4 => "ivec4",
+            GlslType::UInt => "uint",
+            GlslType::UVec2 => "uvec2",
+            GlslType::UVec3 => "uvec3",
+            GlslType::UVec4 => "uvec4",
+            GlslType::Bool => "bool",
+            GlslType::BVec2 => "bvec2",
+            GlslType::BVec3 => "bvec3",
+            GlslType::BVec4 => "bvec4",
+            GlslType::Mat2 => "mat2",
+            GlslType::Mat3 => "mat3",
+            GlslType::Mat4 => "mat4",
+            GlslType::Mat2x3 => "mat2x3",
+            GlslType::Mat2x4 => "mat2x4",
+            GlslType::Mat3x2 => "mat3x2",
+            GlslType::Mat3x4 => "mat3x4",
+            GlslType::Mat4x2 => "mat4x2",
+            GlslType::Mat4x3 => "mat4x3",
+            GlslType::DMat2 => "dmat2",
+            GlslType::DMat3 => "dmat3",
+            GlslType::DMat4 => "dmat4",
+            GlslType::DMat2x3 => "dmat2x3",
+            GlslType::DMat2x4 => "dmat2x4",
+            GlslType::DMat3x2 => "dmat3x2",
+            GlslType::DMat3x4 => "dmat3x4",
+            GlslType::DMat4x2 => "dmat4x2",
+            GlslType::DMat4x3 => "dmat4x3",
+            GlslType::Sampler1D => "sampler1D",
+            GlslType::Sampler2D => "sampler2D",
+            GlslType::Sampler3D => "sampler3D",
+            GlslType::SamplerCube => "samplerCube",
+            GlslType::Sampler1DArray => "sampler1DArray",
+            GlslType::Sampler2DArray => "sampler2DArray",
+            GlslType::SamplerCubeArray => "samplerCubeArray",
+            GlslType::Sampler2DMS => "sampler2DMS",
+            GlslType::Sampler2DMSArray => "sampler2DMSArray",
+            GlslType::Sampler2DRect => "sampler2DRect",
+            GlslType::SamplerBuffer => "samplerBuffer",
+            GlslType::Sampler1DShadow => "sampler1DShadow",
+            GlslType::Sampler2DShadow => "sampler2DShadow",
+            GlslType::SamplerCubeShadow => "samplerCubeShadow",
+            GlslType::Sampler1DArrayShadow => "sampler1DArrayShadow",
+            GlslType::Sampler2DArrayShadow => "sampler2DArrayShadow",
+            GlslType::SamplerCubeArrayShadow => "samplerCubeArrayShadow",
+            GlslType::Sampler2DRectShadow => "sampler2DRectShadow",
+            GlslType::ISampler1D => "isampler1D",
+            GlslType::ISampler2D => "isampler2D",
+            GlslType::ISampler3D => "isampler3D",
+            GlslType::ISamplerCube => "isamplerCube",
+            GlslType::ISampler1DArray => "isampler1DArray",
+            GlslType::ISampler2DArray => "isampler2DArray",
+            GlslType::ISamplerCubeArray => "isamplerCubeArray",
+            GlslType::ISampler2DMS => "isampler2DMS",
+            GlslType::ISampler2DMSArray => "isampler2DMSArray",
+            GlslType::ISampler2DRect => "isampler2DRect",
+            GlslType::ISamplerBuffer => "isamplerBuffer",
+            GlslType::USampler1D => "usampler1D",
+            GlslType::USampler2D => "usampler2D",
+            GlslType::USampler3D => "usampler3D",
+            GlslType::USamplerCube => "usamplerCube",
+            GlslType::USampler1DArray => "usampler1DArray",
+            GlslType::USampler2DArray => "usampler2DArray",
+            GlslType::USamplerCubeArray => "usamplerCubeArray",
+            GlslType::USampler2DMS => "usampler2DMS",
+            GlslType::USampler2DMSArray => "usampler2DMSArray",
+            GlslType::USampler2DRect => "usampler2DRect",
+            GlslType::USamplerBuffer => "usamplerBuffer",
+            GlslType::Image1D => "image1D",
+            GlslType::Image2D => "image2D",
+            GlslType::Image3D => "image3D",
+            GlslType::ImageCube => "imageCube",
+            GlslType::Image1DArray => "image1DArray",
+            GlslType::Image2DArray => "image2DArray",
+            GlslType::ImageCubeArray => "imageCubeArray",
+            GlslType::Image2DMS => "image2DMS",
+            GlslType::Image2DMSArray => "image2DMSArray",
+            GlslType::Image2DRect => "image2DRect",
+            GlslType::ImageBuffer => "imageBuffer",
+            GlslType::IImage1D => "iimage1D",
+            GlslType::IImage2D => "iimage2D",
+            GlslType::IImage3D => "iimage3D",
+            GlslType::IImageCube => "iimageCube",
+            GlslType::IImage1DArray => "iimage1DArray",
+            GlslType::IImage2DArray => "iimage2DArray",
+            GlslType::IImageCubeArray => "iimageCubeArray",
+            GlslType::IImage2DMS => "iimage2DMS",
+            GlslType::IImage2DMSArray => "iimage2DMSArray",
+            GlslType::IImage2DRect => "iimage2DRect",
+            GlslType::IImageBuffer => "iimageBuffer",
+            GlslType::UImage1D => "uimage1D",
+            GlslType::UImage2D => "uimage2D",
+            GlslType::UImage3D => "uimage3D",
+            GlslType::UImageCube => "uimageCube",
+            GlslType::UImage1DArray => "uimage1DArray",
+            GlslType::UImage2DArray => "uimage2DArray",
+            GlslType::UImageCubeArray => "uimageCubeArray",
+            GlslType::UImage2DMS => "uimage2DMS",
+            GlslType::UImage2DMSArray => "uimage2DMSArray",
+            GlslType::UImage2DRect => "uimage2DRect",
+            GlslType::UImageBuffer => "uimageBuffer",
+            GlslType::AtomicUint => "atomic_uint",
+            GlslType::Unknown(_) => "<unknown>"
+        }
+    }
+
+    ///
+    /// Parses a type as written in GLSL, e.g. `mat4` or `usampler2DArray`.
+    ///
+    pub fn from_name(name : &str) -> Option<GlslType> {
+        match name {
+            "float" => Some(GlslType::Float),
+            "vec2" => Some(GlslType::Vec2),
+            "vec3" => Some(GlslType::Vec3),
+            "vec4" => Some(GlslType::Vec4),
+            "double" => Some(GlslType::Double),
+            "dvec2" => Some(GlslType::DVec2),
+            "dvec3" => Some(GlslType::DVec3),
+            "dvec4" => Some(GlslType::DVec4),
+            "int" => Some(GlslType::Int),
+            "ivec2" => Some(GlslType::IVec2),
+            "ivec3" => Some(GlslType::IVec3),
+            "ivec4" => Some(GlslType::IVec4),
+            "uint" => Some(GlslType::UInt),
+            "uvec2" => Some(GlslType::UVec2),
+            "uvec3" => Some(GlslType::UVec3),
+            "uvec4" => Some(GlslType::UVec4),
+            "bool" => Some(GlslType::Bool),
+            "bvec2" => Some(GlslType::BVec2),
+            "bvec3" => Some(GlslType::BVec3),
+            "bvec4" => Some(GlslType::BVec4),
+            "mat2" => Some(GlslType::Mat2),
+            "mat3" => Some(GlslType::Mat3),
+            "mat4" => Some(GlslType::Mat4),
+            "mat2x3" => Some(GlslType::Mat2x3),
+            "mat2x4" => Some(GlslType::Mat2x4),
+            "mat3x2" => Some(GlslType::Mat3x2),
+            "mat3x4" => Some(GlslType::Mat3x4),
+            "mat4x2" => Some(GlslType::Mat4x2),
+            "mat4x3" => Some(GlslType::Mat4x3),
+            "dmat2" => Some(GlslType::DMat2),
+            "dmat3" => Some(GlslType::DMat3),
+            "dmat4" => Some(GlslType::DMat4),
+            "dmat2x3" => Some(GlslType::DMat2x3),
+            "dmat2x4" => Some(GlslType::DMat2x4),
+            "dmat3x2" => Some(GlslType::DMat3x2),
+            "dmat3x4" => Some(GlslType::DMat3x4),
+            "dmat4x2" => Some(GlslType::DMat4x2),
+            "dmat4x3" => Some(GlslType::DMat4x3),
+            "sampler1D" => Some(GlslType::Sampler1D),
+            "sampler2D" => Some(GlslType::Sampler2D),
+            "sampler3D" => Some(GlslType::Sampler3D),
+            "samplerCube" => Some(GlslType::SamplerCube),
+            "sampler1DArray" => Some(GlslType::Sampler1DArray),
+            "sampler2DArray" => Some(GlslType::Sampler2DArray),
+            "samplerCubeArray" => Some(GlslType::SamplerCubeArray),
+            "sampler2DMS" => Some(GlslType::Sampler2DMS),
+            "sampler2DMSArray" => Some(GlslType::Sampler2DMSArray),
+            "sampler2DRect" => Some(GlslType::Sampler2DRect),
+            "samplerBuffer" => Some(GlslType::SamplerBuffer),
+            "sampler1DShadow" => Some(GlslType::Sampler1DShadow),
+            "sampler2DShadow" => Some(GlslType::Sampler2DShadow),
+            "samplerCubeShadow" => Some(GlslType::SamplerCubeShadow),
+            "sampler1DArrayShadow" => Some(GlslType::Sampler1DArrayShadow),
+            "sampler2DArrayShadow" => Some(GlslType::Sampler2DArrayShadow),
+            "samplerCubeArrayShadow" => Some(GlslType::SamplerCubeArrayShadow),
+            "sampler2DRectShadow" => Some(GlslType::Sampler2DRectShadow),
+            "isampler1D" => Some(GlslType::ISampler1D),
+            "isampler2D" => Some(GlslType::ISampler2D),
+            "isampler3D" => Some(GlslType::ISampler3D),
+            "isamplerCube" => Some(GlslType::ISamplerCube),
+            "isampler1DArray" => Some(GlslType::ISampler1DArray),
+            "isampler2DArray" => Some(GlslType::ISampler2DArray),
+            "isamplerCubeArray" => Some(GlslType::ISamplerCubeArray),
+            "isampler2DMS" => Some(GlslType::ISampler2DMS),
+            "isampler2DMSArray" => Some(GlslType::ISampler2DMSArray),
+            "isampler2DRect" => Some(GlslType::ISampler2DRect),
+            "isamplerBuffer" => Some(GlslType::ISamplerBuffer),
+            "usampler1D" => Some(GlslType::USampler1D),
+            "usampler2D" => Some(GlslType::USampler2D),
+            "usampler3D" => Some(GlslType::USampler3D),
+            "usamplerCube" => Some(GlslType::USamplerCube),
+            "usampler1DArray" => Some(GlslType::USampler1DArray),
+            "usampler2DArray" => Some(GlslType::USampler2DArray),
+            "usamplerCubeArray" => Some(GlslType::USamplerCubeArray),
+            "usampler2DMS" => Some(GlslType::USampler2DMS),
+            "usampler2DMSArray" => Some(GlslType::USampler2DMSArray),
+            "usampler2DRect" => Some(GlslType::USampler2DRect),
+            "usamplerBuffer" => Some(GlslType::USamplerBuffer),
+            "image1D" => Some(GlslType::Image1D),
+            "image2D" => Some(GlslType::Image2D),
+            "image3D" => Some(GlslType::Image3D),
+            "imageCube" => Some(GlslType::ImageCube),
+            "image1DArray" => Some(GlslType::Image1DArray),
+            "image2DArray" => Some(GlslType::Image2DArray),
+            "imageCubeArray" => Some(GlslType::ImageCubeArray),
+            "image2DMS" => Some(GlslType::Image2DMS),
+            "image2DMSArray" => Some(GlslType::Image2DMSArray),
+            "image2DRect" => Some(GlslType::Image2DRect),
+            "imageBuffer" => Some(GlslType::ImageBuffer),
+            "iimage1D" => Some(GlslType::IImage1D),
+            "iimage2D" => Some(GlslType::IImage2D),
+            "iimage3D" => Some(GlslType::IImage3D),
+            "iimageCube" => Some(GlslType::IImageCube),
+            "iimage1DArray" => Some(GlslType::IImage1DArray),
+            "iimage2DArray" => Some(GlslType::IImage2DArray),
+            "iimageCubeArray" => Some(GlslType::IImageCubeArray),
+            "iimage2DMS" => Some(GlslType::IImage2DMS),
+            "iimage2DMSArray" => Some(GlslType::IImage2DMSArray),
+            "iimage2DRect" => Some(GlslType::IImage2DRect),
+            "iimageBuffer" => Some(GlslType::IImageBuffer),
+            "uimage1D" => Some(GlslType::UImage1D),
+            "uimage2D" => Some(GlslType::UImage2D),
+            "uimage3D" => Some(GlslType::UImage3D),
+            "uimageCube" => Some(GlslType::UImageCube),
+            "uimage1DArray" => Some(GlslType::UImage1DArray),
+            "uimage2DArray" => Some(GlslType::UImage2DArray),
+            "uimageCubeArray" => Some(GlslType::UImageCubeArray),
+            "uimage2DMS" => Some(GlslType::UImage2DMS),
+            "uimage2DMSArray" => Some(GlslType::UImage2DMSArray),
+            "uimage2DRect" => Some(GlslType::UImage2DRect),
+            "uimageBuffer" => Some(GlslType::UImageBuffer),
+            "atomic_uint" => Some(GlslType::AtomicUint),
+            "mat2x2" => Some(GlslType::Mat2),
+            "mat3x3" => Some(GlslType::Mat3),
+            "mat4x4" => Some(GlslType::Mat4),
+            "dmat2x2" => Some(GlslType::DMat2),
+            "dmat3x3" => Some(GlslType::DMat3),
+            "dmat4x4" => Some(GlslType::DMat4),
+            _ => None
+        }
+    }
+
+    pub fn is_sampler(&self) -> bool {
+        match *self {
+            GlslType::Sampler1D |
+            GlslType::Sampler2D |
+            GlslType::Sampler3D |
+            GlslType::SamplerCube |
+            GlslType::Sampler1DArray |
+            GlslType::Sampler2DArray |
+            GlslType::SamplerCubeArray |
+            GlslType::Sampler2DMS |
+            GlslType::Sampler2DMSArray |
+            GlslType::Sampler2DRect |
+            GlslType::SamplerBuffer |
+            GlslType::Sampler1DShadow |
+            GlslType::Sampler2DShadow |
+            GlslType::SamplerCubeShadow |
+            GlslType::Sampler1DArrayShadow |
+            GlslType::Sampler2DArrayShadow |
+            GlslType::SamplerCubeArrayShadow |
+            GlslType::Sampler2DRectShadow |
+            GlslType::ISampler1D |
+            GlslType::ISampler2D |
+            GlslType::ISampler3D |
+            GlslType::ISamplerCube |
+            GlslType::ISampler1DArray |
+            GlslType::ISampler2DArray |
+            GlslType::ISamplerCubeArray |
+            GlslType::ISampler2DMS |
+            GlslType::ISampler2DMSArray |
+            GlslType::ISampler2DRect |
+            GlslType::ISamplerBuffer |
+            GlslType::USampler1D |
+            GlslType::USampler2D |
+            GlslType::USampler3D |
+            GlslType::USamplerCube |
+            GlslType::USampler1DArray |
+            GlslType::USampler2DArray |
+            GlslType::USamplerCubeArray |
+            GlslType::USampler2DMS |
+            GlslType::USampler2DMSArray |
+            GlslType::USampler2DRect |
+            GlslType::USamplerBuffer => true,
+            _ => false
+        }
+    }
+
+    pub fn is_image(&self) -> bool {
+        match *self {
+            GlslType::Image1D |
+            GlslType::Image2D |
+            GlslType::Image3D |
+            GlslType::ImageCube |
+            GlslType::Image1DArray |
+            GlslType::Image2DArray |
+            GlslType::ImageCubeArray |
+            GlslType::Image2DMS |
+            GlslType::Image2DMSArray |
+            GlslType::Image2DRect |
+            GlslType::ImageBuffer |
+            GlslType::IImage1D |
+            GlslType::IImage2D |
+            GlslType::IImage3D |
+            GlslType::IImageCube |
+            GlslType::IImage1DArray |
+            GlslType::IImage2DArray |
+            GlslType::IImageCubeArray |
+            GlslType::IImage2DMS |
+            GlslType::IImage2DMSArray |
+            GlslType::IImage2DRect |
+            GlslType::IImageBuffer |
+            GlslType::UImage1D |
+            GlslType::UImage2D |
+            GlslType::UImage3D |
+            GlslType::UImageCube |
+            GlslType::UImage1DArray |
+            GlslType::UImage2DArray |
+            GlslType::UImageCubeArray |
+            GlslType::UImage2DMS |
+            GlslType::UImage2DMSArray |
+            GlslType::UImage2DRect |
+            GlslType::UImageBuffer => true,
+            _ => false
+        }
+    }
+}
+
+impl fmt::Display for GlslType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GlslType::Unknown(gl_type) => write!(f, "<unknown type 0x{:x}>", gl_type),
+            _ => write!(f, "{}", self.name())
+        }
+    }
+}
@@ -0,0 +1,696 @@
+extern crate gl;
+use gl::types::*;
+use gl_err::*;
+use glsl_conditionals::*;
+use glsl_parser::*;
+use glsl_types::GlslType;
+
+use std::collections::{HashMap, HashSet};
+
+///
+/// Checks a shader source without the driver, so every vendor reports the
+/// same errors: syntax errors, undeclared identifiers, unknown types and
+/// functions, and features the `#version` doesn't have, unless an
+/// `#extension` enables them. With any extension enabled, unknown types and
+/// functions are allowed, as the extension may declare them. `#if`/`#ifdef` sections are evaluated and
+/// object-like macros expanded. Function-like macros aren't expanded: their
+/// names count as declared, and a syntax error in a source that calls one
+/// is left for the driver to report.
+///
+/// Errors are formatted like driver logs, `ERROR: <source>:<line>: message`,
+/// one per line.
+///
+pub fn validate_glsl(shader_type : GLenum, source : &str) -> Result<()> {
+    let version = find_version(source);
+    let mut macros = predefined_macros(version, shader_type == gl::FRAGMENT_SHADER);
+    let active_source = evaluate_conditionals(source, &mut macros)?;
+    let unit = match parse_glsl(&active_source) {
+        Ok(unit) => unit,
+        Err(_) if calls_function_macro(&active_source, &macros) => return Ok(()),
+        Err(err) => return Err(GlError::new(format!("ERROR: {}", err)))
+    };
+
+    let mut validator = Validator {
+        shader_type: shader_type,
+        version: version,
+        extensions: enabled_extensions(&active_source),
+        macros: macros.into_keys().collect(),
+        scopes: vec![HashSet::new()],
+        functions: HashSet::new(),
+        subroutine_types: HashSet::new(),
+        structs: HashSet::new(),
+        default_float_precision: false,
+        errors: Vec::new()
+    };
+    validator.check_version();
+    for declaration in &unit.declarations {
+        validator.check_global(declaration);
+    }
+    if validator.errors.is_empty() {
+        Ok(())
+    } else {
+        Err(GlError::new(validator.errors.join("\n")))
+    }
+}
+
+///
+/// The extensions an `#extension` line requires or enables.
+///
+fn enabled_extensions(source : &str) -> HashSet<String> {
+    let mut extensions = HashSet::new();
+    for line in source.lines() {
+        let line = line.trim_start();
+        if !line.starts_with('#') || !line[1..].trim_start().starts_with("extension") {
+            continue;
+        }
+        let args = line[1..].trim_start()["extension".len()..].trim();
+        let mut parts = args.splitn(2, ':').map(str::trim);
+        match (parts.next(), parts.next()) {
+            (Some(name), Some("disable")) => {
+                extensions.remove(name);
+            }
+            (Some(name), Some(_)) => {
+                extensions.insert(name.to_string());
+            }
+            _ => ()
+        }
+    }
+    extensions
+}
+
+///
+/// Whether `source` uses a function-like macro, which is left unexpanded.
+///
+fn calls_function_macro(source : &str, macros : &HashMap<String, Option<String>>) -> bool {
+    source.split(|c : char| !c.is_alphanumeric() && c != '_')
+        .any(|word| macros.get(word) == Some(&None))
+}
+
+fn version_name(number : u32, es : bool) -> String {
+    format!("{}.{:02}{}", number / 100, number % 100, if es { " es" } else { "" })
+}
+
+const BUILTIN_FUNCTIONS : &[&str] = &[
+    "radians", "degrees", "sin", "cos", "tan", "asin", "acos", "atan", "sinh", "cosh", "tanh", "asinh", "acosh",
+    "atanh", "pow", "exp", "log", "exp2", "log2", "sqrt", "inversesqrt", "abs", "sign", "floor", "trunc", "round",
+    "roundEven", "ceil", "fract", "mod", "modf", "min", "max", "clamp", "mix", "step", "smoothstep", "isnan",
+    "isinf", "floatBitsToInt", "floatBitsToUint", "intBitsToFloat", "uintBitsToFloat", "fma", "frexp", "ldexp",
+    "packUnorm2x16", "packSnorm2x16", "packUnorm4x8", "packSnorm4x8", "unpackUnorm2x16", "unpackSnorm2x16",
+    "unpackUnorm4x8", "unpackSnorm4x8", "packHalf2x16", "unpackHalf2x16", "packDouble2x32", "unpackDouble2x32",
+    "length", "distance", "dot", "cross", "normalize", "faceforward", "reflect", "refract", "matrixCompMult",
+    "outerProduct", "transpose", "determinant", "inverse", "lessThan", "lessThanEqual", "greaterThan",
+    "greaterThanEqual", "equal", "notEqual", "any", "all", "not", "uaddCarry", "usubBorrow", "umulExtended",
+    "imulExtended", "bitfieldExtract", "bitfieldInsert", "bitfieldReverse", "bitCount", "findLSB", "findMSB",
+    "textureSize", "textureQueryLod", "textureQueryLevels", "textureSamples", "texture", "textureProj",
+    "textureLod", "textureOffset", "texelFetch", "texelFetchOffset", "textureProjOffset", "textureLodOffset",
+    "textureProjLod", "textureProjLodOffset", "textureGrad", "textureGradOffset", "textureProjGrad",
+    "textureProjGradOffset", "textureGather", "textureGatherOffset", "textureGatherOffsets",
+    "atomicCounterIncrement", "atomicCounterDecrement", "atomicCounter", "atomicAdd", "atomicMin", "atomicMax",
+    "atomicAnd", "atomicOr", "atomicXor", "atomicExchange", "atomicCompSwap", "imageSize", "imageSamples",
+    "imageLoad", "imageStore", "imageAtomicAdd", "imageAtomicMin", "imageAtomicMax", "imageAtomicAnd",
+    "imageAtomicOr", "imageAtomicXor", "imageAtomicExchange", "imageAtomicCompSwap", "dFdx", "dFdy", "dFdxFine",
+    "dFdyFine", "dFdxCoarse", "dFdyCoarse", "fwidth", "fwidthFine", "fwidthCoarse", "interpolateAtCentroid",
+    "interpolateAtSample", "interpolateAtOffset", "noise1", "noise2", "noise3", "noise4", "EmitStreamVertex",
+    "EndStreamPrimitive", "EmitVertex", "EndPrimitive", "barrier", "memoryBarrier", "memoryBarrierAtomicCounter",
+    "memoryBarrierBuffer", "memoryBarrierShared", "memoryBarrierImage", "groupMemoryBarrier", "anyInvocation",
+    "allInvocations", "allInvocationsEqual"
+];
+
+///
+/// Texture functions of GLSL 1.10/1.20 and ES 1.00, removed from the core
+/// profile and ES 3.00.
+///
+const LEGACY_TEXTURE_FUNCTIONS : &[&str] = &[
+    "texture1D", "texture1DProj", "texture1DLod", "texture1DProjLod", "texture2D", "texture2DProj",
+    "texture2DLod", "texture2DProjLod", "texture3D", "texture3DProj", "texture3DLod", "texture3DProjLod",
+    "textureCube", "textureCubeLod", "shadow1D", "shadow2D", "shadow1DProj", "shadow2DProj", "shadow1DLod",
+    "shadow2DLod", "shadow1DProjLod", "shadow2DProjLod", "ftransform"
+];
+
+///
+/// Built-in variables only some stages have, with the stage.
+///
+const STAGE_VARIABLES : &[(&str, GLenum)] = &[
+    ("gl_FragCoord", gl::FRAGMENT_SHADER),
+    ("gl_FrontFacing", gl::FRAGMENT_SHADER),
+    ("gl_PointCoord", gl::FRAGMENT_SHADER),
+    ("gl_FragDepth", gl::FRAGMENT_SHADER),
+    ("gl_SampleID", gl::FRAGMENT_SHADER),
+    ("gl_SamplePosition", gl::FRAGMENT_SHADER),
+    ("gl_SampleMaskIn", gl::FRAGMENT_SHADER),
+    ("gl_SampleMask", gl::FRAGMENT_SHADER),
+    ("gl_HelperInvocation", gl::FRAGMENT_SHADER),
+    ("gl_FragColor", gl::FRAGMENT_SHADER),
+    ("gl_FragData", gl::FRAGMENT_SHADER),
+    ("gl_VertexID", gl::VERTEX_SHADER),
+    ("gl_InstanceID", gl::VERTEX_SHADER),
+    ("gl_BaseVertex", gl::VERTEX_SHADER),
+    ("gl_BaseInstance", gl::VERTEX_SHADER),
+    ("gl_DrawID", gl::VERTEX_SHADER),
+    ("gl_Vertex", gl::VERTEX_SHADER),
+    ("gl_Normal", gl::VERTEX_SHADER),
+    ("gl_Color", gl::VERTEX_SHADER),
+    ("gl_NumWorkGroups", gl::COMPUTE_SHADER),
+    ("gl_WorkGroupSize", gl::COMPUTE_SHADER),
+    ("gl_WorkGroupID", gl::COMPUTE_SHADER),
+    ("gl_LocalInvocationID", gl::COMPUTE_SHADER),
+    ("gl_GlobalInvocationID", gl::COMPUTE_SHADER),
+    ("gl_LocalInvocationIndex", gl::COMPUTE_SHADER)
+];
+
+///
+/// Built-in variables of GLSL 1.10/1.20 removed from the core profile and
+/// ES 3.00.
+///
+const LEGACY_VARIABLES : &[&str] = &["gl_FragColor", "gl_FragData", "gl_Vertex", "gl_Normal", "gl_Color"];
+
+///
+/// Layout qualifiers newer than layouts themselves, with the versions that
+/// have them (ES 0 for none) and the extensions that add them to older ones.
+///
+const LAYOUT_QUALIFIERS : &[(&str, u32, u32, &[&str])] = &[
+    ("location", 330, 300, &["GL_ARB_explicit_attrib_location", "GL_ARB_separate_shader_objects",
+                             "GL_ARB_explicit_uniform_location"]),
+    ("index", 330, 0, &["GL_ARB_blend_func_extended"]),
+    ("binding", 420, 310, &["GL_ARB_shading_language_420pack"]),
+    ("offset", 420, 310, &["GL_ARB_shader_atomic_counters", "GL_ARB_enhanced_layouts"]),
+    ("early_fragment_tests", 420, 310, &["GL_ARB_shader_image_load_store"]),
+    ("std430", 430, 310, &["GL_ARB_shader_storage_buffer_object"])
+];
+
+///
+/// Extensions that add layout qualifiers to versions before GLSL 1.40.
+///
+const LAYOUT_EXTENSIONS : &[&str] = &[
+    "GL_ARB_uniform_buffer_object", "GL_ARB_explicit_attrib_location", "GL_ARB_separate_shader_objects",
+    "GL_ARB_explicit_uniform_location", "GL_ARB_blend_func_extended", "GL_ARB_shading_language_420pack",
+    "GL_ARB_shader_atomic_counters", "GL_ARB_enhanced_layouts", "GL_ARB_shader_image_load_store",
+    "GL_ARB_shader_storage_buffer_object", "GL_ARB_compute_shader", "GL_ARB_fragment_coord_conventions"
+];
+
+struct Validator {
+    shader_type : GLenum,
+    version : GlslVersion,
+    extensions : HashSet<String>,
+    macros : HashSet<String>,
+    scopes : Vec<HashSet<String>>,
+    functions : HashSet<String>,
+    subroutine_types : HashSet<String>,
+    structs : HashSet<String>,
+    default_float_precision : bool,
+    errors : Vec<String>
+}
+
+impl Validator {
+    fn error(&mut self, position : GlslPosition, message : String) {
+        self.errors.push(format!("ERROR: {}:{}: {}", position.source, position.line, message));
+    }
+
+    fn is_core(&self) -> bool {
+        !self.version.es && self.version.number >= 140 && !self.version.compatibility
+    }
+
+    ///
+    /// Reports `what` if the version is older than `desktop` (or `es` for
+    /// GLSL ES) and none of `extensions` is enabled. `es` 0 means ES doesn't
+    /// have it at all.
+    ///
+    fn require(&mut self, desktop : u32, es : u32, extensions : &[&str], what : &str, position : GlslPosition) {
+        if extensions.iter().any(|&extension| self.extensions.contains(extension)) {
+            return;
+        }
+        let (required, es_version) = if self.version.es { (es, true) } else { (desktop, false) };
+        if required == 0 {
+            let message = format!("{} is not available in GLSL ES", what);
+            self.error(position, message);
+        } else if self.version.number < required {
+            let message = format!("GLSL {} is required for {}, but the shader is GLSL {}",
+                                  version_name(required, es_version), what, version_name(self.version.number, es_version));
+            self.error(position, message);
+        }
+    }
+
+    fn check_version(&mut self) {
+        let desktop = [110, 120, 130, 140, 150, 330, 400, 410, 420, 430, 440, 450, 460];
+        let es = [100, 300, 310, 320];
+        let position = GlslPosition::default();
+        let valid = if self.version.es { es.contains(&self.version.number) } else { desktop.contains(&self.version.number) };
+        if !valid {
+            let message = format!("unsupported #version {}", version_name(self.version.number, self.version.es));
+            self.error(position, message);
+        }
+        if self.version.compatibility && self.version.number < 150 {
+            self.error(position, "profiles require GLSL 1.50".to_string());
+        }
+    }
+
+    fn declare(&mut self, name : &str) {
+        self.scopes.last_mut().unwrap().insert(name.to_string());
+    }
+
+    fn is_declared(&self, name : &str) -> bool {
+        self.scopes.iter().any(|scope| scope.contains(name)) || self.macros.contains(name)
+    }
+
+    fn check_global(&mut self, declaration : &GlslDeclaration) {
+        match *declaration {
+            GlslDeclaration::Variable(ref variable) => self.check_variable(variable, true),
+            GlslDeclaration::Block(ref block) => {
+                self.check_qualifiers(&block.qualifiers, block.position, true);
+                match block.qualifiers.storage {
+                    Some(GlslStorage::Uniform) => {
+                        self.require(140, 300, &["GL_ARB_uniform_buffer_object"], "uniform blocks", block.position);
+                    }
+                    Some(GlslStorage::Buffer) => (),
+                    _ => {
+                        let extensions = ["GL_EXT_shader_io_blocks", "GL_OES_shader_io_blocks"];
+                        self.require(150, 320, &extensions, "interface blocks", block.position);
+                    }
+                }
+                for member in &block.members {
+                    self.check_type(&member.type_name, &member.qualifiers, member.position);
+                }
+                match block.instance_name {
+                    Some(ref instance) => self.declare(instance),
+                    None => for member in &block.members {
+                        self.declare(&member.name);
+                    }
+                }
+            }
+            GlslDeclaration::Struct(ref glsl_struct) => self.check_struct(glsl_struct),
+            GlslDeclaration::Function(ref function) => self.check_function(function),
+            GlslDeclaration::Qualifiers(ref qualifiers) => {
+                self.check_qualifiers(qualifiers, GlslPosition::default(), true);
+            }
+            GlslDeclaration::Precision(_, ref type_name) => {
+                if !self.version.es {
+                    self.require(130, 100, &[], "precision qualifiers", GlslPosition::default());
+                }
+                if type_name == "float" {
+                    self.default_float_precision = true;
+                }
+            }
+        }
+    }
+
+    fn check_struct(&mut self, glsl_struct : &GlslStruct) {
+        for member in &glsl_struct.members {
+            self.check_type(&member.type_name, &member.qualifiers, member.position);
+        }
+        self.structs.insert(glsl_struct.name.clone());
+        self.declare(&glsl_struct.name);
+    }
+
+    fn check_function(&mut self, function : &GlslFunction) {
+        self.check_type(&function.return_type, &GlslQualifiers::default(), function.position);
+        self.check_qualifiers(&function.qualifiers, function.position, true);
+        for subroutine_type in &function.qualifiers.subroutine_types {
+            if !self.subroutine_types.contains(subroutine_type) {
+                self.error(function.position, format!("`{}` : unknown subroutine type", subroutine_type));
+            }
+        }
+        // `subroutine` without types declares a subroutine type.
+        if function.qualifiers.is_subroutine() && function.qualifiers.subroutine_types.is_empty() {
+            self.subroutine_types.insert(function.name.clone());
+        }
+        self.functions.insert(function.name.clone());
+        let body = match function.body {
+            Some(ref body) => body,
+            None => {
+                for parameter in &function.parameters {
+                    self.check_type(&parameter.type_name, &parameter.qualifiers, parameter.position);
+                }
+                return;
+            }
+        };
+        self.scopes.push(HashSet::new());
+        for parameter in &function.parameters {
+            self.check_type(&parameter.type_name, &parameter.qualifiers, parameter.position);
+            if !parameter.name.is_empty() {
+                self.declare(&parameter.name);
+            }
+        }
+        for statement in body {
+            self.check_statement(statement);
+        }
+        self.scopes.pop();
+    }
+
+    fn check_variable(&mut self, variable : &GlslVariable, global : bool) {
+        self.check_qualifiers(&variable.qualifiers, variable.position, global);
+        if let Some(ref initializer) = variable.initializer {
+            self.check_expression(initializer);
+        }
+        self.declare(&variable.name);
+        // Subroutine uniforms are called like functions.
+        if variable.qualifiers.is_subroutine() {
+            if !self.subroutine_types.contains(&variable.type_name) {
+                self.error(variable.position, format!("`{}` : unknown subroutine type", variable.type_name));
+            }
+            self.functions.insert(variable.name.clone());
+            return;
+        }
+        self.check_type(&variable.type_name, &variable.qualifiers, variable.position);
+    }
+
+    fn check_qualifiers(&mut self, qualifiers : &GlslQualifiers, position : GlslPosition, global : bool) {
+        let stage = self.shader_type;
+        match qualifiers.storage {
+            Some(GlslStorage::In) | Some(GlslStorage::Out) if global => {
+                self.require(130, 300, &[], "`in` and `out` variables", position);
+            }
+            Some(GlslStorage::Attribute) | Some(GlslStorage::Varying) => {
+                let what = if qualifiers.storage == Some(GlslStorage::Attribute) { "attribute" } else { "varying" };
+                if self.is_core() || (self.version.es && self.version.number >= 300) {
+                    let message = format!("`{}` was removed in GLSL {}; use `in` and `out`", what,
+                                          version_name(self.version.number, self.version.es));
+                    self.error(position, message);
+                }
+                if what == "attribute" && stage != gl::VERTEX_SHADER {
+                    self.error(position, "`attribute` is only allowed in vertex shaders".to_string());
+                }
+            }
+            Some(GlslStorage::Buffer) => {
+                self.require(430, 310, &["GL_ARB_shader_storage_buffer_object"], "storage buffers", position);
+            }
+            Some(GlslStorage::Shared) => {
+                self.require(430, 310, &["GL_ARB_compute_shader"], "`shared` variables", position);
+                if stage != gl::COMPUTE_SHADER {
+                    self.error(position, "`shared` is only allowed in compute shaders".to_string());
+                }
+            }
+            _ => ()
+        }
+        if qualifiers.is_subroutine() {
+            self.require(400, 0, &["GL_ARB_shader_subroutine"], "subroutines", position);
+        }
+        if qualifiers.interpolation.is_some() {
+            self.require(130, 300, &["GL_EXT_gpu_shader4"], "interpolation qualifiers", position);
+        }
+
+        if qualifiers.layout.is_empty() {
+            return;
+        }
+        self.require(140, 300, LAYOUT_EXTENSIONS, "layout qualifiers", position);
+        for &(ref name, _) in &qualifiers.layout {
+            let (desktop, es, extensions) = match LAYOUT_QUALIFIERS.iter().find(|&&(n, _, _, _)| n == name) {
+                Some(&(_, desktop, es, extensions)) => (desktop, es, extensions),
+                None if name.starts_with("local_size_") => (430, 310, &["GL_ARB_compute_shader"][..]),
+                None => continue
+            };
+            self.require(desktop, es, extensions, &format!("`layout({})`", name), position);
+        }
+    }
+
+    ///
+    /// Checks that `type_name`, possibly an array type, is declared and the
+    /// version has it.
+    ///
+    fn check_type(&mut self, type_name : &str, qualifiers : &GlslQualifiers, position : GlslPosition) {
+        let base = type_name.split('[').next().unwrap_or(type_name);
+        if base == "void" || self.structs.contains(base) || self.macros.contains(base) {
+            return;
+        }
+        let glsl_type = match GlslType::from_name(base) {
+            Some(glsl_type) => glsl_type,
+            // Extensions add types, e.g. `samplerExternalOES`.
+            None if !self.extensions.is_empty() => return,
+            None => {
+                self.error(position, format!("`{}` : unknown type", base));
+                return;
+            }
+        };
+        let what = format!("type `{}`", base);
+        if base == "double" || base.starts_with("dvec") || base.starts_with("dmat") {
+            self.require(400, 0, &["GL_ARB_gpu_shader_fp64"], &what, position);
+        } else if base.starts_with("uint") || base.starts_with("uvec") || base.starts_with("usampler") {
+            self.require(130, 300, &["GL_EXT_gpu_shader4"], &what, position);
+        } else if glsl_type.is_image() {
+            self.require(420, 310, &["GL_ARB_shader_image_load_store"], &what, position);
+        } else if base == "atomic_uint" {
+            self.require(420, 310, &["GL_ARB_shader_atomic_counters"], &what, position);
+        }
+
+        // ES fragment shaders have no default float precision.
+        let is_float = base == "float" || base.starts_with("vec") || base.starts_with("mat");
+        let has_precision = qualifiers.other.iter().any(|q| q == "highp" || q == "mediump" || q == "lowp");
+        if self.version.es && self.shader_type == gl::FRAGMENT_SHADER && is_float && !has_precision
+            && !self.default_float_precision {
+            self.error(position, "no default precision for float; add `precision mediump float;`".to_string());
+            // Report it once.
+            self.default_float_precision = true;
+        }
+    }
+
+    fn check_statement(&mut self, statement : &GlslStatement) {
+        match *statement {
+            GlslStatement::Declaration(ref declarations) => {
+                for declaration in declarations {
+                    match *declaration {
+                        GlslDeclaration::Variable(ref variable) => self.check_variable(variable, false),
+                        GlslDeclaration::Struct(ref glsl_struct) => self.check_struct(glsl_struct),
+                        _ => ()
+                    }
+                }
+            }
+            GlslStatement::Expression(ref expression) => self.check_expression(expression),
+            GlslStatement::Compound(ref statements) => {
+                self.scopes.push(HashSet::new());
+                for statement in statements {
+                    self.check_statement(statement);
+                }
+                self.scopes.pop();
+            }
+            GlslStatement::If(ref condition, ref then, ref otherwise) => {
+                self.check_expression(condition);
+                self.check_statement(then);
+                if let Some(ref otherwise) = *otherwise {
+                    self.check_statement(otherwise);
+                }
+            }
+            GlslStatement::For(ref init, ref condition, ref step, ref body) => {
+                self.scopes.push(HashSet::new());
+                self.check_statement(init);
+                if let Some(ref condition) = *condition {
+                    self.check_expression(condition);
+                }
+                if let Some(ref step) = *step {
+                    self.check_expression(step);
+                }
+                self.check_statement(body);
+                self.scopes.pop();
+            }
+            GlslStatement::While(ref condition, ref body) => {
+                self.check_expression(condition);
+                self.check_statement(body);
+            }
+            GlslStatement::DoWhile(ref body, ref condition) => {
+                self.check_statement(body);
+                self.check_expression(condition);
+            }
+            GlslStatement::Switch(ref value, ref statements, position) => {
+                self.require(130, 300, &[], "`switch`", position);
+                self.check_expression(value);
+                self.scopes.push(HashSet::new());
+                for statement in statements {
+                    self.check_statement(statement);
+                }
+                self.scopes.pop();
+            }
+            GlslStatement::Case(ref value) => self.check_expression(value),
+            GlslStatement::Return(Some(ref value)) => self.check_expression(value),
+            GlslStatement::Discard(position) => {
+                if self.shader_type != gl::FRAGMENT_SHADER {
+                    self.error(position, "`discard` is only allowed in fragment shaders".to_string());
+                }
+            }
+            GlslStatement::Return(None) | GlslStatement::Default | GlslStatement::Break |
+            GlslStatement::Continue | GlslStatement::Empty => ()
+        }
+    }
+
+    fn check_expression(&mut self, expression : &GlslExpression) {
+        let position = expression.position;
+        match expression.kind {
+            GlslExpressionKind::Identifier(ref name) => self.check_identifier(name, position),
+            GlslExpressionKind::IntConstant(_) | GlslExpressionKind::FloatConstant(_) |
+            GlslExpressionKind::BoolConstant(_) => (),
+            GlslExpressionKind::Unary(_, ref operand) | GlslExpressionKind::Postfix(_, ref operand) |
+            GlslExpressionKind::Field(ref operand, _) => self.check_expression(operand),
+            GlslExpressionKind::Binary(_, ref left, ref right) | GlslExpressionKind::Assign(_, ref left, ref right) |
+            GlslExpressionKind::Comma(ref left, ref right) | GlslExpressionKind::Index(ref left, ref right) => {
+                self.check_expression(left);
+                self.check_expression(right);
+            }
+            GlslExpressionKind::Conditional(ref condition, ref then, ref otherwise) => {
+                self.check_expression(condition);
+                self.check_expression(then);
+                self.check_expression(otherwise);
+            }
+            GlslExpressionKind::Call(ref name, ref arguments) => {
+                self.check_call(name, position);
+                for argument in arguments {
+                    self.check_expression(argument);
+                }
+            }
+            GlslExpressionKind::MethodCall(ref object, ref name, ref arguments) => {
+                self.check_expression(object);
+                if name != "length" || !arguments.is_empty() {
+                    self.error(position, format!("`{}` : no such method; only `length()` is allowed", name));
+                }
+            }
+            GlslExpressionKind::List(ref items) => {
+                self.require(420, 0, &["GL_ARB_shading_language_420pack"], "initializer lists", position);
+                for item in items {
+                    self.check_expression(item);
+                }
+            }
+        }
+    }
+
+    fn check_identifier(&mut self, name : &str, position : GlslPosition) {
+        if self.is_declared(name) {
+            return;
+        }
+        if !name.starts_with("gl_") {
+            self.error(position, format!("`{}` : undeclared identifier", name));
+            return;
+        }
+        if let Some(&(_, stage)) = STAGE_VARIABLES.iter().find(|&&(n, _)| n == name) {
+            if stage != self.shader_type {
+                self.error(position, format!("`{}` isn't available in this shader stage", name));
+            }
+        }
+        if LEGACY_VARIABLES.contains(&name) && (self.is_core() || (self.version.es && self.version.number >= 300)) {
+            let message = format!("`{}` was removed in GLSL {}", name, version_name(self.version.number, self.version.es));
+            self.error(position, message);
+        }
+    }
+
+    fn check_call(&mut self, name : &str, position : GlslPosition) {
+        if self.functions.contains(name) || self.structs.contains(name) || self.macros.contains(name)
+            || GlslType::from_name(name).is_some() {
+            return;
+        }
+        if LEGACY_TEXTURE_FUNCTIONS.contains(&name) {
+            if self.is_core() || (self.version.es && self.version.number >= 300) {
+                let message = format!("`{}` was removed in GLSL {}; use `texture`", name,
+                                      version_name(self.version.number, self.version.es));
+                self.error(position, message);
+            }
+            return;
+        }
+        if BUILTIN_FUNCTIONS.contains(&name) {
+            if name.starts_with("texture") || name.starts_with("texel") {
+                self.require(130, 300, &[], &format!("`{}`", name), position);
+            }
+            return;
+        }
+        // Extensions add functions, e.g. `texture2DLodEXT`.
+        if self.extensions.is_empty() {
+            self.error(position, format!("`{}` : no matching function", name));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn errors(shader_type : GLenum, source : &str) -> String {
+        validate_glsl(shader_type, source).err().map_or(String::new(), |err| err.to_string())
+    }
+
+    #[test]
+    fn valid_shaders_pass() {
+        let source = "#version 330\nlayout(location = 0) in vec3 pos;\nuniform mat4 mvp;\nout vec2 uv;\n\
+                      void main() {\n    uv = pos.xy;\n    gl_Position = mvp * vec4(pos, 1.0);\n}\n";
+        assert_eq!(errors(gl::VERTEX_SHADER, source), "");
+    }
+
+    #[test]
+    fn versions_gate_features() {
+        let source = "#version 150\nlayout(location = 0) in vec3 pos;\nvoid main() {}\n";
+        assert_eq!(errors(gl::VERTEX_SHADER, source),
+                   "ERROR: 0:2: GLSL 3.30 is required for `layout(location)`, but the shader is GLSL 1.50");
+        let source = "#version 300 es\nprecision mediump float;\nuniform double scale;\nvoid main() {}\n";
+        assert_eq!(errors(gl::FRAGMENT_SHADER, source), "ERROR: 0:3: type `double` is not available in GLSL ES");
+    }
+
+    #[test]
+    fn extensions_enable_features() {
+        let source = "#version 150\n#extension GL_ARB_explicit_attrib_location : require\n\
+                      layout(location = 0) in vec3 pos;\nvoid main() {}\n";
+        assert_eq!(errors(gl::VERTEX_SHADER, source), "");
+        let source = "#version 150\n#extension GL_ARB_explicit_attrib_location : enable\n\
+                      #extension GL_ARB_explicit_attrib_location : disable\n\
+                      layout(location = 0) in vec3 pos;\nvoid main() {}\n";
+        assert!(errors(gl::VERTEX_SHADER, source).contains("`layout(location)`"));
+    }
+
+    #[test]
+    fn extensions_allow_unknown_functions_and_types() {
+        let source = "#version 100\n#extension GL_EXT_shader_texture_lod : enable\nprecision mediump float;\n\
+                      uniform sampler2D tex;\nvarying vec2 uv;\n\
+                      void main() {\n    gl_FragColor = texture2DLodEXT(tex, uv, 0.0);\n}\n";
+        assert_eq!(errors(gl::FRAGMENT_SHADER, source), "");
+        let source = "#version 300 es\n#extension GL_OES_EGL_image_external_essl3 : require\nprecision mediump float;\n\
+                      uniform samplerExternalOES frame;\nin vec2 uv;\nout vec4 color;\n\
+                      void main() {\n    color = texture(frame, uv);\n}\n";
+        assert_eq!(errors(gl::FRAGMENT_SHADER, source), "");
+        let source = "#version 450\n#extension GL_KHR_shader_subgroup_basic : require\nlayout(local_size_x = 64) in;\n\
+                      void main() {\n    subgroupBarrier();\n}\n";
+        assert_eq!(errors(gl::COMPUTE_SHADER, source), "");
+        let source = "#version 100\nprecision mediump float;\nuniform sampler2D tex;\n\
+                      void main() {\n    gl_FragColor = texture2DLodEXT(tex, vec2(0.0), 0.0);\n}\n";
+        assert_eq!(errors(gl::FRAGMENT_SHADER, source), "ERROR: 0:5: `texture2DLodEXT` : no matching function");
+    }
+
+    #[test]
+    fn subroutines_are_declared() {
+        let source = "#version 400\nsubroutine vec4 Shade();\nsubroutine(Shade) vec4 red() { return vec4(1.0, 0.0, 0.0, 1.0); }\n\
+                      subroutine uniform Shade shade;\nout vec4 color;\nvoid main() {\n    color = shade();\n}\n";
+        assert_eq!(errors(gl::FRAGMENT_SHADER, source), "");
+        let source = "#version 330\nsubroutine vec4 Shade();\nsubroutine(Tint) vec4 red() { return vec4(1.0); }\n";
+        assert_eq!(errors(gl::FRAGMENT_SHADER, source),
+                   "ERROR: 0:2: GLSL 4.00 is required for subroutines, but the shader is GLSL 3.30\n\
+                    ERROR: 0:3: GLSL 4.00 is required for subroutines, but the shader is GLSL 3.30\n\
+                    ERROR: 0:3: `Tint` : unknown subroutine type");
+    }
+
+    #[test]
+    fn extensions_in_inactive_sections_are_ignored() {
+        let source = "#version 150\n#ifdef GL_ES\n#extension GL_ARB_explicit_attrib_location : require\n#endif\n\
+                      layout(location = 0) in vec3 pos;\nvoid main() {}\n";
+        assert!(errors(gl::VERTEX_SHADER, source).contains("`layout(location)`"));
+    }
+
+    #[test]
+    fn if_sections_are_evaluated() {
+        let source = "#version 330\n#if __VERSION__ >= 400\nuniform dvec3 offset;\n#elif defined(GL_core_profile)\n\
+                      uniform vec3 offset;\n#endif\nout vec4 color;\nvoid main() {\n    color = vec4(offset, 1.0);\n}\n";
+        assert_eq!(errors(gl::FRAGMENT_SHADER, source), "");
+    }
+
+    #[test]
+    fn object_like_macros_are_expanded() {
+        let source = "#version 330\n#define VARYING out\n#define COUNT 4\nVARYING vec2 uv;\nuniform vec4 weights[COUNT];\n\
+                      void main() {\n    uv = weights[COUNT - 1].xy;\n}\n";
+        assert_eq!(errors(gl::VERTEX_SHADER, source), "");
+    }
+
+    #[test]
+    fn function_like_macros_leave_syntax_to_the_driver() {
+        let source = "#version 330\n#define DECLARE(type, name) uniform type name;\nDECLARE(float, scale)\n\
+                      void main() {}\n";
+        assert_eq!(errors(gl::VERTEX_SHADER, source), "");
+        let source = "#version 330\nuniform float scale\nvoid main() {}\n";
+        assert!(errors(gl::VERTEX_SHADER, source).starts_with("ERROR: 0:3:"));
+    }
+
+    #[test]
+    fn undeclared_identifiers_are_reported() {
+        let source = "#version 330\nout vec4 color;\nvoid main() {\n    color = tint;\n}\n";
+        assert_eq!(errors(gl::FRAGMENT_SHADER, source), "ERROR: 0:4: `tint` : undeclared identifier");
+    }
+}
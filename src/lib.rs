@@ -4,38 +4,140 @@
 #![cfg_attr(feature="clippy", plugin(clippy))]
 
 extern crate gl;
+#[cfg(feature="derive")]
+extern crate glust_derive;
+#[cfg(feature="images")]
+extern crate image;
 
 mod gl_shaders;
+mod glsl_types;
+mod gl_reflection;
+mod gl_shader_builder;
+mod gl_shader_reload;
+mod gl_shader_variants;
+mod gl_program_pipeline;
+mod gl_program_cache;
+mod glsl_preprocess;
+mod glsl_parser;
+mod glsl_conditionals;
+mod glsl_bindgen;
+#[cfg(feature="validate")]
+mod glsl_validate;
 mod gl_buffer;
 mod gl_framebuffer;
 mod gl_vertex_array;
+mod gl_vertex_format;
 mod gl_texture;
+mod gl_texture_format;
+mod gl_sampler;
+#[cfg(feature="images")]
+mod gl_texture_image;
 mod gl_err;
 mod shader;
 mod gl_render;
+mod gl_compute;
+mod gl_transform_feedback;
+mod gl_spirv;
+mod gl_stage_interface;
 
-pub use gl_shaders::AttribInfo;
-pub use gl_shaders::UniformInfo;
+pub use gl_reflection::AttribInfo;
+pub use gl_reflection::UniformInfo;
+pub use gl_reflection::FragOutputInfo;
+pub use gl_reflection::InterfaceVariableInfo;
+pub use gl_reflection::BlockInfo;
+pub use gl_reflection::BlockMemberInfo;
 pub use gl_shaders::GlShader;
+pub use gl_shaders::GlUniformLocation;
 pub use gl_shaders::GlShaderUniform;
+pub use gl_shaders::GlShaderUniformArray;
+pub use gl_shaders::GlImageAccess;
+pub use gl_shaders::GlMatrixLayout;
+pub use glsl_types::GlslType;
+pub use gl_shader_builder::GlShaderBuilder;
+pub use gl_shader_builder::GlShaderStage;
+pub use gl_shader_reload::GlReloadableShader;
+pub use gl_shader_variants::GlShaderVariants;
+pub use gl_program_pipeline::GlProgramPipeline;
+pub use gl_program_cache::GlProgramCache;
+pub use gl_program_cache::GlProgramCacheKey;
+pub use glsl_preprocess::ShaderFileSystem;
+pub use glsl_preprocess::DirectoryFileSystem;
+pub use glsl_preprocess::EmbeddedFileSystem;
+pub use glsl_preprocess::MemoryFileSystem;
+pub use glsl_preprocess::PreprocessedSource;
+pub use glsl_preprocess::preprocess;
+pub use glsl_preprocess::inject_defines;
+pub use glsl_parser::GlslTranslationUnit;
+pub use glsl_parser::GlslVersion;
+pub use glsl_parser::GlslDeclaration;
+pub use glsl_parser::GlslStorage;
+pub use glsl_parser::GlslQualifiers;
+pub use glsl_parser::GlslVariable;
+pub use glsl_parser::GlslBlock;
+pub use glsl_parser::GlslStruct;
+pub use glsl_parser::GlslFunction;
+pub use glsl_parser::GlslPosition;
+pub use glsl_parser::GlslStatement;
+pub use glsl_parser::GlslExpression;
+pub use glsl_parser::GlslExpressionKind;
+pub use glsl_parser::parse_glsl;
+pub use glsl_bindgen::ShaderBindgen;
+#[cfg(feature="validate")]
+pub use glsl_validate::validate_glsl;
 
 pub use gl_buffer::GlBufferRaw;
 pub use gl_buffer::GlBufferElementType;
 pub use gl_buffer::GlBuffer;
 pub use gl_buffer::GlIndexBuffer;
+pub use gl_buffer::gl_type_size;
 
 pub use gl_framebuffer::GlFramebuffer;
 pub use gl_vertex_array::GlVertexArray;
 pub use gl_vertex_array::GlVertexArrayTmp;
 pub use gl_vertex_array::HasGlVertexArrayHandle;
+pub use gl_vertex_format::GlVertexFormat;
+pub use gl_vertex_format::GlVertexAttribFormat;
+pub use gl_texture::GlPixelElement;
 pub use gl_texture::GlTexture;
+pub use gl_texture_format::TextureFormat;
+pub use gl_sampler::GlSampler;
+pub use gl_sampler::GlFilter;
+pub use gl_sampler::GlWrap;
+pub use gl_sampler::GlCompareFunc;
+#[cfg(feature="images")]
+pub use gl_texture_image::GlColorSpace;
+#[cfg(feature="images")]
+pub use gl_texture_image::GlImageOptions;
 
 pub use gl_render::RenderTarget;
+pub use gl_render::GlRenderProgram;
 pub use gl_render::render;
 pub use gl_render::render_indexed;
 pub use gl_render::clear;
 
+pub use gl_compute::GlComputeShader;
+pub use gl_compute::GlMemoryBarrier;
+pub use gl_compute::memory_barrier;
+
+pub use gl_transform_feedback::GlTransformFeedback;
+pub use gl_transform_feedback::GlFeedbackMode;
+pub use gl_transform_feedback::GlFeedbackPrimitive;
+pub use gl_transform_feedback::GlFeedbackQuery;
+pub use gl_spirv::GlSpirvModule;
+pub use gl_spirv::load_spirv_with;
+
 pub use gl_err::validate_gl;
 pub use gl_err::GlError;
 
 pub use shader::Shader;
+pub use shader::ShaderAttribs;
+pub use shader::ShaderUniforms;
+pub use shader::ShaderUniformValue;
+pub use shader::ShaderProgram;
+
+#[cfg(feature="derive")]
+pub use glust_derive::Shader;
+#[cfg(feature="derive")]
+pub use glust_derive::ShaderAttribs;
+#[cfg(feature="derive")]
+pub use glust_derive::ShaderUniforms;
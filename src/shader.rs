@@ -1,9 +1,17 @@
 
 use gl_vertex_array::GlVertexArray;
 use gl_render::RenderTarget;
+use gl_render;
 use gl_buffer::GlBufferRaw;
+use gl_shaders::GlShader;
+use gl_shaders::GlShaderUniform;
+use gl_shaders::GlShaderUniformArray;
+use gl_texture::GlTexture;
+use gl_sampler::GlSampler;
 use gl_err::*;
 
+use std::cell::{Cell, RefCell};
+
 pub trait Shader<'a> {
     type Attribs;
     type Uniforms;
@@ -22,3 +30,219 @@ pub trait Shader<'a> {
 
 }
 
+///
+/// Vertex attribute buffers of a `Shader`, one field per attribute in location
+/// order. Implemented by `#[derive(ShaderAttribs)]`.
+///
+pub trait ShaderAttribs {
+    fn attrib_names() -> &'static [&'static str];
+    fn into_buffers(self) -> Vec<GlBufferRaw>;
+}
+
+///
+/// Uniform values of a `Shader`, one field per uniform. Implemented by
+/// `#[derive(ShaderUniforms)]`.
+///
+pub trait ShaderUniforms<'a> {
+    fn to_uniforms(&self) -> Vec<(&'static str, GlShaderUniform<'a>)>;
+}
+
+///
+/// Types usable as fields of a `ShaderUniforms` struct.
+///
+pub trait ShaderUniformValue<'a> {
+    fn to_uniform(&self) -> GlShaderUniform<'a>;
+}
+
+///
+/// Program of a derived `Shader`. Checks on first draw that the attribute and
+/// uniform structs match the program's reflection.
+///
+pub struct ShaderProgram {
+    pub shader : GlShader,
+    checked : Cell<bool>,
+    ///
+    /// Names of the program's storage blocks, reflected on first draw if a
+    /// field is a `StorageBuffer`.
+    ///
+    storage_blocks : RefCell<Vec<String>>
+}
+
+impl ShaderProgram {
+    pub fn new(shader : GlShader) -> ShaderProgram {
+        ShaderProgram {
+            shader: shader,
+            checked: Cell::new(false),
+            storage_blocks: RefCell::new(Vec::new())
+        }
+    }
+
+    pub fn render(&self,
+                  vertex_array  : &GlVertexArray,
+                  rendertarget  : &RenderTarget,
+                  attrib_names  : &[&str],
+                  uniforms      : &[(&str, GlShaderUniform)]) -> Result<()> {
+        self.check_reflection(attrib_names, uniforms)?;
+        let uniforms = self.active_uniforms(uniforms);
+        gl_render::render(&self.shader, vertex_array, vertex_array.vertex_count, rendertarget, &uniforms)
+    }
+
+    pub fn render_indexed(&self,
+                          vertex_array  : &GlVertexArray,
+                          index_buffer  : &GlBufferRaw,
+                          rendertarget  : &RenderTarget,
+                          attrib_names  : &[&str],
+                          uniforms      : &[(&str, GlShaderUniform)]) -> Result<()> {
+        self.check_reflection(attrib_names, uniforms)?;
+        let uniforms = self.active_uniforms(uniforms);
+        gl_render::render_indexed(&self.shader, vertex_array, index_buffer, rendertarget, &uniforms)
+    }
+
+    ///
+    /// Checks, once, that every active attribute is the field at its location
+    /// in the attribs struct, and that every active uniform has a field of a
+    /// matching type. Fields the driver stripped as unused are allowed.
+    ///
+    pub fn check_reflection(&self, attrib_names : &[&str], uniforms : &[(&str, GlShaderUniform)]) -> Result<()> {
+        if self.checked.get() {
+            return Ok(());
+        }
+
+        for attrib in self.shader.get_attrib_infos_sorted()? {
+            if attrib.location < 0 {
+                continue;
+            }
+            match attrib_names.get(attrib.location as usize) {
+                Some(&name) if name == attrib.name => (),
+                Some(&name) => return Err(GlError::new(format!(
+                    "Attribute {:?} is at location {}, but the attribs field there is {:?}",
+                    attrib.name, attrib.location, name))),
+                None => return Err(GlError::new(format!(
+                    "Attribute {:?} at location {} has no field in the attribs struct",
+                    attrib.name, attrib.location)))
+            }
+        }
+
+        for info in self.shader.get_uniform_infos()? {
+            if info.location < 0 || info.name.starts_with("gl_") {
+                continue;
+            }
+            let name = if info.name.ends_with("[0]") { &info.name[..info.name.len() - 3] } else { &info.name[..] };
+            match uniforms.iter().find(|&&(field, _)| field == name) {
+                Some(&(_, ref value)) if !value.matches_glsl_type(info.datatype) => {
+                    return Err(GlError::new(format!("Uniform {:?} has GLSL type {}, but its field is {}",
+                                                    name, info.datatype, value.type_name())));
+                }
+                Some(_) => (),
+                None => {
+                    return Err(GlError::new(format!("Uniform {:?} has no field in the uniforms struct", name)));
+                }
+            }
+        }
+
+        // Storage blocks are only reflected with GL 4.3, so only look when needed.
+        if uniforms.iter().any(|(_, value)| is_storage_buffer(value)) {
+            let blocks = self.shader.get_storage_blocks()?;
+            *self.storage_blocks.borrow_mut() = blocks.into_iter().map(|block| block.name).collect();
+        }
+
+        self.checked.set(true);
+        Ok(())
+    }
+
+    ///
+    /// The fields the program uses: uniforms it has and storage buffers of
+    /// its storage blocks.
+    ///
+    fn active_uniforms<'a, 'b>(&self, uniforms : &[(&'b str, GlShaderUniform<'a>)]) -> Vec<(&'b str, GlShaderUniform<'a>)> {
+        let storage_blocks = self.storage_blocks.borrow();
+        uniforms.iter()
+            .filter(|&&(name, ref value)| if is_storage_buffer(value) {
+                storage_blocks.iter().any(|block| block == name)
+            } else {
+                self.shader.uniform_location(name).is_some()
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+fn is_storage_buffer(value : &GlShaderUniform) -> bool {
+    matches!(*value, GlShaderUniform::StorageBuffer(_))
+}
+
+impl<'a> ShaderUniformValue<'a> for GlShaderUniform<'a> {
+    fn to_uniform(&self) -> GlShaderUniform<'a> { *self }
+}
+impl<'a> ShaderUniformValue<'a> for f32 {
+    fn to_uniform(&self) -> GlShaderUniform<'a> { GlShaderUniform::Float(*self) }
+}
+impl<'a> ShaderUniformValue<'a> for [f32;2] {
+    fn to_uniform(&self) -> GlShaderUniform<'a> { GlShaderUniform::Vec2(*self) }
+}
+impl<'a> ShaderUniformValue<'a> for [f32;3] {
+    fn to_uniform(&self) -> GlShaderUniform<'a> { GlShaderUniform::Vec3(*self) }
+}
+impl<'a> ShaderUniformValue<'a> for [f32;4] {
+    fn to_uniform(&self) -> GlShaderUniform<'a> { GlShaderUniform::Vec4(*self) }
+}
+impl<'a> ShaderUniformValue<'a> for [f32;9] {
+    fn to_uniform(&self) -> GlShaderUniform<'a> { GlShaderUniform::Mat3x3(*self) }
+}
+impl<'a> ShaderUniformValue<'a> for [f32;16] {
+    fn to_uniform(&self) -> GlShaderUniform<'a> { GlShaderUniform::Mat4x4(*self) }
+}
+impl<'a> ShaderUniformValue<'a> for i32 {
+    fn to_uniform(&self) -> GlShaderUniform<'a> { GlShaderUniform::Int(*self) }
+}
+impl<'a> ShaderUniformValue<'a> for [i32;2] {
+    fn to_uniform(&self) -> GlShaderUniform<'a> { GlShaderUniform::IVec2(*self) }
+}
+impl<'a> ShaderUniformValue<'a> for [i32;3] {
+    fn to_uniform(&self) -> GlShaderUniform<'a> { GlShaderUniform::IVec3(*self) }
+}
+impl<'a> ShaderUniformValue<'a> for [i32;4] {
+    fn to_uniform(&self) -> GlShaderUniform<'a> { GlShaderUniform::IVec4(*self) }
+}
+impl<'a> ShaderUniformValue<'a> for u32 {
+    fn to_uniform(&self) -> GlShaderUniform<'a> { GlShaderUniform::UInt(*self) }
+}
+impl<'a> ShaderUniformValue<'a> for [u32;2] {
+    fn to_uniform(&self) -> GlShaderUniform<'a> { GlShaderUniform::UVec2(*self) }
+}
+impl<'a> ShaderUniformValue<'a> for [u32;3] {
+    fn to_uniform(&self) -> GlShaderUniform<'a> { GlShaderUniform::UVec3(*self) }
+}
+impl<'a> ShaderUniformValue<'a> for [u32;4] {
+    fn to_uniform(&self) -> GlShaderUniform<'a> { GlShaderUniform::UVec4(*self) }
+}
+impl<'a> ShaderUniformValue<'a> for bool {
+    fn to_uniform(&self) -> GlShaderUniform<'a> { GlShaderUniform::Bool(*self) }
+}
+impl<'a> ShaderUniformValue<'a> for f64 {
+    fn to_uniform(&self) -> GlShaderUniform<'a> { GlShaderUniform::Double(*self) }
+}
+impl<'a> ShaderUniformValue<'a> for &GlTexture {
+    fn to_uniform(&self) -> GlShaderUniform<'a> { GlShaderUniform::TextureHandle(self.handle) }
+}
+impl<'a> ShaderUniformValue<'a> for (&GlTexture, &GlSampler) {
+    fn to_uniform(&self) -> GlShaderUniform<'a> { GlShaderUniform::SampledTexture(self.0.handle, self.1.handle) }
+}
+impl<'a> ShaderUniformValue<'a> for &'a [f32] {
+    fn to_uniform(&self) -> GlShaderUniform<'a> { GlShaderUniform::Array(GlShaderUniformArray::Float(*self)) }
+}
+impl<'a> ShaderUniformValue<'a> for &'a [[f32;2]] {
+    fn to_uniform(&self) -> GlShaderUniform<'a> { GlShaderUniform::Array(GlShaderUniformArray::Vec2(*self)) }
+}
+impl<'a> ShaderUniformValue<'a> for &'a [[f32;3]] {
+    fn to_uniform(&self) -> GlShaderUniform<'a> { GlShaderUniform::Array(GlShaderUniformArray::Vec3(*self)) }
+}
+impl<'a> ShaderUniformValue<'a> for &'a [[f32;4]] {
+    fn to_uniform(&self) -> GlShaderUniform<'a> { GlShaderUniform::Array(GlShaderUniformArray::Vec4(*self)) }
+}
+impl<'a> ShaderUniformValue<'a> for &'a [[f32;16]] {
+    fn to_uniform(&self) -> GlShaderUniform<'a> { GlShaderUniform::Array(GlShaderUniformArray::Mat4x4(*self)) }
+}
+impl<'a> ShaderUniformValue<'a> for &'a [i32] {
+    fn to_uniform(&self) -> GlShaderUniform<'a> { GlShaderUniform::Array(GlShaderUniformArray::Int(*self)) }
+}
@@ -0,0 +1,47 @@
+#![cfg(feature = "derive")]
+extern crate glust;
+
+use glust::{GlBuffer, ShaderAttribs, ShaderUniforms};
+
+#[derive(ShaderAttribs)]
+#[allow(dead_code)]
+struct MeshAttribs {
+    position : GlBuffer<[f32;3]>,
+    r#type : GlBuffer<f32>,
+    uv : GlBuffer<[f32;2]>
+}
+
+#[derive(ShaderUniforms)]
+struct PlainUniforms {
+    scale : f32,
+    r#type : i32,
+    tint : [f32;3]
+}
+
+#[derive(ShaderUniforms)]
+struct BorrowedUniforms<'a> {
+    weights : &'a [f32],
+    count : u32
+}
+
+fn debug_uniforms<'a, U : ShaderUniforms<'a>>(uniforms : &U) -> Vec<String> {
+    uniforms.to_uniforms().iter().map(|&(name, ref value)| format!("{} {:?}", name, value)).collect()
+}
+
+#[test]
+fn attrib_names_follow_field_order() {
+    assert_eq!(MeshAttribs::attrib_names(), &["position", "type", "uv"]);
+}
+
+#[test]
+fn uniforms_without_lifetime() {
+    let uniforms = PlainUniforms { scale: 2.0, r#type: 3, tint: [1.0, 0.5, 0.0] };
+    assert_eq!(debug_uniforms(&uniforms), vec!["scale Float(2.0)", "type Int(3)", "tint Vec3([1.0, 0.5, 0.0])"]);
+}
+
+#[test]
+fn uniforms_with_lifetime() {
+    let weights = [0.25, 0.75];
+    let uniforms = BorrowedUniforms { weights: &weights, count: 2 };
+    assert_eq!(debug_uniforms(&uniforms), vec!["weights Array(Float([0.25, 0.75]))", "count UInt(2)"]);
+}